@@ -0,0 +1,405 @@
+//! 命令行工具，与桌面应用共用连接存储和会话逻辑
+
+use std::process::ExitCode;
+use std::time::Instant;
+
+use russh::ChannelMsg;
+use sled::Db;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use ssh_rust_core::bundle::{self, BundleFormat};
+use ssh_rust_core::error::{AppError, AppResult};
+use ssh_rust_core::forward;
+use ssh_rust_core::history::{self, HistorySource};
+use ssh_rust_core::session::Session;
+use ssh_rust_core::sled::{self as store, SshConnection};
+use ssh_rust_core::ssh::connect_session;
+use ssh_rust_core::ssh_config;
+use ssh_rust_core::utils::{inline_data_dir, DATA_DIR_FLAG};
+
+const USAGE: &str = "用法: ssh-rust-cli [--data-dir <目录>] <子命令>
+
+子命令:
+  list [关键字]                         列出连接，提供关键字时模糊搜索
+  connect <名称>                        打开远程 shell（无 PTY，逐行发送标准输入）
+  exec <名称|标签> -- <命令>            在一个连接或带该标签的所有连接上执行命令
+  import-ssh-config [路径]              从 OpenSSH 配置导入连接，默认 ~/.ssh/config
+  export <路径> [--format json|toml] [--include-secrets] [--passphrase <口令>]
+                                        导出全部连接和片段
+  forward <名称> [-L [地址:]端口:主机:端口]...
+                                        建立本地端口转发，未指定 -L 时使用连接中保存的转发
+
+数据库同一时间只能被一个进程打开，桌面应用运行时请先退出，或用 --data-dir 指定其他数据目录";
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = cli_args();
+    let db = match store::open_db() {
+        Ok(db) => db,
+        Err(e) => {
+            eprintln!("打开数据库失败: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = run(&db, args).await;
+    if let Err(e) = db.flush() {
+        eprintln!("刷新数据库失败: {}", e);
+    }
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// 去掉由 utils::data_dir 处理的 --data-dir 参数
+fn cli_args() -> Vec<String> {
+    let mut args = Vec::new();
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == DATA_DIR_FLAG {
+            iter.next();
+        } else if inline_data_dir(&arg).is_none() {
+            args.push(arg);
+        }
+    }
+    args
+}
+
+async fn run(db: &Db, args: Vec<String>) -> AppResult<ExitCode> {
+    let Some((command, rest)) = args.split_first() else {
+        println!("{}", USAGE);
+        return Ok(ExitCode::FAILURE);
+    };
+    match command.as_str() {
+        "list" => list(db, rest.first().map(String::as_str)),
+        "connect" => connect(db, required(rest, 0, "连接名称")?).await,
+        "exec" => exec(db, rest).await,
+        "import-ssh-config" => import_ssh_config(db, rest.first().map(String::as_str)),
+        "export" => export(db, rest),
+        "forward" => port_forward(db, rest).await,
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        }
+        other => Err(AppError::invalid_input(format!(
+            "未知子命令: {}\n\n{}",
+            other, USAGE
+        ))),
+    }
+}
+
+fn required<'a>(args: &'a [String], index: usize, name: &str) -> AppResult<&'a str> {
+    args.get(index)
+        .map(String::as_str)
+        .ok_or_else(|| AppError::invalid_input(format!("缺少参数: {}\n\n{}", name, USAGE)))
+}
+
+fn list(db: &Db, query: Option<&str>) -> AppResult<ExitCode> {
+    let connections = match query {
+        Some(query) => store::search_connections(db, query, None)?,
+        None => store::get_all_connections(db)?,
+    };
+    for connection in &connections {
+        let tags = if connection.tags.is_empty() {
+            String::new()
+        } else {
+            format!("  [{}]", connection.tags.join(", "))
+        };
+        println!("{:<24} {}{}", connection.name, endpoint(connection), tags);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn endpoint(connection: &SshConnection) -> String {
+    match connection.username.as_deref() {
+        Some(user) if !user.is_empty() => {
+            format!("{}@{}:{}", user, connection.host, connection.port)
+        }
+        _ => format!("{}:{}", connection.host, connection.port),
+    }
+}
+
+// 先按名称精确匹配，再忽略大小写匹配
+fn find_by_name(db: &Db, name: &str) -> AppResult<SshConnection> {
+    let connections = store::get_all_connections(db)?;
+    connections
+        .iter()
+        .find(|c| c.name == name)
+        .or_else(|| {
+            connections
+                .iter()
+                .find(|c| c.name.eq_ignore_ascii_case(name))
+        })
+        .cloned()
+        .ok_or_else(|| AppError::not_found(format!("未找到名为 {} 的连接", name)))
+}
+
+// 名称匹配时只返回该连接，否则返回带有同名标签的所有连接
+fn find_targets(db: &Db, target: &str) -> AppResult<Vec<SshConnection>> {
+    if let Ok(connection) = find_by_name(db, target) {
+        return Ok(vec![connection]);
+    }
+    let connections: Vec<SshConnection> = store::get_all_connections(db)?
+        .into_iter()
+        .filter(|c| c.tags.iter().any(|t| t.eq_ignore_ascii_case(target)))
+        .collect();
+    if connections.is_empty() {
+        return Err(AppError::not_found(format!(
+            "未找到名称或标签为 {} 的连接",
+            target
+        )));
+    }
+    Ok(connections)
+}
+
+async fn open(db: &Db, connection: &SshConnection) -> AppResult<Session> {
+    let id = connection
+        .id
+        .ok_or_else(|| AppError::invalid_input("连接ID不存在"))?;
+    let user = connection
+        .username
+        .clone()
+        .filter(|u| !u.is_empty())
+        .or_else(|| std::env::var("USER").ok())
+        .ok_or_else(|| AppError::invalid_input(format!("连接 {} 未设置用户名", connection.name)))?;
+    let password = connection.password.clone().unwrap_or_default();
+    let addr = (connection.host.clone(), connection.port);
+    let session = connect_session(id as usize, user, password, addr, db).await?;
+    if let Err(e) = store::record_connected(db, id) {
+        eprintln!("记录连接时间失败: {}", e);
+    }
+    Ok(session)
+}
+
+async fn connect(db: &Db, name: &str) -> AppResult<ExitCode> {
+    let connection = find_by_name(db, name)?;
+    let mut session = open(db, &connection).await?;
+    let mut channel = session.session.channel_open_session().await?;
+    channel.request_shell(true).await?;
+
+    let mut stdin = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    let mut stderr = tokio::io::stderr();
+    let mut stdin_open = true;
+    let mut exit_status = None;
+    loop {
+        tokio::select! {
+            line = stdin.next_line(), if stdin_open => match line? {
+                Some(line) => channel.data(format!("{}\n", line).as_bytes()).await?,
+                None => {
+                    stdin_open = false;
+                    channel.eof().await?;
+                }
+            },
+            msg = channel.wait() => match msg {
+                Some(ChannelMsg::Data { data }) => {
+                    stdout.write_all(&data).await?;
+                    stdout.flush().await?;
+                }
+                Some(ChannelMsg::ExtendedData { data, .. }) => {
+                    stderr.write_all(&data).await?;
+                    stderr.flush().await?;
+                }
+                Some(ChannelMsg::ExitStatus { exit_status: status }) => exit_status = Some(status),
+                Some(_) => {}
+                None => break,
+            },
+        }
+    }
+    session.close().await?;
+    Ok(exit_code(exit_status))
+}
+
+fn exit_code(status: Option<u32>) -> ExitCode {
+    match status {
+        Some(0) | None => ExitCode::SUCCESS,
+        Some(status) => ExitCode::from(u8::try_from(status).unwrap_or(u8::MAX)),
+    }
+}
+
+async fn exec(db: &Db, args: &[String]) -> AppResult<ExitCode> {
+    let target = required(args, 0, "连接名称或标签")?;
+    let command = match args.iter().position(|a| a == "--") {
+        Some(index) => args[index + 1..].join(" "),
+        None => args[1..].join(" "),
+    };
+    if command.trim().is_empty() {
+        return Err(AppError::invalid_input(format!(
+            "缺少要执行的命令\n\n{}",
+            USAGE
+        )));
+    }
+
+    let targets = find_targets(db, target)?;
+    let results = futures::future::join_all(
+        targets
+            .iter()
+            .map(|connection| exec_one(db, connection, &command)),
+    )
+    .await;
+
+    let prefix = targets.len() > 1;
+    let mut failed = false;
+    for (connection, result) in targets.iter().zip(results) {
+        match result {
+            Ok((output, status)) => {
+                for line in output.lines() {
+                    if prefix {
+                        println!("[{}] {}", connection.name, line);
+                    } else {
+                        println!("{}", line);
+                    }
+                }
+                if let Some(status) = status.filter(|s| *s != 0) {
+                    eprintln!("[{}] 退出状态: {}", connection.name, status);
+                    failed = true;
+                }
+            }
+            Err(e) => {
+                eprintln!("[{}] {}", connection.name, e);
+                failed = true;
+            }
+        }
+    }
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+async fn exec_one(
+    db: &Db,
+    connection: &SshConnection,
+    command: &str,
+) -> AppResult<(String, Option<u32>)> {
+    let mut session = open(db, connection).await?;
+    let started = Instant::now();
+    let result = session.call_with_status(command).await;
+    if let (Ok((_, status)), Some(id)) = (&result, connection.id) {
+        history::record(
+            db,
+            id,
+            command,
+            HistorySource::Query,
+            *status,
+            started.elapsed().as_millis() as u64,
+        );
+    }
+    let _ = session.close().await;
+    result
+}
+
+fn import_ssh_config(db: &Db, path: Option<&str>) -> AppResult<ExitCode> {
+    let report = ssh_config::import_ssh_config(db, path)?;
+    println!(
+        "从 {} 导入 {} 个连接，跳过 {} 个同名连接",
+        report.path,
+        report.imported.len(),
+        report.skipped.len()
+    );
+    for name in &report.imported {
+        println!("  + {}", name);
+    }
+    for name in &report.skipped {
+        println!("  = {}", name);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn export(db: &Db, args: &[String]) -> AppResult<ExitCode> {
+    let path = required(args, 0, "导出路径")?;
+    let mut format = if path.ends_with(".toml") {
+        BundleFormat::Toml
+    } else {
+        BundleFormat::Json
+    };
+    let mut include_secrets = false;
+    let mut passphrase = None;
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--format" => {
+                format = match options.next().map(String::as_str) {
+                    Some("json") => BundleFormat::Json,
+                    Some("toml") => BundleFormat::Toml,
+                    other => {
+                        return Err(AppError::invalid_input(format!(
+                            "不支持的导出格式: {}",
+                            other.unwrap_or("")
+                        )))
+                    }
+                }
+            }
+            "--include-secrets" => include_secrets = true,
+            "--passphrase" => {
+                let value = options
+                    .next()
+                    .ok_or_else(|| AppError::invalid_input("--passphrase 缺少口令"))?;
+                passphrase = Some(value.clone());
+            }
+            other => return Err(AppError::invalid_input(format!("未知选项: {}", other))),
+        }
+    }
+    let report = bundle::export_bundle(db, path, format, None, include_secrets, passphrase)?;
+    println!(
+        "已导出 {} 个连接和 {} 个片段到 {}{}",
+        report.exported,
+        report.snippets,
+        report.path,
+        if report.encrypted {
+            "（密码已加密）"
+        } else {
+            ""
+        }
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn port_forward(db: &Db, args: &[String]) -> AppResult<ExitCode> {
+    let connection = find_by_name(db, required(args, 0, "连接名称")?)?;
+    let mut forwards = Vec::new();
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        let spec = match option.strip_prefix("-L") {
+            Some("") => options
+                .next()
+                .ok_or_else(|| AppError::invalid_input("-L 缺少转发规则"))?
+                .as_str(),
+            Some(spec) => spec,
+            None => return Err(AppError::invalid_input(format!("未知选项: {}", option))),
+        };
+        let forward = ssh_config::parse_local_forward(spec)
+            .ok_or_else(|| AppError::invalid_input(format!("无效的转发规则: {}", spec)))?;
+        forwards.push(forward);
+    }
+    if forwards.is_empty() {
+        forwards = connection.local_forwards.clone();
+    }
+    if forwards.is_empty() {
+        return Err(AppError::invalid_input(format!(
+            "连接 {} 没有保存端口转发，请使用 -L 指定",
+            connection.name
+        )));
+    }
+
+    let session = open(db, &connection).await?;
+    for rule in &forwards {
+        println!(
+            "{}:{} -> {}:{}",
+            rule.bind_address.as_deref().unwrap_or("127.0.0.1"),
+            rule.bind_port,
+            rule.remote_host,
+            rule.remote_port
+        );
+    }
+    println!("断线后自动重连并恢复转发，按 Ctrl-C 结束");
+    forward::keep_local_forwards(session, &forwards, async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await?;
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,366 @@
+use std::borrow::Cow;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use russh::keys::key;
+use russh::{cipher, kex, mac, Preferred};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+
+const SSH_MSG_KEXINIT: u8 = 20;
+/// RFC 4253 要求实现至少支持 35000 字节的数据包
+const MAX_PACKET_LEN: usize = 35000;
+// 服务器在 KEXINIT 之前发送的内容超过这个长度就不再记录
+const MAX_CAPTURE_LEN: usize = 64 * 1024;
+
+/// 按优先级排列的算法列表，为空时使用 russh 的默认顺序
+#[derive(Serialize, Deserialize, Debug, Clone, Default, TS)]
+#[serde(default)]
+pub struct AlgorithmPreferences {
+    pub kex: Vec<String>,
+    pub host_key: Vec<String>,
+    pub cipher: Vec<String>,
+    pub mac: Vec<String>,
+}
+
+/// 会话握手时与服务器协商出的算法
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct NegotiatedAlgorithms {
+    pub server_banner: String,
+    pub kex: String,
+    pub host_key: String,
+    pub cipher_client_to_server: String,
+    pub cipher_server_to_client: String,
+    // AEAD 加密算法自带完整性校验，此时不协商 MAC
+    pub mac_client_to_server: Option<String>,
+    pub mac_server_to_client: Option<String>,
+}
+
+impl AlgorithmPreferences {
+    /// 转换为 russh 的 Preferred，名称无法识别时报错
+    pub fn to_preferred(&self) -> AppResult<Preferred> {
+        let mut preferred = Preferred::default();
+        if !self.kex.is_empty() {
+            let mut names = resolve(&self.kex, kex::ALL_KEX_ALGORITHMS, "KEX")?;
+            // 扩展协商与 strict kex 标记不是真正的算法，用户列表中不需要填写
+            for marker in [
+                kex::EXTENSION_SUPPORT_AS_CLIENT,
+                kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT,
+            ] {
+                if !names.contains(&marker) {
+                    names.push(marker);
+                }
+            }
+            preferred.kex = Cow::Owned(names);
+        }
+        if !self.host_key.is_empty() {
+            preferred.key = Cow::Owned(resolve(&self.host_key, key::ALL_KEY_TYPES, "主机密钥")?);
+        }
+        if !self.cipher.is_empty() {
+            preferred.cipher = Cow::Owned(resolve(&self.cipher, cipher::ALL_CIPHERS, "加密")?);
+        }
+        if !self.mac.is_empty() {
+            preferred.mac = Cow::Owned(resolve(&self.mac, mac::ALL_MAC_ALGORITHMS, "MAC")?);
+        }
+        Ok(preferred)
+    }
+}
+
+fn resolve<N: AsRef<str> + Copy>(
+    names: &[String],
+    all: &[&N],
+    category: &str,
+) -> AppResult<Vec<N>> {
+    names
+        .iter()
+        .map(|name| {
+            all.iter()
+                .find(|known| known.as_ref() == name.trim())
+                .map(|known| **known)
+                .ok_or_else(|| {
+                    AppError::invalid_input(format!("不支持的{}算法: {}", category, name))
+                })
+        })
+        .collect()
+}
+
+impl NegotiatedAlgorithms {
+    /// 由本次握手收到的服务器 KEXINIT 与本端使用的算法列表，按 RFC 4253 的规则
+    /// （取客户端列表中第一个服务器也支持的算法）得出双方协商结果，
+    /// 主机密钥算法直接使用服务器实际出示的密钥
+    pub(crate) fn from_handshake(
+        preferences: &AlgorithmPreferences,
+        server_banner: String,
+        kexinit: &[u8],
+        host_key: Option<String>,
+    ) -> AppResult<Self> {
+        let preferred = preferences.to_preferred()?;
+        let server = parse_kexinit(kexinit)?;
+
+        let host_key = match host_key {
+            Some(host_key) => host_key,
+            None => negotiate("主机密钥", &names(&preferred.key), &server[1])?,
+        };
+        let cipher_c2s = negotiate("加密", &names(&preferred.cipher), &server[2])?;
+        let cipher_s2c = negotiate("加密", &names(&preferred.cipher), &server[3])?;
+        let mac_c2s = negotiate_mac(&cipher_c2s, &names(&preferred.mac), &server[4])?;
+        let mac_s2c = negotiate_mac(&cipher_s2c, &names(&preferred.mac), &server[5])?;
+        Ok(Self {
+            server_banner,
+            kex: negotiate("KEX", &names(&preferred.kex), &server[0])?,
+            host_key,
+            cipher_client_to_server: cipher_c2s,
+            cipher_server_to_client: cipher_s2c,
+            mac_client_to_server: mac_c2s,
+            mac_server_to_client: mac_s2c,
+        })
+    }
+}
+
+/// 包装会话使用的底层连接，记录服务器发来的版本行与第一个数据包。
+/// 第一个数据包就是服务器的 KEXINIT，此时还没有加密
+pub(crate) struct KexTap<S> {
+    inner: S,
+    capture: Arc<Mutex<KexCapture>>,
+}
+
+#[derive(Default)]
+pub(crate) struct KexCapture {
+    buffer: Vec<u8>,
+    result: Option<AppResult<(String, Vec<u8>)>>,
+}
+
+impl<S> KexTap<S> {
+    pub(crate) fn new(inner: S) -> (Self, Arc<Mutex<KexCapture>>) {
+        let capture = Arc::new(Mutex::new(KexCapture::default()));
+        let tap = Self {
+            inner,
+            capture: capture.clone(),
+        };
+        (tap, capture)
+    }
+}
+
+impl KexCapture {
+    fn record(&mut self, data: &[u8]) {
+        if self.result.is_some() || data.is_empty() {
+            return;
+        }
+        self.buffer.extend_from_slice(data);
+        match first_packet(&self.buffer) {
+            Ok(Some(packet)) => self.result = Some(Ok(packet)),
+            Ok(None) if self.buffer.len() <= MAX_CAPTURE_LEN => return,
+            Ok(None) => self.result = Some(Err(AppError::parse("服务器没有发送 KEXINIT"))),
+            Err(e) => self.result = Some(Err(e)),
+        }
+        self.buffer = Vec::new();
+    }
+
+    /// 取出记录到的版本行与 KEXINIT 负载，握手完成后调用
+    pub(crate) fn take(&mut self) -> AppResult<(String, Vec<u8>)> {
+        self.result
+            .take()
+            .unwrap_or_else(|| Err(AppError::parse("没有记录到服务器的 KEXINIT")))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for KexTap<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            if let Ok(mut capture) = self.capture.lock() {
+                capture.record(&buf.filled()[before..]);
+            }
+        }
+        poll
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for KexTap<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+// 从已收到的数据中解析版本行与第一个数据包的负载，数据还不完整时返回 None
+fn first_packet(data: &[u8]) -> AppResult<Option<(String, Vec<u8>)>> {
+    // 版本行之前服务器可能会发送其他文本
+    let mut rest = data;
+    let server_banner = loop {
+        let Some(end) = rest.iter().position(|&b| b == b'\n') else {
+            return Ok(None);
+        };
+        let line = String::from_utf8_lossy(&rest[..end]).trim_end().to_string();
+        rest = &rest[end + 1..];
+        if line.starts_with("SSH-") {
+            break line;
+        }
+    };
+
+    let Some(len_bytes) = rest.get(..4).and_then(|b| <[u8; 4]>::try_from(b).ok()) else {
+        return Ok(None);
+    };
+    let packet_len = u32::from_be_bytes(len_bytes) as usize;
+    if !(5..=MAX_PACKET_LEN).contains(&packet_len) {
+        return Err(AppError::parse(format!("无效的数据包长度: {}", packet_len)));
+    }
+    let Some(packet) = rest.get(4..4 + packet_len) else {
+        return Ok(None);
+    };
+    let padding_len = packet[0] as usize;
+    let payload = packet
+        .get(1..packet_len.saturating_sub(padding_len))
+        .ok_or_else(|| AppError::parse("无效的数据包填充长度"))?;
+    Ok(Some((server_banner, payload.to_vec())))
+}
+
+fn names<N: AsRef<str>>(list: &[N]) -> Vec<String> {
+    list.iter()
+        .map(|name| name.as_ref().to_string())
+        .filter(|name| !name.starts_with("ext-info-") && !name.starts_with("kex-strict-"))
+        .collect()
+}
+
+fn negotiate(category: &str, client: &[String], server: &[String]) -> AppResult<String> {
+    client
+        .iter()
+        .find(|name| server.contains(name))
+        .cloned()
+        .ok_or_else(|| {
+            AppError::invalid_input(format!("没有双方都支持的{}算法", category))
+                .with_details(format!("服务器支持: {}", server.join(",")))
+        })
+}
+
+fn negotiate_mac(cipher: &str, client: &[String], server: &[String]) -> AppResult<Option<String>> {
+    if cipher.starts_with("chacha20-poly1305") || cipher.contains("-gcm") {
+        return Ok(None);
+    }
+    negotiate("MAC", client, server).map(Some)
+}
+
+// KEXINIT 负载：消息号、16 字节 cookie，之后是 10 个逗号分隔的名称列表
+fn parse_kexinit(payload: &[u8]) -> AppResult<Vec<Vec<String>>> {
+    if payload.first() != Some(&SSH_MSG_KEXINIT) || payload.len() < 17 {
+        return Err(AppError::parse("服务器没有发送 KEXINIT"));
+    }
+    let mut rest = &payload[17..];
+    let mut lists = Vec::with_capacity(10);
+    for _ in 0..10 {
+        let len_bytes: [u8; 4] = rest
+            .get(..4)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| AppError::parse("KEXINIT 数据不完整"))?;
+        let len = u32::from_be_bytes(len_bytes) as usize;
+        let list = rest
+            .get(4..4 + len)
+            .ok_or_else(|| AppError::parse("KEXINIT 数据不完整"))?;
+        lists.push(
+            String::from_utf8_lossy(list)
+                .split(',')
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect(),
+        );
+        rest = &rest[4 + len..];
+    }
+    Ok(lists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kexinit(lists: [&str; 10]) -> Vec<u8> {
+        let mut payload = vec![SSH_MSG_KEXINIT];
+        payload.extend_from_slice(&[0; 16]);
+        for list in lists {
+            payload.extend_from_slice(&(list.len() as u32).to_be_bytes());
+            payload.extend_from_slice(list.as_bytes());
+        }
+        payload.extend_from_slice(&[0; 5]);
+        payload
+    }
+
+    fn packet(payload: &[u8]) -> Vec<u8> {
+        let padding = 8 - (payload.len() + 5) % 8 + 4;
+        let mut packet = ((payload.len() + padding + 1) as u32)
+            .to_be_bytes()
+            .to_vec();
+        packet.push(padding as u8);
+        packet.extend_from_slice(payload);
+        packet.resize(packet.len() + padding, 0);
+        packet
+    }
+
+    #[test]
+    fn captures_kexinit_split_across_reads() {
+        let payload = kexinit([
+            "curve25519-sha256,diffie-hellman-group14-sha256",
+            "ssh-ed25519",
+            "aes256-ctr,chacha20-poly1305@openssh.com",
+            "chacha20-poly1305@openssh.com",
+            "hmac-sha2-256",
+            "hmac-sha2-256",
+            "none",
+            "none",
+            "",
+            "",
+        ]);
+        let mut data = b"welcome\r\nSSH-2.0-OpenSSH_9.6\r\n".to_vec();
+        data.extend(packet(&payload));
+
+        let mut capture = KexCapture::default();
+        for chunk in data.chunks(7) {
+            capture.record(chunk);
+        }
+        let (banner, kexinit) = capture.take().unwrap();
+        assert_eq!(banner, "SSH-2.0-OpenSSH_9.6");
+        assert_eq!(kexinit, payload);
+
+        let preferences = AlgorithmPreferences {
+            cipher: vec!["aes256-ctr".into(), "chacha20-poly1305@openssh.com".into()],
+            ..Default::default()
+        };
+        let negotiated = NegotiatedAlgorithms::from_handshake(
+            &preferences,
+            banner,
+            &kexinit,
+            Some("ssh-ed25519".into()),
+        )
+        .unwrap();
+        assert_eq!(negotiated.kex, "curve25519-sha256");
+        assert_eq!(negotiated.cipher_client_to_server, "aes256-ctr");
+        assert_eq!(
+            negotiated.mac_client_to_server.as_deref(),
+            Some("hmac-sha2-256")
+        );
+        assert_eq!(
+            negotiated.cipher_server_to_client,
+            "chacha20-poly1305@openssh.com"
+        );
+        assert_eq!(negotiated.mac_server_to_client, None);
+    }
+}
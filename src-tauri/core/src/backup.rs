@@ -0,0 +1,391 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::history::HISTORY_TREE;
+use crate::migrations::{self, SCHEMA_VERSION};
+use crate::sled::{current_timestamp, SshConnection};
+use crate::utils::data_dir;
+
+/// 快照文件格式版本
+const SNAPSHOT_VERSION: u32 = 1;
+/// 最多保留的快照数量，超出后删除最旧的
+const MAX_SNAPSHOTS: usize = 20;
+const SNAPSHOT_PREFIX: &str = "connections-";
+const SNAPSHOT_EXTENSION: &str = ".json";
+/// sled 默认 tree 的名称，即连接记录所在的 tree
+const DEFAULT_TREE: &str = "__sled__default";
+/// 不写入快照的 tree。命令历史清空后不应再从快照中恢复，恢复时也保留本机现有的历史
+const EXCLUDED_TREES: &[&str] = &[HISTORY_TREE];
+/// 定时快照间隔
+pub const DAILY_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 数据库中除 EXCLUDED_TREES 外所有 tree 的快照，键值均为 base64
+#[derive(Serialize, Deserialize, Debug)]
+struct Snapshot {
+    version: u32,
+    created_at: u64,
+    schema_version: u64,
+    trees: BTreeMap<String, Vec<(String, String)>>,
+}
+
+#[derive(Serialize, Debug, TS)]
+pub struct BackupInfo {
+    pub name: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    #[ts(type = "number | null")]
+    pub created_at: Option<u64>,
+}
+
+#[derive(Serialize, Debug, TS)]
+pub struct RestoreReport {
+    pub restored_from: String,
+    pub connections: usize,
+    // 恢复前自动生成的快照，便于撤销
+    pub previous_snapshot: String,
+}
+
+fn backup_dir() -> AppResult<PathBuf> {
+    Ok(data_dir()?.join("backups"))
+}
+
+/// 把数据库导出为一个快照文件，并清理多余的旧快照
+pub fn create_snapshot(db: &Db) -> AppResult<String> {
+    create_snapshot_in(db, &backup_dir()?)
+}
+
+fn create_snapshot_in(db: &Db, dir: &Path) -> AppResult<String> {
+    let mut trees = BTreeMap::new();
+    for name in db.tree_names() {
+        let name = String::from_utf8_lossy(&name).to_string();
+        if EXCLUDED_TREES.contains(&name.as_str()) {
+            continue;
+        }
+        let tree = open_tree(db, &name)?;
+        let mut entries = Vec::new();
+        for item in tree.iter() {
+            let (key, value) = item?;
+            entries.push((STANDARD.encode(key), STANDARD.encode(value)));
+        }
+        trees.insert(name, entries);
+    }
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        created_at: current_timestamp(),
+        schema_version: migrations::schema_version(db)?,
+        trees,
+    };
+
+    fs::create_dir_all(dir)?;
+    let mut name = format!(
+        "{}{}{}",
+        SNAPSHOT_PREFIX, snapshot.created_at, SNAPSHOT_EXTENSION
+    );
+    // 同一秒内多次变更时追加序号，避免覆盖
+    let mut index = 1;
+    while dir.join(&name).exists() {
+        name = format!(
+            "{}{}-{}{}",
+            SNAPSHOT_PREFIX, snapshot.created_at, index, SNAPSHOT_EXTENSION
+        );
+        index += 1;
+    }
+    let content = serde_json::to_vec(&snapshot)?;
+    // 先写临时文件再重命名，避免写到一半的快照被当作可用备份
+    let tmp_path = dir.join(format!("{}.tmp", name));
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, dir.join(&name))?;
+
+    rotate_snapshots(dir)?;
+    Ok(name)
+}
+
+/// 数据变更后调用，失败只记录日志，不影响原操作
+pub fn snapshot_after_change(db: &Db) {
+    if let Err(e) = create_snapshot(db) {
+        eprintln!("创建数据库快照失败: {}", e);
+    }
+}
+
+/// 每天创建一次快照，在应用启动时放到后台运行
+pub async fn run_daily_snapshots(db: Db) {
+    let mut interval = tokio::time::interval(DAILY_INTERVAL);
+    loop {
+        interval.tick().await;
+        snapshot_after_change(&db);
+    }
+}
+
+pub fn list_backups() -> AppResult<Vec<BackupInfo>> {
+    list_backups_in(&backup_dir()?)
+}
+
+fn list_backups_in(dir: &Path) -> AppResult<Vec<BackupInfo>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !is_snapshot_name(&name) {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        backups.push((
+            snapshot_order(&name),
+            BackupInfo {
+                created_at: snapshot_order(&name).map(|(timestamp, _)| timestamp),
+                name,
+                size,
+            },
+        ));
+    }
+    // 按时间戳和序号从新到旧排列，按文件名比较时 -10 会排在 -9 之前
+    backups.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.name.cmp(&a.1.name)));
+    Ok(backups.into_iter().map(|(_, backup)| backup).collect())
+}
+
+/// 校验快照后替换当前数据，替换前会先为当前数据生成一份快照
+pub fn restore_backup(db: &Db, name: &str) -> AppResult<RestoreReport> {
+    restore_backup_in(db, &backup_dir()?, name)
+}
+
+fn restore_backup_in(db: &Db, dir: &Path, name: &str) -> AppResult<RestoreReport> {
+    if !is_snapshot_name(name) || name.contains(&['/', '\\'][..]) {
+        return Err(AppError::invalid_input(format!("无效的备份名称: {}", name)));
+    }
+    let content = fs::read(dir.join(name))?;
+    let snapshot = validate_snapshot(&content)?;
+    let connections = snapshot.trees.get(DEFAULT_TREE).map_or(0, |t| t.len());
+
+    let previous_snapshot = create_snapshot_in(db, dir)?;
+
+    for name in db.tree_names() {
+        let name = String::from_utf8_lossy(&name).to_string();
+        if EXCLUDED_TREES.contains(&name.as_str()) {
+            continue;
+        }
+        if name == DEFAULT_TREE {
+            db.clear()?;
+        } else {
+            db.drop_tree(name.as_bytes())?;
+        }
+    }
+    // 旧版本生成的快照可能包含这些 tree，同样跳过
+    for (name, entries) in &snapshot.trees {
+        if EXCLUDED_TREES.contains(&name.as_str()) {
+            continue;
+        }
+        let tree = open_tree(db, name)?;
+        for (key, value) in entries {
+            let key = STANDARD.decode(key)?;
+            let value = STANDARD.decode(value)?;
+            tree.insert(key, value)?;
+        }
+    }
+    // 旧快照的 schema 可能落后于当前版本
+    migrations::run_migrations(db)?;
+    db.flush()?;
+
+    Ok(RestoreReport {
+        restored_from: name.to_string(),
+        connections,
+        previous_snapshot,
+    })
+}
+
+// 恢复前完整校验：格式版本、schema 版本、编码以及每条连接记录都必须可解析
+fn validate_snapshot(content: &[u8]) -> AppResult<Snapshot> {
+    let snapshot: Snapshot = serde_json::from_slice(content)
+        .map_err(|e| AppError::parse("快照文件无法解析").with_details(e))?;
+    if snapshot.version > SNAPSHOT_VERSION {
+        return Err(AppError::invalid_input(format!(
+            "不支持的快照版本: {}",
+            snapshot.version
+        )));
+    }
+    if snapshot.schema_version > SCHEMA_VERSION {
+        return Err(AppError::invalid_input(format!(
+            "快照 schema 版本 {} 高于当前程序支持的版本 {}",
+            snapshot.schema_version, SCHEMA_VERSION
+        )));
+    }
+    for (name, entries) in &snapshot.trees {
+        for (key, value) in entries {
+            STANDARD.decode(key).map_err(|e| {
+                AppError::parse(format!("快照中 {} 的键编码错误", name)).with_details(e)
+            })?;
+            let value = STANDARD.decode(value).map_err(|e| {
+                AppError::parse(format!("快照中 {} 的值编码错误", name)).with_details(e)
+            })?;
+            if name == DEFAULT_TREE {
+                serde_json::from_slice::<SshConnection>(&value)
+                    .map_err(|e| AppError::parse("快照中的连接记录无法解析").with_details(e))?;
+            }
+        }
+    }
+    Ok(snapshot)
+}
+
+fn open_tree(db: &Db, name: &str) -> AppResult<Tree> {
+    if name == DEFAULT_TREE {
+        Ok(db.deref().clone())
+    } else {
+        Ok(db.open_tree(name)?)
+    }
+}
+
+// 依赖 list_backups 从新到旧的顺序
+fn rotate_snapshots(dir: &Path) -> AppResult<()> {
+    let backups = list_backups_in(dir)?;
+    for backup in backups.iter().skip(MAX_SNAPSHOTS) {
+        fs::remove_file(dir.join(&backup.name))?;
+    }
+    Ok(())
+}
+
+fn is_snapshot_name(name: &str) -> bool {
+    name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(SNAPSHOT_EXTENSION)
+}
+
+// 从 connections-T.json 或 connections-T-N.json 中解析时间戳与序号，前者序号为 0
+fn snapshot_order(name: &str) -> Option<(u64, u64)> {
+    let stem = name
+        .strip_prefix(SNAPSHOT_PREFIX)?
+        .strip_suffix(SNAPSHOT_EXTENSION)?;
+    match stem.split_once('-') {
+        Some((timestamp, index)) => Some((timestamp.parse().ok()?, index.parse().ok()?)),
+        None => Some((stem.parse().ok()?, 0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_order_compares_numeric_index() {
+        assert_eq!(snapshot_order("connections-100.json"), Some((100, 0)));
+        assert_eq!(snapshot_order("connections-100-9.json"), Some((100, 9)));
+        assert!(
+            snapshot_order("connections-100-10.json") > snapshot_order("connections-100-9.json")
+        );
+        assert!(snapshot_order("connections-101.json") > snapshot_order("connections-100-10.json"));
+        assert_eq!(snapshot_order("connections-abc.json"), None);
+    }
+
+    fn temp_db() -> Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    fn connection(name: &str) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "id": 1,
+            "name": name,
+            "host": "10.0.0.5",
+            "port": 22,
+            "username": "root",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn restores_snapshot_and_keeps_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = temp_db();
+        migrations::run_migrations(&db).unwrap();
+        db.insert("1", connection("web")).unwrap();
+        db.open_tree("settings")
+            .unwrap()
+            .insert("theme", "dark")
+            .unwrap();
+        let snapshot = create_snapshot_in(&db, dir.path()).unwrap();
+
+        db.insert("1", connection("changed")).unwrap();
+        db.insert("2", connection("added")).unwrap();
+        db.open_tree("settings")
+            .unwrap()
+            .insert("lang", "en")
+            .unwrap();
+        let history = db.open_tree(HISTORY_TREE).unwrap();
+        history.insert("entry", "uptime").unwrap();
+
+        let report = restore_backup_in(&db, dir.path(), &snapshot).unwrap();
+        assert_eq!(report.restored_from, snapshot);
+        assert_eq!(report.connections, 1);
+        assert!(dir.path().join(&report.previous_snapshot).exists());
+
+        let restored: SshConnection =
+            serde_json::from_slice(&db.get("1").unwrap().unwrap()).unwrap();
+        assert_eq!(restored.name, "web");
+        assert!(db.get("2").unwrap().is_none());
+        let settings = db.open_tree("settings").unwrap();
+        assert!(settings.get("theme").unwrap().is_some());
+        assert!(settings.get("lang").unwrap().is_none());
+        // 命令历史不写入快照，恢复时也不会被清空
+        assert!(history.get("entry").unwrap().is_some());
+    }
+
+    #[test]
+    fn rejects_invalid_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = temp_db();
+        db.insert("1", connection("web")).unwrap();
+        let name = "connections-100.json";
+        let snapshot = Snapshot {
+            version: SNAPSHOT_VERSION,
+            created_at: 100,
+            schema_version: SCHEMA_VERSION,
+            trees: BTreeMap::from([(
+                DEFAULT_TREE.to_string(),
+                vec![(STANDARD.encode("2"), STANDARD.encode("not json"))],
+            )]),
+        };
+        fs::write(
+            dir.path().join(name),
+            serde_json::to_vec(&snapshot).unwrap(),
+        )
+        .unwrap();
+
+        assert!(restore_backup_in(&db, dir.path(), name).is_err());
+        assert!(restore_backup_in(&db, dir.path(), "connections-/../connections-1.json").is_err());
+        // 校验失败时不改动当前数据，也不生成快照
+        assert!(db.get("1").unwrap().is_some());
+        assert_eq!(list_backups_in(dir.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rotation_keeps_newest_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        for index in 0..MAX_SNAPSHOTS + 2 {
+            let name = format!("connections-100-{}.json", index + 1);
+            fs::write(dir.path().join(name), "{}").unwrap();
+        }
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        rotate_snapshots(dir.path()).unwrap();
+        let names: Vec<String> = list_backups_in(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|backup| backup.name)
+            .collect();
+        assert_eq!(names.len(), MAX_SNAPSHOTS);
+        assert_eq!(
+            names[0],
+            format!("connections-100-{}.json", MAX_SNAPSHOTS + 2)
+        );
+        assert_eq!(names[MAX_SNAPSHOTS - 1], "connections-100-3.json");
+        assert!(dir.path().join("notes.txt").exists());
+    }
+}
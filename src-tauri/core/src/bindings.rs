@@ -0,0 +1,152 @@
+use ts_rs::TS;
+
+use crate::algorithms::{AlgorithmPreferences, NegotiatedAlgorithms};
+use crate::backup::{BackupInfo, RestoreReport};
+use crate::bundle::{BundleFormat, ExportReport, ImportReport, MergeStrategy};
+use crate::certificate::CertificateInfo;
+use crate::containers::{Container, ContainerAction, ContainerRuntime, ContainerStats};
+use crate::error::ErrorKind;
+use crate::events::{
+    CertificateExpiring, ConnectionOpened, ConnectionState, ConnectionStateUpdate, CpuUpdate,
+    CpuUsage, DiskTotals, DiskUpdate, DiskUsage, LogBatch, MemoryUpdate, MemoryUsage,
+    NetworkUpdate, NetworkUsage, ReplayFrame, ServerInfo, ShellClosed, ShellOutput, StreamClosed,
+    StreamOutput, UptimeUpdate,
+};
+use crate::files::{BackupTarget, FileSaved, FileVersion, RemoteFile};
+use crate::history::{HistoryEntry, HistoryMatch, HistoryQuery, HistorySource};
+use crate::hostkeys::HostKeyInfo;
+use crate::logs::{LogLine, LogSource, LogTailOptions, Severity};
+use crate::migrations::QuarantinedRecord;
+use crate::platform::{HostProfile, InitSystem, OsFamily};
+use crate::recording::{CastHeader, RecordingInfo};
+use crate::settings::{HistorySettings, Settings, TimeoutOverrides, Timeouts};
+use crate::shell::ShellOpened;
+use crate::sled::{DbOperation, LocalForward, SshConnection};
+use crate::snippets::{Placeholder, PlaceholderKind, Snippet, SnippetRunResult};
+use crate::ssh::SshCommand;
+use crate::ssh_config::{SshConfigExportReport, SshConfigImportReport, SshConfigTarget};
+use crate::streams::StreamOpened;
+use crate::systemd::{JournalEntry, SystemdUnit, UnitAction, UnitStatus};
+
+/// 生成的绑定文件，相对于 src-tauri/core 目录
+pub const BINDINGS_PATH: &str = "../../src/types/bindings.ts";
+
+const HEADER: &str =
+    "// 此文件由 src-tauri/core/src/bindings.rs 根据 Rust 类型生成，请勿手动修改。\n\
+// 修改 Rust 类型后运行 `UPDATE_BINDINGS=1 cargo test` 重新生成。\n";
+
+/// 渲染所有命令、参数、响应与事件类型的 TypeScript 声明
+pub fn render() -> String {
+    let declarations = [
+        // 命令参数
+        SshCommand::decl(),
+        DbOperation::decl(),
+        SshConnection::decl(),
+        LocalForward::decl(),
+        TimeoutOverrides::decl(),
+        AlgorithmPreferences::decl(),
+        Settings::decl(),
+        Timeouts::decl(),
+        HistorySettings::decl(),
+        HistoryQuery::decl(),
+        HistoryMatch::decl(),
+        Snippet::decl(),
+        Placeholder::decl(),
+        PlaceholderKind::decl(),
+        BundleFormat::decl(),
+        MergeStrategy::decl(),
+        SshConfigTarget::decl(),
+        UnitAction::decl(),
+        ContainerRuntime::decl(),
+        ContainerAction::decl(),
+        LogTailOptions::decl(),
+        LogSource::decl(),
+        FileVersion::decl(),
+        BackupTarget::decl(),
+        // 响应
+        ErrorKind::decl(),
+        ConnectionOpened::decl(),
+        ServerInfo::decl(),
+        DiskTotals::decl(),
+        ExportReport::decl(),
+        ImportReport::decl(),
+        SshConfigExportReport::decl(),
+        SshConfigImportReport::decl(),
+        QuarantinedRecord::decl(),
+        BackupInfo::decl(),
+        RestoreReport::decl(),
+        NegotiatedAlgorithms::decl(),
+        CertificateInfo::decl(),
+        HostKeyInfo::decl(),
+        ShellOpened::decl(),
+        RecordingInfo::decl(),
+        CastHeader::decl(),
+        HistoryEntry::decl(),
+        HistorySource::decl(),
+        SnippetRunResult::decl(),
+        HostProfile::decl(),
+        OsFamily::decl(),
+        InitSystem::decl(),
+        SystemdUnit::decl(),
+        UnitStatus::decl(),
+        JournalEntry::decl(),
+        Container::decl(),
+        ContainerStats::decl(),
+        StreamOpened::decl(),
+        LogLine::decl(),
+        Severity::decl(),
+        RemoteFile::decl(),
+        FileSaved::decl(),
+        // 事件
+        UptimeUpdate::decl(),
+        MemoryUpdate::decl(),
+        MemoryUsage::decl(),
+        CpuUpdate::decl(),
+        CpuUsage::decl(),
+        NetworkUpdate::decl(),
+        NetworkUsage::decl(),
+        DiskUpdate::decl(),
+        DiskUsage::decl(),
+        ConnectionStateUpdate::decl(),
+        ConnectionState::decl(),
+        CertificateExpiring::decl(),
+        ShellOutput::decl(),
+        ShellClosed::decl(),
+        StreamOutput::decl(),
+        StreamClosed::decl(),
+        LogBatch::decl(),
+        ReplayFrame::decl(),
+    ];
+
+    let mut output = String::from(HEADER);
+    for declaration in declarations {
+        output.push_str("\nexport ");
+        output.push_str(&declaration);
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// 绑定文件与 Rust 定义不一致时失败；设置 UPDATE_BINDINGS 环境变量时改为重新生成
+    #[test]
+    fn bindings_are_up_to_date() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(BINDINGS_PATH);
+        let expected = render();
+        if std::env::var_os("UPDATE_BINDINGS").is_some() {
+            std::fs::write(&path, &expected).expect("写入绑定文件失败");
+            return;
+        }
+        let actual = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            actual == expected,
+            "{} 已过期，请运行 `UPDATE_BINDINGS=1 cargo test` 重新生成",
+            path.display()
+        );
+    }
+}
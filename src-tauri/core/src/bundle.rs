@@ -0,0 +1,325 @@
+use std::fs;
+use std::path::Path;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::settings;
+use crate::sled::{current_timestamp, get_all_connections, SshConnection};
+use crate::snippets::{check_snippet, list_snippets, save_snippet, Snippet};
+
+/// 当前导出包格式版本，格式不兼容变更时递增
+pub const BUNDLE_VERSION: u32 = 1;
+
+const PBKDF2_ITERATIONS: u32 = 200_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, TS)]
+pub enum BundleFormat {
+    Json,
+    Toml,
+}
+
+/// 导入时遇到冲突（host + port + username 相同）的处理方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, TS)]
+pub enum MergeStrategy {
+    Skip,
+    Overwrite,
+    Duplicate,
+}
+
+/// 可移植的连接配置包。分组（标签）和端口转发随连接一起保存
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Bundle {
+    pub version: u32,
+    pub exported_at: u64,
+    // 设置后 connections 中的 password 为加密后的 base64 文本
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<BundleEncryption>,
+    #[serde(default)]
+    pub connections: Vec<SshConnection>,
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BundleEncryption {
+    pub kdf: String,
+    pub iterations: u32,
+    pub salt: String,
+}
+
+/// 导入结果，片段按名称（不区分大小写）检测冲突，冲突列表中以“片段 ”开头
+#[derive(Serialize, Debug, Default, TS)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+    pub snippets_imported: usize,
+    pub snippets_overwritten: usize,
+    pub snippets_skipped: usize,
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Serialize, Debug, TS)]
+pub struct ExportReport {
+    pub path: String,
+    pub exported: usize,
+    pub snippets: usize,
+    pub encrypted: bool,
+}
+
+/// 导出连接和全部片段到文件。ids 为 None 或空列表时导出全部连接；
+/// include_secrets 为 false 时不导出密码，提供 passphrase 时密码会被加密
+pub fn export_bundle(
+    db: &Db,
+    path: &str,
+    format: BundleFormat,
+    ids: Option<Vec<u64>>,
+    include_secrets: bool,
+    passphrase: Option<String>,
+) -> AppResult<ExportReport> {
+    let ids = ids.filter(|ids| !ids.is_empty());
+    let mut connections: Vec<SshConnection> = get_all_connections(db)?
+        .into_iter()
+        .filter(|c| match (&ids, c.id) {
+            (Some(ids), Some(id)) => ids.contains(&id),
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .collect();
+
+    let mut encryption = None;
+    if !include_secrets {
+        for connection in connections.iter_mut() {
+            connection.password = None;
+        }
+    } else if let Some(passphrase) = passphrase.filter(|p| !p.is_empty()) {
+        let salt: [u8; 16] = rand::random();
+        let cipher = derive_cipher(&passphrase, &salt, PBKDF2_ITERATIONS)?;
+        for connection in connections.iter_mut() {
+            if let Some(password) = connection.password.take() {
+                connection.password = Some(encrypt_secret(&cipher, &password)?);
+            }
+        }
+        encryption = Some(BundleEncryption {
+            kdf: "pbkdf2-sha256".to_string(),
+            iterations: PBKDF2_ITERATIONS,
+            salt: STANDARD.encode(salt),
+        });
+    }
+
+    let bundle = Bundle {
+        version: BUNDLE_VERSION,
+        exported_at: current_timestamp(),
+        encryption,
+        connections,
+        snippets: list_snippets(db)?,
+    };
+    let content = match format {
+        BundleFormat::Json => serde_json::to_string_pretty(&bundle)?,
+        BundleFormat::Toml => toml::to_string_pretty(&bundle)?,
+    };
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+
+    Ok(ExportReport {
+        path: path.to_string(),
+        exported: bundle.connections.len(),
+        snippets: bundle.snippets.len(),
+        encrypted: bundle.encryption.is_some(),
+    })
+}
+
+/// 从文件导入连接和片段，连接按 host + port + username 检测冲突，片段按名称检测冲突
+pub fn import_bundle(
+    db: &Db,
+    path: &str,
+    strategy: MergeStrategy,
+    passphrase: Option<String>,
+) -> AppResult<ImportReport> {
+    let content = fs::read_to_string(path)?;
+    let bundle = parse_bundle(path, &content)?;
+    if bundle.version > BUNDLE_VERSION {
+        return Err(AppError::invalid_input(format!(
+            "导出包版本 {} 高于当前支持的版本 {}",
+            bundle.version, BUNDLE_VERSION
+        )));
+    }
+
+    let cipher = match &bundle.encryption {
+        Some(encryption) => {
+            let passphrase =
+                passphrase.ok_or_else(|| AppError::invalid_input("导出包已加密，请提供密码"))?;
+            let salt = STANDARD.decode(&encryption.salt)?;
+            Some(derive_cipher(&passphrase, &salt, encryption.iterations)?)
+        }
+        None => None,
+    };
+
+    // 先解密并校验全部内容，任何一项出错都不写入数据库
+    let mut connections = bundle.connections;
+    for connection in &mut connections {
+        if let (Some(cipher), Some(secret)) = (&cipher, connection.password.take()) {
+            connection.password = Some(decrypt_secret(cipher, &secret)?);
+        }
+        settings::validate_overrides(db, &connection.timeouts)
+            .map_err(|e| e.with_details(format!("连接 {}", endpoint_label(connection))))?;
+    }
+    for snippet in &bundle.snippets {
+        check_snippet(snippet)?;
+    }
+
+    let mut existing = get_all_connections(db)?;
+    let mut report = ImportReport::default();
+    let now = current_timestamp();
+
+    for mut connection in connections {
+        connection.last_connected_at = None;
+        connection.connect_count = 0;
+
+        let conflict = existing.iter().position(|c| same_endpoint(c, &connection));
+        match (conflict, strategy) {
+            (Some(index), MergeStrategy::Skip) => {
+                report.conflicts.push(endpoint_label(&existing[index]));
+                report.skipped += 1;
+            }
+            (Some(index), MergeStrategy::Overwrite) => {
+                let current = &existing[index];
+                report.conflicts.push(endpoint_label(current));
+                connection.id = current.id;
+                connection.created_at = current.created_at;
+                connection.last_connected_at = current.last_connected_at;
+                connection.connect_count = current.connect_count;
+                connection.updated_at = Some(now);
+                write_connection(db, &connection)?;
+                existing[index] = connection;
+                report.overwritten += 1;
+            }
+            (conflict, _) => {
+                if let Some(index) = conflict {
+                    report.conflicts.push(endpoint_label(&existing[index]));
+                }
+                connection.id = Some(db.generate_id()?);
+                connection.created_at = Some(now);
+                connection.updated_at = Some(now);
+                write_connection(db, &connection)?;
+                existing.push(connection);
+                report.imported += 1;
+            }
+        }
+    }
+    import_snippets(db, bundle.snippets, strategy, &mut report)?;
+    db.flush()?;
+
+    Ok(report)
+}
+
+fn import_snippets(
+    db: &Db,
+    snippets: Vec<Snippet>,
+    strategy: MergeStrategy,
+    report: &mut ImportReport,
+) -> AppResult<()> {
+    let mut existing = list_snippets(db)?;
+    for mut snippet in snippets {
+        let conflict = existing
+            .iter()
+            .position(|s| s.name.to_lowercase() == snippet.name.to_lowercase());
+        if let Some(index) = conflict {
+            report
+                .conflicts
+                .push(format!("片段 {}", existing[index].name));
+        }
+        match (conflict, strategy) {
+            (Some(_), MergeStrategy::Skip) => report.snippets_skipped += 1,
+            (Some(index), MergeStrategy::Overwrite) => {
+                // 保留本机片段的 ID 和创建时间
+                snippet.id = existing[index].id;
+                existing[index] = save_snippet(db, snippet)?;
+                report.snippets_overwritten += 1;
+            }
+            _ => {
+                snippet.id = None;
+                existing.push(save_snippet(db, snippet)?);
+                report.snippets_imported += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+// 优先按扩展名判断格式，无法判断时依次尝试 JSON 和 TOML
+fn parse_bundle(path: &str, content: &str) -> AppResult<Bundle> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    match extension.as_deref() {
+        Some("json") => Ok(serde_json::from_str(content)?),
+        Some("toml") => Ok(toml::from_str(content)?),
+        _ => serde_json::from_str(content)
+            .or_else(|_| toml::from_str(content))
+            .map_err(|e: toml::de::Error| AppError::parse("无法解析导出包").with_details(e)),
+    }
+}
+
+fn same_endpoint(a: &SshConnection, b: &SshConnection) -> bool {
+    a.host.eq_ignore_ascii_case(&b.host) && a.port == b.port && a.username == b.username
+}
+
+fn endpoint_label(connection: &SshConnection) -> String {
+    format!(
+        "{}@{}:{}",
+        connection.username.as_deref().unwrap_or(""),
+        connection.host,
+        connection.port
+    )
+}
+
+fn write_connection(db: &Db, connection: &SshConnection) -> AppResult<()> {
+    let key = connection
+        .id
+        .ok_or_else(|| AppError::invalid_input("连接ID不存在"))?
+        .to_string();
+    let value = serde_json::to_vec(connection)?;
+    db.insert(key, value)?;
+    Ok(())
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8], iterations: u32) -> AppResult<Aes256Gcm> {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    Aes256Gcm::new_from_slice(&key).map_err(|e| AppError::internal("密钥长度错误").with_details(e))
+}
+
+// 密文格式: base64(nonce || ciphertext)
+fn encrypt_secret(cipher: &Aes256Gcm, secret: &str) -> AppResult<String> {
+    let nonce: [u8; 12] = rand::random();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), secret.as_bytes())
+        .map_err(|_| AppError::internal("加密失败"))?;
+    let mut payload = nonce.to_vec();
+    payload.extend_from_slice(&ciphertext);
+    Ok(STANDARD.encode(payload))
+}
+
+fn decrypt_secret(cipher: &Aes256Gcm, secret: &str) -> AppResult<String> {
+    let payload = STANDARD.decode(secret)?;
+    if payload.len() < 12 {
+        return Err(AppError::parse("密文格式错误"));
+    }
+    let (nonce, ciphertext) = payload.split_at(12);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| AppError::auth("解密失败，密码错误或导出包已损坏"))?;
+    String::from_utf8(plaintext)
+        .map_err(|e| AppError::parse("解密结果不是有效的 UTF-8").with_details(e))
+}
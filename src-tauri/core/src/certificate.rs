@@ -0,0 +1,99 @@
+use std::path::{Path, PathBuf};
+
+use russh::keys::load_openssh_certificate;
+use serde::Serialize;
+use ssh_key::Certificate;
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::sled::{current_timestamp, SshConnection};
+use crate::utils::expand_home;
+
+/// 证书剩余有效期低于该值时提醒用户，单位秒
+pub const EXPIRY_WARNING_SECS: u64 = 15 * 60;
+
+/// 用户证书信息，在连接详情中展示
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct CertificateInfo {
+    pub path: String,
+    pub key_id: String,
+    pub principals: Vec<String>,
+    #[ts(type = "number")]
+    pub valid_after: u64,
+    #[ts(type = "number")]
+    pub valid_before: u64,
+    // 距离过期的秒数，已过期时为负数
+    #[ts(type = "number")]
+    pub expires_in: i64,
+}
+
+impl CertificateInfo {
+    /// 按当前时间计算的剩余有效期
+    pub fn remaining(&self) -> i64 {
+        seconds_until(self.valid_before)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining() <= 0
+    }
+
+    pub fn expires_soon(&self) -> bool {
+        self.remaining() < EXPIRY_WARNING_SECS as i64
+    }
+}
+
+fn seconds_until(timestamp: u64) -> i64 {
+    timestamp.min(i64::MAX as u64) as i64 - current_timestamp() as i64
+}
+
+/// 连接使用的证书路径：优先使用显式配置，否则按 OpenSSH 的约定查找 <私钥>-cert.pub
+pub fn certificate_path(connection: &SshConnection) -> AppResult<Option<PathBuf>> {
+    if let Some(file) = connection
+        .certificate_file
+        .as_deref()
+        .filter(|f| !f.is_empty())
+    {
+        return expand_home(file).map(Some);
+    }
+    let Some(identity_file) = connection
+        .identity_file
+        .as_deref()
+        .filter(|f| !f.is_empty())
+    else {
+        return Ok(None);
+    };
+    let mut path = expand_home(identity_file)?.into_os_string();
+    path.push("-cert.pub");
+    let path = PathBuf::from(path);
+    Ok(path.exists().then_some(path))
+}
+
+pub fn load_certificate(path: &Path) -> AppResult<Certificate> {
+    load_openssh_certificate(path)
+        .map_err(|e| AppError::auth(format!("无法加载证书: {}", path.display())).with_details(e))
+}
+
+pub fn inspect(path: &Path) -> AppResult<CertificateInfo> {
+    let certificate = load_certificate(path)?;
+    Ok(describe(path, &certificate))
+}
+
+pub fn describe(path: &Path, certificate: &Certificate) -> CertificateInfo {
+    let valid_before = certificate.valid_before();
+    CertificateInfo {
+        path: path.to_string_lossy().to_string(),
+        key_id: certificate.key_id().to_string(),
+        principals: certificate.valid_principals().to_vec(),
+        valid_after: certificate.valid_after(),
+        valid_before,
+        expires_in: seconds_until(valid_before),
+    }
+}
+
+/// 查询连接配置的证书，未使用证书时返回 None
+pub fn connection_certificate(connection: &SshConnection) -> AppResult<Option<CertificateInfo>> {
+    match certificate_path(connection)? {
+        Some(path) => inspect(&path).map(Some),
+        None => Ok(None),
+    }
+}
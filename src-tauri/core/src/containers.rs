@@ -0,0 +1,454 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::platform::HostProfile;
+use crate::session::Session;
+use crate::utils::shell_quote;
+
+/// 日志默认显示的行数
+const DEFAULT_LOG_TAIL: usize = 200;
+
+/// 容器运行时，两者的命令行基本兼容，只有 JSON 输出格式不同
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum ContainerRuntime {
+    Docker,
+    Podman,
+}
+
+impl ContainerRuntime {
+    /// 按探测到的可用命令选择运行时，都没有时默认 docker
+    pub fn detect(profile: &HostProfile) -> Self {
+        if !profile.has_tool("docker") && profile.has_tool("podman") {
+            ContainerRuntime::Podman
+        } else {
+            ContainerRuntime::Docker
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            ContainerRuntime::Docker => "docker",
+            ContainerRuntime::Podman => "podman",
+        }
+    }
+}
+
+/// 执行容器命令的方式：运行时与是否通过 sudo 执行
+#[derive(Debug, Clone, Copy)]
+pub struct Runner {
+    pub runtime: ContainerRuntime,
+    pub sudo: bool,
+}
+
+impl Runner {
+    pub fn new(profile: &HostProfile, runtime: Option<ContainerRuntime>, sudo: bool) -> Self {
+        Self {
+            runtime: runtime.unwrap_or_else(|| ContainerRuntime::detect(profile)),
+            sudo,
+        }
+    }
+
+    // 没有加入 docker 组的用户需要 sudo，-n 避免等待密码输入
+    fn command(&self, args: &str) -> String {
+        let sudo = if self.sudo { "sudo -n " } else { "" };
+        format!("{}{} {}", sudo, self.runtime.program(), args)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, TS)]
+pub struct Container {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub command: String,
+    // running、exited、paused 等
+    pub state: String,
+    // 例如 Up 2 hours
+    pub status: String,
+    // 例如 0.0.0.0:8080->80/tcp
+    pub ports: Vec<String>,
+    pub created_at: String,
+    // 只有运行中的容器并且请求了资源占用时才有
+    pub stats: Option<ContainerStats>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, TS)]
+pub struct ContainerStats {
+    pub cpu_percent: f32,
+    // 例如 10MiB / 1.9GiB
+    pub memory_usage: String,
+    pub memory_percent: f32,
+    pub net_io: String,
+    pub block_io: String,
+    pub pids: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum ContainerAction {
+    Start,
+    Stop,
+    Restart,
+    Remove,
+}
+
+impl ContainerAction {
+    fn verb(self) -> &'static str {
+        match self {
+            ContainerAction::Start => "start",
+            ContainerAction::Stop => "stop",
+            ContainerAction::Restart => "restart",
+            ContainerAction::Remove => "rm",
+        }
+    }
+}
+
+/// 列出全部容器，stats 为 true 时附带运行中容器的资源占用（docker stats 需要采样约 2 秒）
+pub async fn list_containers(
+    session: &Session,
+    runner: Runner,
+    stats: bool,
+) -> AppResult<Vec<Container>> {
+    let mut containers = match runner.runtime {
+        ContainerRuntime::Docker => {
+            parse_docker_ps(&call(session, &runner.command("ps -a --format '{{json .}}'")).await?)
+        }
+        ContainerRuntime::Podman => {
+            parse_podman_ps(&call(session, &runner.command("ps -a --format json")).await?)?
+        }
+    };
+    if stats && containers.iter().any(|c| c.state == "running") {
+        let all_stats = match runner.runtime {
+            ContainerRuntime::Docker => parse_docker_stats(
+                &call(
+                    session,
+                    &runner.command("stats --no-stream --format '{{json .}}'"),
+                )
+                .await?,
+            ),
+            ContainerRuntime::Podman => parse_podman_stats(
+                &call(session, &runner.command("stats --no-stream --format json")).await?,
+            )?,
+        };
+        merge_stats(&mut containers, all_stats);
+    }
+    Ok(containers)
+}
+
+/// 启动、停止、重启或删除容器
+pub async fn container_action(
+    session: &Session,
+    runner: Runner,
+    container: &str,
+    action: ContainerAction,
+) -> AppResult<String> {
+    validate_container(container)?;
+    let args = format!("{} {}", action.verb(), shell_quote(container));
+    call(session, &runner.command(&args)).await
+}
+
+/// 查看日志的命令，follow 为 true 时持续输出，交给输出流执行
+pub fn logs_command(
+    runner: Runner,
+    container: &str,
+    tail: Option<usize>,
+    follow: bool,
+) -> AppResult<String> {
+    validate_container(container)?;
+    let follow = if follow { " --follow" } else { "" };
+    let args = format!(
+        "logs --tail {}{} {} 2>&1",
+        tail.unwrap_or(DEFAULT_LOG_TAIL),
+        follow,
+        shell_quote(container)
+    );
+    Ok(runner.command(&args))
+}
+
+/// 在容器中打开终端的命令，需要在 PTY 中执行，shell 默认为 sh
+pub fn exec_command(runner: Runner, container: &str, shell: Option<&str>) -> AppResult<String> {
+    validate_container(container)?;
+    let shell = shell.filter(|s| !s.trim().is_empty()).unwrap_or("sh");
+    let args = format!("exec -it {} {}", shell_quote(container), shell_quote(shell));
+    Ok(runner.command(&args))
+}
+
+// 合并 stderr，失败时把运行时的报错放进详情
+async fn call(session: &Session, command: &str) -> AppResult<String> {
+    let (output, status) = session
+        .call_with_status(&format!("{} 2>&1", command))
+        .await?;
+    if let Some(status) = status.filter(|s| *s != 0) {
+        return Err(AppError::command("容器命令执行失败").with_details(format!(
+            "exit status: {}\n{}",
+            status,
+            output.trim()
+        )));
+    }
+    Ok(output)
+}
+
+// 容器 ID 或名称，不能被当作选项
+fn validate_container(container: &str) -> AppResult<()> {
+    let valid = !container.is_empty()
+        && !container.starts_with('-')
+        && container
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::invalid_input(format!(
+            "无效的容器名: {}",
+            container
+        )))
+    }
+}
+
+// 取第一个存在的字符串字段，兼容 docker 与 podman 不同的字段名
+fn text(value: &Value, keys: &[&str]) -> String {
+    keys.iter()
+        .find_map(|key| match &value[*key] {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Array(items) => Some(
+                items
+                    .iter()
+                    .filter_map(|i| i.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn percent(value: &str) -> f32 {
+    value.trim().trim_end_matches('%').parse().unwrap_or(0.0)
+}
+
+// 旧版 docker 的 ps 输出没有 State 字段，按 Status 推断
+fn state_from_status(status: &str) -> String {
+    let state = match status.split_whitespace().next().unwrap_or("") {
+        "Up" if status.contains("(Paused)") => "paused",
+        "Up" => "running",
+        "Exited" => "exited",
+        "Created" => "created",
+        "Restarting" => "restarting",
+        "Removal" => "removing",
+        "Dead" => "dead",
+        _ => "unknown",
+    };
+    state.to_string()
+}
+
+/// 解析 docker ps --format '{{json .}}'，每行一个容器
+pub fn parse_docker_ps(output: &str) -> Vec<Container> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .map(|value| {
+            let status = text(&value, &["Status"]);
+            let state = match text(&value, &["State"]) {
+                state if state.is_empty() => state_from_status(&status),
+                state => state,
+            };
+            Container {
+                id: text(&value, &["ID"]),
+                name: text(&value, &["Names"]),
+                image: text(&value, &["Image"]),
+                command: text(&value, &["Command"]).trim_matches('"').to_string(),
+                state,
+                status,
+                ports: text(&value, &["Ports"])
+                    .split(", ")
+                    .filter(|p| !p.is_empty())
+                    .map(String::from)
+                    .collect(),
+                created_at: text(&value, &["CreatedAt"]),
+                stats: None,
+            }
+        })
+        .collect()
+}
+
+/// 解析 podman ps --format json，整体是一个数组，端口为对象
+pub fn parse_podman_ps(output: &str) -> AppResult<Vec<Container>> {
+    let values: Vec<Value> = serde_json::from_str(output.trim())?;
+    Ok(values
+        .iter()
+        .map(|value| Container {
+            id: text(value, &["Id", "ID"]),
+            name: text(value, &["Names"])
+                .split(' ')
+                .next()
+                .unwrap_or("")
+                .to_string(),
+            image: text(value, &["Image"]),
+            command: text(value, &["Command"]),
+            state: text(value, &["State"]).to_lowercase(),
+            status: text(value, &["Status"]),
+            ports: value["Ports"]
+                .as_array()
+                .map(|ports| ports.iter().map(podman_port).collect())
+                .unwrap_or_default(),
+            created_at: text(value, &["CreatedAt"]),
+            stats: None,
+        })
+        .collect())
+}
+
+fn podman_port(port: &Value) -> String {
+    let host_ip = match text(port, &["host_ip", "hostIP"]) {
+        ip if ip.is_empty() => "0.0.0.0".to_string(),
+        ip => ip,
+    };
+    format!(
+        "{}:{}->{}/{}",
+        host_ip,
+        text(port, &["host_port", "hostPort"]),
+        text(port, &["container_port", "containerPort"]),
+        text(port, &["protocol"]),
+    )
+}
+
+/// 解析 docker stats --format '{{json .}}'，返回 (容器 ID, 资源占用)
+pub fn parse_docker_stats(output: &str) -> Vec<(String, ContainerStats)> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .map(|value| (text(&value, &["ID", "Container"]), stats_from(&value)))
+        .collect()
+}
+
+/// 解析 podman stats --format json
+pub fn parse_podman_stats(output: &str) -> AppResult<Vec<(String, ContainerStats)>> {
+    let values: Vec<Value> = serde_json::from_str(output.trim())?;
+    Ok(values
+        .iter()
+        .map(|value| (text(value, &["id", "ID"]), stats_from(value)))
+        .collect())
+}
+
+fn stats_from(value: &Value) -> ContainerStats {
+    ContainerStats {
+        cpu_percent: percent(&text(value, &["CPUPerc", "cpu_percent"])),
+        memory_usage: text(value, &["MemUsage", "mem_usage"]),
+        memory_percent: percent(&text(value, &["MemPerc", "mem_percent"])),
+        net_io: text(value, &["NetIO", "net_io"]),
+        block_io: text(value, &["BlockIO", "block_io"]),
+        pids: text(value, &["PIDs", "pids"]).parse().ok(),
+    }
+}
+
+/// 按 ID 前缀匹配，ps 与 stats 输出的 ID 长短可能不同
+pub fn merge_stats(containers: &mut [Container], stats: Vec<(String, ContainerStats)>) {
+    for (id, stats) in stats {
+        if id.is_empty() {
+            continue;
+        }
+        if let Some(container) = containers
+            .iter_mut()
+            .find(|c| !c.id.is_empty() && (c.id.starts_with(&id) || id.starts_with(&c.id)))
+        {
+            container.stats = Some(stats);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCKER_PS: &str = r#"{"Command":"\"/docker-entrypoint.…\"","CreatedAt":"2024-05-06 10:00:00 +0000 UTC","ID":"3f4a1b2c5d6e","Image":"nginx:1.25","Labels":"","LocalVolumes":"0","Mounts":"","Names":"web","Networks":"bridge","Ports":"0.0.0.0:8080->80/tcp, :::8080->80/tcp","RunningFor":"2 hours ago","Size":"0B","State":"running","Status":"Up 2 hours"}
+{"Command":"\"redis-server\"","CreatedAt":"2024-05-01 08:00:00 +0000 UTC","ID":"9a8b7c6d5e4f","Image":"redis:7","Labels":"","LocalVolumes":"1","Mounts":"data","Names":"cache","Networks":"bridge","Ports":"","RunningFor":"5 days ago","Size":"0B","Status":"Exited (0) 3 days ago"}
+"#;
+
+    const DOCKER_STATS: &str = r#"{"BlockIO":"1.2MB / 0B","CPUPerc":"0.15%","Container":"3f4a1b2c5d6e","ID":"3f4a1b2c5d6e","MemPerc":"0.52%","MemUsage":"10.1MiB / 1.9GiB","Name":"web","NetIO":"4.2kB / 1.1kB","PIDs":"3"}
+"#;
+
+    const PODMAN_PS: &str = r#"[
+  {
+    "AutoRemove": false,
+    "Command": ["nginx", "-g", "daemon off;"],
+    "CreatedAt": "2 hours ago",
+    "Id": "5b6c7d8e9f0a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b0c1d2e3f4a5b6c",
+    "Image": "docker.io/library/nginx:latest",
+    "Names": ["web"],
+    "Ports": [{"host_ip": "", "container_port": 80, "host_port": 8080, "range": 1, "protocol": "tcp"}],
+    "State": "running",
+    "Status": "Up 2 hours"
+  }
+]"#;
+
+    const PODMAN_STATS: &str = r#"[
+  {
+    "id": "5b6c7d8e9f0a",
+    "name": "web",
+    "cpu_percent": "1.03%",
+    "mem_usage": "5.3MB / 8.2GB",
+    "mem_percent": "0.06%",
+    "net_io": "1.1kB / 648B",
+    "block_io": "0B / 0B",
+    "pids": "2"
+  }
+]"#;
+
+    #[test]
+    fn parses_docker_ps_and_stats() {
+        let mut containers = parse_docker_ps(DOCKER_PS);
+        assert_eq!(containers.len(), 2);
+        assert_eq!(containers[0].name, "web");
+        assert_eq!(containers[0].command, "/docker-entrypoint.…");
+        assert_eq!(
+            containers[0].ports,
+            vec!["0.0.0.0:8080->80/tcp", ":::8080->80/tcp"]
+        );
+        // 缺少 State 字段时按 Status 推断
+        assert_eq!(containers[1].state, "exited");
+        assert!(containers[1].ports.is_empty());
+
+        merge_stats(&mut containers, parse_docker_stats(DOCKER_STATS));
+        let stats = containers[0].stats.as_ref().unwrap();
+        assert_eq!(stats.cpu_percent, 0.15);
+        assert_eq!(stats.memory_usage, "10.1MiB / 1.9GiB");
+        assert_eq!(stats.pids, Some(3));
+        assert!(containers[1].stats.is_none());
+    }
+
+    #[test]
+    fn parses_podman_ps_and_stats() {
+        let mut containers = parse_podman_ps(PODMAN_PS).unwrap();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].name, "web");
+        assert_eq!(containers[0].command, "nginx -g daemon off;");
+        assert_eq!(containers[0].ports, vec!["0.0.0.0:8080->80/tcp"]);
+
+        merge_stats(&mut containers, parse_podman_stats(PODMAN_STATS).unwrap());
+        let stats = containers[0].stats.as_ref().unwrap();
+        assert_eq!(stats.cpu_percent, 1.03);
+        assert_eq!(stats.memory_percent, 0.06);
+    }
+
+    #[test]
+    fn builds_commands_for_runtime() {
+        let podman = Runner {
+            runtime: ContainerRuntime::Podman,
+            sudo: true,
+        };
+        assert_eq!(
+            logs_command(podman, "web", Some(50), true).unwrap(),
+            "sudo -n podman logs --tail 50 --follow web 2>&1"
+        );
+        let docker = Runner {
+            runtime: ContainerRuntime::Docker,
+            sudo: false,
+        };
+        assert_eq!(
+            exec_command(docker, "web", Some("/bin/bash")).unwrap(),
+            "docker exec -it web /bin/bash"
+        );
+        assert!(exec_command(docker, "--privileged", None).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+use ts_rs::TS;
+
+use crate::utils::ApiResponse;
+
+/// 错误类别，序列化为稳定的机器可读错误码，前端据此区分错误类型。
+/// 已发布的错误码不要修改，只能新增
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorKind {
+    Db,
+    Network,
+    Timeout,
+    Auth,
+    HostKey,
+    Channel,
+    Command,
+    Sftp,
+    Parse,
+    NotFound,
+    InvalidInput,
+    Io,
+    Internal,
+    // 需要 sudo 密码，前端应提示输入后通过 SetSudoPassword 提交
+    SudoRequired,
+    // 远端数据在读取之后被修改，例如保存时文件已被他人改动
+    Conflict,
+}
+
+impl ErrorKind {
+    /// 对应 ApiResponse 中的数字状态码
+    pub fn status(self) -> u16 {
+        match self {
+            ErrorKind::InvalidInput | ErrorKind::Parse => 400,
+            ErrorKind::Auth | ErrorKind::SudoRequired => 401,
+            ErrorKind::HostKey => 403,
+            ErrorKind::NotFound => 404,
+            ErrorKind::Conflict => 409,
+            ErrorKind::Timeout => 504,
+            ErrorKind::Network | ErrorKind::Channel => 502,
+            ErrorKind::Db
+            | ErrorKind::Command
+            | ErrorKind::Sftp
+            | ErrorKind::Io
+            | ErrorKind::Internal => 500,
+        }
+    }
+}
+
+/// 应用统一错误类型，所有 Tauri 命令都返回它
+#[derive(Debug)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub details: Option<String>,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        AppError {
+            kind,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    /// 附加底层错误信息等调试细节
+    pub fn with_details(mut self, details: impl ToString) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+
+    pub fn db(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Db, message)
+    }
+
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Network, message)
+    }
+
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Timeout, message)
+    }
+
+    pub fn auth(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Auth, message)
+    }
+
+    pub fn host_key(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::HostKey, message)
+    }
+
+    pub fn channel(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Channel, message)
+    }
+
+    pub fn command(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Command, message)
+    }
+
+    pub fn sftp(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Sftp, message)
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Parse, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Internal, message)
+    }
+
+    pub fn sudo_required(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::SudoRequired, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Conflict, message)
+    }
+
+    /// 连接池中找不到指定连接
+    pub fn connection_not_found(id: usize) -> Self {
+        Self::not_found(format!("未找到 ID 为 {} 的连接", id))
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.details {
+            Some(details) => write!(f, "{}: {}", self.message, details),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+// 以 ApiResponse 的形式序列化，前端收到的错误与成功响应结构一致
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ApiResponse::<()>::error(self).serialize(serializer)
+    }
+}
+
+impl From<sled::Error> for AppError {
+    fn from(e: sled::Error) -> Self {
+        AppError::db("数据库操作失败").with_details(e)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::parse("JSON 解析失败").with_details(e)
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        let kind = match e.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            std::io::ErrorKind::TimedOut => ErrorKind::Timeout,
+            _ => ErrorKind::Io,
+        };
+        AppError::new(kind, "文件读写失败").with_details(e)
+    }
+}
+
+impl From<base64::DecodeError> for AppError {
+    fn from(e: base64::DecodeError) -> Self {
+        AppError::parse("base64 解码失败").with_details(e)
+    }
+}
+
+impl From<toml::de::Error> for AppError {
+    fn from(e: toml::de::Error) -> Self {
+        AppError::parse("TOML 解析失败").with_details(e)
+    }
+}
+
+impl From<toml::ser::Error> for AppError {
+    fn from(e: toml::ser::Error) -> Self {
+        AppError::parse("TOML 序列化失败").with_details(e)
+    }
+}
+
+impl From<russh::keys::Error> for AppError {
+    fn from(e: russh::keys::Error) -> Self {
+        AppError::auth("无法加载私钥").with_details(e)
+    }
+}
+
+impl From<russh::Error> for AppError {
+    fn from(e: russh::Error) -> Self {
+        let (kind, message) = match &e {
+            russh::Error::UnknownKey | russh::Error::WrongServerSig => {
+                (ErrorKind::HostKey, "服务器主机密钥校验失败")
+            }
+            russh::Error::ConnectionTimeout
+            | russh::Error::InactivityTimeout
+            | russh::Error::KeepaliveTimeout => (ErrorKind::Timeout, "连接超时"),
+            russh::Error::NotAuthenticated => (ErrorKind::Auth, "尚未通过身份验证"),
+            russh::Error::Keys(_) => (ErrorKind::Auth, "无法加载私钥"),
+            russh::Error::IO(_) | russh::Error::Disconnect | russh::Error::HUP => {
+                (ErrorKind::Network, "网络连接已断开")
+            }
+            _ => (ErrorKind::Channel, "SSH 通道错误"),
+        };
+        AppError::new(kind, message).with_details(e)
+    }
+}
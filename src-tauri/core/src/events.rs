@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::certificate::CertificateInfo;
+use crate::logs::LogLine;
+
+/// 事件输出接口。桌面应用把事件转发给前端窗口，命令行工具和测试可以提供自己的实现
+pub trait EventSink: Send + Sync {
+    fn emit_value(&self, event: &str, payload: serde_json::Value);
+}
+
+impl dyn EventSink + '_ {
+    /// 序列化后发送事件，序列化失败只记录日志
+    pub fn emit<T: Serialize>(&self, event: &str, payload: T) {
+        match serde_json::to_value(payload) {
+            Ok(payload) => self.emit_value(event, payload),
+            Err(e) => eprintln!("序列化事件 {} 失败: {}", event, e),
+        }
+    }
+}
+
+/// 在后台任务之间共享的事件输出
+pub type Sink = Arc<dyn EventSink>;
+
+/// 丢弃所有事件
+pub struct NullSink;
+
+impl EventSink for NullSink {
+    fn emit_value(&self, _event: &str, _payload: serde_json::Value) {}
+}
+
+/// 打开连接成功后的响应
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct ConnectionOpened {
+    pub id: usize,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct MemoryUsage {
+    #[ts(type = "number")]
+    pub total: u64,
+    #[ts(type = "number")]
+    pub used: u64,
+    #[ts(type = "number")]
+    pub free: u64,
+}
+
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct CpuUsage {
+    pub user: f32,
+    pub system: f32,
+    pub total: f32,
+}
+
+/// 网络速率，单位 kb/s
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct NetworkUsage {
+    pub rx_speed: f64,
+    pub tx_speed: f64,
+}
+
+/// df -h 的汇总行，保留带单位的原始文本
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct DiskUsage {
+    pub total: String,
+    pub used: String,
+    pub available: String,
+    pub use_percentage: String,
+}
+
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct DiskTotals {
+    #[ts(type = "number")]
+    pub used: u64,
+    #[ts(type = "number")]
+    pub available: u64,
+}
+
+/// ExecuteQuery("baseinfo") 的响应
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct ServerInfo {
+    pub uptime: String,
+    pub memory_usage: MemoryUsage,
+    pub cpu_usage: CpuUsage,
+    pub network_usage: NetworkUsage,
+    pub disk_usage: DiskTotals,
+    pub upload_speed: f64,
+    pub download_speed: f64,
+}
+
+/// server-uptime-update-{id} 事件
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct UptimeUpdate {
+    pub uptime: String,
+}
+
+/// server-memory-update-{id} 事件
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct MemoryUpdate {
+    pub memory_usage: MemoryUsage,
+}
+
+/// server-cpu-update-{id} 事件
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct CpuUpdate {
+    pub cpu_usage: CpuUsage,
+}
+
+/// server-network-update-{id} 事件
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct NetworkUpdate {
+    pub network_usage: NetworkUsage,
+}
+
+/// server-disk-update-{id} 事件
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct DiskUpdate {
+    pub disk_usage: DiskUsage,
+}
+
+/// 连接状态
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Reconnecting,
+    Failed,
+}
+
+/// connection-state-{id} 事件
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct ConnectionStateUpdate {
+    pub id: usize,
+    pub state: ConnectionState,
+    // 当前重连次数，非重连状态时为 0
+    pub attempt: u32,
+    pub message: Option<String>,
+}
+
+/// certificate-expiring-{id} 事件，证书即将过期时发送一次
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct CertificateExpiring {
+    pub id: usize,
+    pub certificate: CertificateInfo,
+}
+
+/// shell-output-{id} 事件
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct ShellOutput {
+    pub id: usize,
+    pub data: String,
+}
+
+/// shell-closed-{id} 事件
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct ShellClosed {
+    pub id: usize,
+    pub exit_status: Option<u32>,
+}
+
+/// stream-output-{stream_id} 事件
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct StreamOutput {
+    #[ts(type = "number")]
+    pub stream_id: u64,
+    pub id: usize,
+    pub data: String,
+}
+
+/// log-lines-{stream_id} 事件，日志按批发送
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct LogBatch {
+    #[ts(type = "number")]
+    pub stream_id: u64,
+    pub id: usize,
+    pub lines: Vec<LogLine>,
+}
+
+/// stream-closed-{stream_id} 事件，命令结束或被停止时发送
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct StreamClosed {
+    #[ts(type = "number")]
+    pub stream_id: u64,
+    pub id: usize,
+    pub exit_status: Option<u32>,
+}
+
+/// replay-frame 事件，code 与 asciicast 一致：o 输出、i 输入、r 调整窗口大小
+#[derive(Serialize, Debug, Clone, TS)]
+pub struct ReplayFrame {
+    pub name: String,
+    pub time: f64,
+    pub code: String,
+    pub data: String,
+}
@@ -0,0 +1,489 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::platform::OsFamily;
+use crate::session::Session;
+use crate::sled::current_timestamp;
+use crate::sudo;
+use crate::utils::{data_dir, shell_quote};
+
+/// 新建文件时使用的权限
+const DEFAULT_MODE: &str = "644";
+/// 可在编辑器中打开的最大文件大小
+const MAX_EDIT_SIZE: u64 = 5 * 1024 * 1024;
+
+/// 远程文件的权限、属主、大小与修改时间
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStat {
+    // 八进制权限，例如 644
+    pub mode: String,
+    pub owner: String,
+    pub group: String,
+    pub size: u64,
+    // 修改时间，秒
+    pub mtime: u64,
+}
+
+/// 打开时的文件版本，保存时据此判断远端是否被修改
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct FileVersion {
+    #[ts(type = "number")]
+    pub size: u64,
+    #[ts(type = "number")]
+    pub mtime: u64,
+    // 内容的 SHA-256，十六进制
+    pub hash: String,
+}
+
+#[derive(Serialize, Debug, TS)]
+pub struct RemoteFile {
+    pub path: String,
+    pub contents: String,
+    pub version: FileVersion,
+    pub mode: String,
+    pub owner: String,
+    pub group: String,
+}
+
+/// 保存前备份原文件的位置，默认备份到本地
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, TS)]
+pub enum BackupTarget {
+    None,
+    // 在原文件旁边复制一份 <文件名>.<时间戳>.bak。
+    // 按目录加载配置的程序（如各种 conf.d）可能会把备份也当作配置读取，需要显式选择
+    Remote,
+    // 保存到本地数据目录的 file_backups 下
+    #[default]
+    Local,
+}
+
+#[derive(Serialize, Debug, TS)]
+pub struct FileSaved {
+    pub path: String,
+    // 保存后的版本，下次保存时作为 expected 传入
+    pub version: FileVersion,
+    // 备份文件的路径，远程备份为远端路径，本地备份为本地路径
+    pub backup: Option<String>,
+}
+
+/// 打开远程文本文件用于编辑，返回内容与当前版本
+pub async fn open_file(session: &mut Session, path: &str, sudo: bool) -> AppResult<RemoteFile> {
+    validate_path(path)?;
+    let stat = stat(session, path, sudo)
+        .await?
+        .ok_or_else(|| AppError::not_found(format!("文件不存在: {}", path)))?;
+    if stat.size > MAX_EDIT_SIZE {
+        return Err(AppError::invalid_input("文件过大，无法编辑")
+            .with_details(format!("size: {}", stat.size)));
+    }
+    let contents = read(session, path, sudo).await?;
+    if contents.contains(['\0', char::REPLACEMENT_CHARACTER]) {
+        return Err(AppError::invalid_input("文件不是 UTF-8 文本，无法编辑"));
+    }
+    Ok(RemoteFile {
+        path: path.to_string(),
+        version: FileVersion {
+            size: stat.size,
+            mtime: stat.mtime,
+            hash: hash(&contents),
+        },
+        contents,
+        mode: stat.mode,
+        owner: stat.owner,
+        group: stat.group,
+    })
+}
+
+/// 保存编辑后的文件。expected 为打开时的版本，为 None 表示新建文件；
+/// 远端内容与 expected 不一致时返回 Conflict，force 为 true 时直接覆盖。
+/// 覆盖前按 backup 备份原文件，保存后保留原有的权限与属主
+pub async fn save_file(
+    session: &mut Session,
+    path: &str,
+    contents: &str,
+    expected: Option<&FileVersion>,
+    force: bool,
+    backup: BackupTarget,
+    sudo: bool,
+) -> AppResult<FileSaved> {
+    validate_path(path)?;
+    let current = stat(session, path, sudo).await?;
+    // 大小不同时不必下载即可判定冲突
+    let size_changed = matches!((&current, expected), (Some(c), Some(e)) if c.size != e.size);
+    let need_contents =
+        current.is_some() && ((!force && !size_changed) || backup == BackupTarget::Local);
+    let current_contents = if need_contents {
+        Some(read(session, path, sudo).await?)
+    } else {
+        None
+    };
+
+    if !force {
+        check_version(
+            current.is_some(),
+            size_changed,
+            current_contents.as_deref(),
+            expected,
+        )?;
+    }
+
+    let backup = match (&current, backup) {
+        (None, _) | (_, BackupTarget::None) => None,
+        (Some(_), BackupTarget::Remote) => Some(backup_remote(session, path, sudo).await?),
+        (Some(_), BackupTarget::Local) => {
+            let contents = current_contents.as_deref().unwrap_or_default();
+            Some(backup_local(&session.params.host, path, contents)?)
+        }
+    };
+
+    write_file(session, path, contents.as_bytes(), sudo).await?;
+    let saved = stat(session, path, sudo)
+        .await?
+        .ok_or_else(|| AppError::sftp("保存后找不到文件"))?;
+    Ok(FileSaved {
+        path: path.to_string(),
+        version: FileVersion {
+            size: saved.size,
+            mtime: saved.mtime,
+            hash: hash(contents),
+        },
+        backup,
+    })
+}
+
+// 以内容的哈希为准，只 touch 过的文件不算修改
+fn check_version(
+    exists: bool,
+    size_changed: bool,
+    current_contents: Option<&str>,
+    expected: Option<&FileVersion>,
+) -> AppResult<()> {
+    match (exists, expected) {
+        (false, None) => Ok(()),
+        (true, None) => Err(AppError::conflict("文件已存在")),
+        (false, Some(_)) => Err(AppError::conflict("文件已被删除")),
+        (true, Some(expected)) => {
+            let changed = size_changed
+                || current_contents.is_some_and(|contents| hash(contents) != expected.hash);
+            if changed {
+                Err(AppError::conflict("文件在打开后已被修改"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 写入远程文件。文件内容通过标准输入传输，不经过 shell 转义。
+/// sudo 为 true 时先以当前用户写入临时文件，再用 sudo install 放到目标位置，
+/// 已存在的文件保留原有的权限与属主，符号链接写入其指向的文件
+pub async fn write_file(
+    session: &mut Session,
+    path: &str,
+    contents: &[u8],
+    sudo: bool,
+) -> AppResult<()> {
+    validate_path(path)?;
+    if !sudo {
+        return upload(session, path, contents).await;
+    }
+
+    let temp = session.call("mktemp").await?.trim().to_string();
+    if temp.is_empty() {
+        return Err(AppError::sftp("创建临时文件失败"));
+    }
+    if let Err(e) = upload(session, &temp, contents).await {
+        let _ = session.call(&format!("rm -f {}", shell_quote(&temp))).await;
+        return Err(e);
+    }
+    let (resolved, _) = sudo::call(session, &resolve_command(path)).await?;
+    let target = Some(resolved.trim())
+        .filter(|t| !t.is_empty())
+        .unwrap_or(path);
+    let current = stat(session, target, true).await?;
+    let command = install_command(&temp, target, current.as_ref());
+    let (_, exit_status) = sudo::call(session, &command).await?;
+    match exit_status {
+        Some(0) | None => Ok(()),
+        Some(status) => Err(AppError::sftp("以 root 身份保存文件失败")
+            .with_details(format!("exit status: {}", status))),
+    }
+}
+
+// install 会删除目标路径再新建文件，直接安装到符号链接上会把链接替换为普通文件，
+// 所以先解析出链接指向的路径。旧版 macOS 的 readlink 不支持 -f，此时使用原路径
+fn resolve_command(path: &str) -> String {
+    let path = shell_quote(path);
+    format!(
+        "readlink -f {} 2>/dev/null || printf '%s\\n' {}",
+        path, path
+    )
+}
+
+// 无论 install 是否成功都删除临时文件，并保留 install 的退出状态
+fn install_command(temp: &str, target: &str, current: Option<&FileStat>) -> String {
+    let mut install = format!(
+        "install -m {}",
+        current.map_or(DEFAULT_MODE, |c| c.mode.as_str())
+    );
+    if let Some(current) = current {
+        install.push_str(&format!(
+            " -o {} -g {}",
+            shell_quote(&current.owner),
+            shell_quote(&current.group)
+        ));
+    }
+    format!(
+        "{} {} {}; status=$?; rm -f {}; exit $status",
+        install,
+        shell_quote(temp),
+        shell_quote(target),
+        shell_quote(temp)
+    )
+}
+
+// 以当前用户身份写入，目标文件已存在时保留其 inode、权限与属主
+async fn upload(session: &Session, path: &str, contents: &[u8]) -> AppResult<()> {
+    // 重定向失败的提示由 shell 输出，放进同一组才能取到
+    let command = format!("{{ cat > {}; }} 2>&1", shell_quote(path));
+    let (output, exit_status) = session.call_with_input(&command, contents).await?;
+    match exit_status {
+        Some(0) | None => Ok(()),
+        Some(_) => Err(AppError::sftp("写入文件失败").with_details(output.trim())),
+    }
+}
+
+async fn read(session: &mut Session, path: &str, sudo: bool) -> AppResult<String> {
+    let command = format!("cat {}", shell_quote(path));
+    let (contents, exit_status) = call(session, &command, sudo).await?;
+    match exit_status {
+        Some(0) | None => Ok(contents),
+        Some(status) => {
+            Err(AppError::sftp("读取文件失败").with_details(format!("exit status: {}", status)))
+        }
+    }
+}
+
+// 在原文件旁复制一份，cp -p 保留权限与修改时间
+async fn backup_remote(session: &mut Session, path: &str, sudo: bool) -> AppResult<String> {
+    let backup = format!("{}.{}.bak", path, current_timestamp());
+    let command = format!("cp -p {} {}", shell_quote(path), shell_quote(&backup));
+    let (_, exit_status) = call(session, &command, sudo).await?;
+    match exit_status {
+        Some(0) | None => Ok(backup),
+        Some(status) => {
+            Err(AppError::sftp("备份远程文件失败").with_details(format!("exit status: {}", status)))
+        }
+    }
+}
+
+fn backup_local(host: &str, path: &str, contents: &str) -> AppResult<String> {
+    let dir = data_dir()?.join("file_backups");
+    std::fs::create_dir_all(&dir)?;
+    let file: PathBuf = dir.join(backup_name(host, path, current_timestamp()));
+    std::fs::write(&file, contents)?;
+    Ok(file.to_string_lossy().into_owned())
+}
+
+/// 本地备份的文件名：主机_路径.时间戳.bak，路径中的分隔符等替换为下划线
+pub fn backup_name(host: &str, path: &str, timestamp: u64) -> String {
+    let path = path.trim_start_matches('/');
+    let name: String = format!("{}_{}", host, path)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.{}.bak", name, timestamp)
+}
+
+async fn call(
+    session: &mut Session,
+    command: &str,
+    sudo: bool,
+) -> AppResult<(String, Option<u32>)> {
+    if sudo {
+        sudo::call(session, command).await
+    } else {
+        session.call_with_status(command).await
+    }
+}
+
+/// 查询文件的权限、属主、大小与修改时间，文件不存在时返回 None
+pub async fn stat(session: &mut Session, path: &str, sudo: bool) -> AppResult<Option<FileStat>> {
+    let path = shell_quote(path);
+    let command = format!(
+        "if [ -e {} ]; then {} {}; fi",
+        path,
+        stat_command(session.profile.os),
+        path
+    );
+    let (output, _) = call(session, &command, sudo).await?;
+    Ok(parse_stat(&output))
+}
+
+// GNU 与 BusyBox 使用 -c，BSD 与 macOS 使用 -f。-L 读取符号链接指向的文件，
+// 否则得到的是链接本身的 777 权限
+fn stat_command(os: OsFamily) -> &'static str {
+    match os {
+        OsFamily::FreeBsd | OsFamily::Darwin => "stat -L -f '%Lp %Su %Sg %z %m'",
+        _ => "stat -L -c '%a %U %G %s %Y'",
+    }
+}
+
+/// 解析 "权限 属主 属组 大小 修改时间" 格式的 stat 输出
+pub fn parse_stat(output: &str) -> Option<FileStat> {
+    let mut fields = output.split_whitespace();
+    let mode = fields.next()?;
+    if !mode.chars().all(|c| c.is_digit(8)) {
+        return None;
+    }
+    Some(FileStat {
+        mode: mode.to_string(),
+        owner: fields.next()?.to_string(),
+        group: fields.next()?.to_string(),
+        size: fields.next()?.parse().ok()?,
+        mtime: fields.next()?.parse().ok()?,
+    })
+}
+
+/// 内容的 SHA-256，十六进制
+pub fn hash(contents: &str) -> String {
+    format!("{:x}", Sha256::digest(contents.as_bytes()))
+}
+
+fn validate_path(path: &str) -> AppResult<()> {
+    if path.trim().is_empty() {
+        return Err(AppError::invalid_input("文件路径不能为空"));
+    }
+    if path.ends_with('/') {
+        return Err(AppError::invalid_input("文件路径不能是目录"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn version(contents: &str) -> FileVersion {
+        FileVersion {
+            size: contents.len() as u64,
+            mtime: 1715000000,
+            hash: hash(contents),
+        }
+    }
+
+    #[test]
+    fn parses_stat() {
+        assert_eq!(
+            parse_stat("640 root adm 1024 1715000000\n"),
+            Some(FileStat {
+                mode: "640".to_string(),
+                owner: "root".to_string(),
+                group: "adm".to_string(),
+                size: 1024,
+                mtime: 1715000000,
+            })
+        );
+        assert_eq!(parse_stat(""), None);
+        assert_eq!(parse_stat("stat: cannot stat 'x'"), None);
+        assert_eq!(parse_stat("644 root root"), None);
+    }
+
+    #[test]
+    fn hashes_contents() {
+        assert_eq!(
+            hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn detects_conflicts() {
+        let opened = version("listen 80;\n");
+        assert!(check_version(true, false, Some("listen 80;\n"), Some(&opened)).is_ok());
+        let changed = check_version(true, false, Some("listen 81;\n"), Some(&opened));
+        assert_eq!(changed.unwrap_err().kind, ErrorKind::Conflict);
+        assert!(check_version(true, true, None, Some(&opened)).is_err());
+        assert!(check_version(false, false, None, Some(&opened)).is_err());
+        assert!(check_version(true, false, Some(""), None).is_err());
+        assert!(check_version(false, false, None, None).is_ok());
+    }
+
+    #[test]
+    fn builds_local_backup_name() {
+        assert_eq!(
+            backup_name(
+                "10.0.0.5",
+                "/etc/nginx/sites-enabled/my site.conf",
+                1715000000
+            ),
+            "10.0.0.5_etc_nginx_sites-enabled_my_site.conf.1715000000.bak"
+        );
+    }
+
+    // 在本地按保存时的顺序执行解析、stat 与 install，链接应保留，权限不变
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn saves_through_symlink() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+        use std::process::Command;
+
+        let sh = |command: &str| {
+            let output = Command::new("sh").arg("-c").arg(command).output().unwrap();
+            assert!(output.status.success(), "{}", command);
+            String::from_utf8(output.stdout).unwrap()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("default.conf");
+        let link = dir.path().join("enabled.conf");
+        let temp = dir.path().join("upload");
+        std::fs::write(&real, "listen 80;\n").unwrap();
+        std::fs::set_permissions(&real, std::fs::Permissions::from_mode(0o640)).unwrap();
+        symlink(&real, &link).unwrap();
+        std::fs::write(&temp, "listen 81;\n").unwrap();
+
+        let link_path = link.to_str().unwrap();
+        let target = sh(&resolve_command(link_path)).trim().to_string();
+        assert_eq!(target, real.to_str().unwrap());
+        let quoted = shell_quote(&target);
+        let current = parse_stat(&sh(&format!(
+            "{} {}",
+            stat_command(OsFamily::Linux),
+            quoted
+        )))
+        .unwrap();
+        assert_eq!(current.mode, "640");
+        sh(&install_command(
+            temp.to_str().unwrap(),
+            &target,
+            Some(&current),
+        ));
+
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read_to_string(&real).unwrap(), "listen 81;\n");
+        let mode = std::fs::metadata(&real).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        assert!(!temp.exists());
+    }
+
+    #[test]
+    fn rejects_directory_path() {
+        assert!(validate_path("/etc/nginx/").is_err());
+        assert!(validate_path(" ").is_err());
+        assert!(validate_path("/etc/nginx/nginx.conf").is_ok());
+    }
+}
@@ -0,0 +1,147 @@
+use std::future::Future;
+use std::sync::Arc;
+
+use russh::client::Handle;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::session::{Client, Session};
+use crate::sled::LocalForward;
+use crate::ssh::{reconnect, WATCH_INTERVAL};
+
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1";
+
+/// 监听本地端口，把每个连接通过 direct-tcpip 通道转发到远端地址。
+/// 返回监听任务，abort 即停止转发
+pub async fn start_local_forward(
+    session: Arc<Handle<Client>>,
+    forward: &LocalForward,
+) -> AppResult<JoinHandle<()>> {
+    let bind_address = forward
+        .bind_address
+        .as_deref()
+        .filter(|a| !a.is_empty())
+        .unwrap_or(DEFAULT_BIND_ADDRESS);
+    let listener = TcpListener::bind((bind_address, forward.bind_port))
+        .await
+        .map_err(|e| {
+            AppError::network(format!("无法监听 {}:{}", bind_address, forward.bind_port))
+                .with_details(e)
+        })?;
+    let forward = forward.clone();
+    Ok(tokio::spawn(async move {
+        loop {
+            let (mut stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    eprintln!("接受转发连接失败: {}", e);
+                    continue;
+                }
+            };
+            if session.is_closed() {
+                break;
+            }
+            let channel = match session
+                .channel_open_direct_tcpip(
+                    forward.remote_host.clone(),
+                    forward.remote_port as u32,
+                    peer.ip().to_string(),
+                    peer.port() as u32,
+                )
+                .await
+            {
+                Ok(channel) => channel,
+                Err(e) => {
+                    eprintln!(
+                        "打开到 {}:{} 的转发通道失败: {}",
+                        forward.remote_host, forward.remote_port, e
+                    );
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                let mut remote = channel.into_stream();
+                if let Err(e) = tokio::io::copy_bidirectional(&mut stream, &mut remote).await {
+                    eprintln!("转发连接 {} 中断: {}", peer, e);
+                }
+            });
+        }
+    }))
+}
+
+/// 建立全部转发并保持到 shutdown 完成。连接断开后停止转发，
+/// 按与连接池相同的退避策略重连，重连成功后重新建立全部转发
+pub async fn keep_local_forwards(
+    mut session: Session,
+    forwards: &[LocalForward],
+    shutdown: impl Future<Output = ()>,
+) -> AppResult<()> {
+    let mut tasks = start_all(&session.session, forwards).await?;
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = tokio::time::sleep(WATCH_INTERVAL) => {}
+        }
+        if !session.is_closed() {
+            continue;
+        }
+        println!("连接已断开，端口转发暂停，开始重连");
+        stop_forwards(std::mem::take(&mut tasks)).await;
+
+        let (handle, negotiated) = tokio::select! {
+            _ = &mut shutdown => return Ok(()),
+            result = reconnect(&session.params, |_| {}) => result.map_err(|e| match e.kind {
+                ErrorKind::Auth => e,
+                _ => AppError::network("自动重连失败，端口转发已停止").with_details(e),
+            })?,
+        };
+        session.session = Arc::new(handle);
+        session.negotiated = negotiated;
+        tasks = start_all(&session.session, forwards).await?;
+        println!("重连成功，端口转发已恢复");
+    }
+    stop_forwards(tasks).await;
+    Ok(())
+}
+
+/// 为连接池中的连接建立保存的转发，单条转发失败只记录日志，不影响其他转发
+pub(crate) async fn start_forwards(
+    session: &Arc<Handle<Client>>,
+    forwards: &[LocalForward],
+) -> Vec<JoinHandle<()>> {
+    let mut tasks = Vec::with_capacity(forwards.len());
+    for forward in forwards {
+        match start_local_forward(session.clone(), forward).await {
+            Ok(task) => tasks.push(task),
+            Err(e) => eprintln!("建立端口转发失败: {}", e),
+        }
+    }
+    tasks
+}
+
+async fn start_all(
+    session: &Arc<Handle<Client>>,
+    forwards: &[LocalForward],
+) -> AppResult<Vec<JoinHandle<()>>> {
+    let mut tasks = Vec::with_capacity(forwards.len());
+    for forward in forwards {
+        match start_local_forward(session.clone(), forward).await {
+            Ok(task) => tasks.push(task),
+            Err(e) => {
+                stop_forwards(tasks).await;
+                return Err(e);
+            }
+        }
+    }
+    Ok(tasks)
+}
+
+/// 停止转发任务。等待任务真正结束，确保监听端口已释放，重连后才能重新绑定
+pub(crate) async fn stop_forwards(tasks: Vec<JoinHandle<()>>) {
+    for task in tasks {
+        task.abort();
+        let _ = task.await;
+    }
+}
@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use ts_rs::TS;
+
+use crate::error::AppResult;
+use crate::settings::{load_settings, HistorySettings};
+use crate::sled::{current_timestamp, fuzzy_score, get_connection};
+
+/// 命令历史 tree，键为 连接 ID + 自增 ID（均为大端序），同一连接的记录按时间顺序排列
+pub(crate) const HISTORY_TREE: &str = "history";
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum HistorySource {
+    Query,
+    Shell,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct HistoryEntry {
+    #[ts(type = "number")]
+    pub connection_id: u64,
+    pub host: String,
+    pub command: String,
+    pub source: HistorySource,
+    // 终端中输入的命令无法获取退出码
+    pub exit_code: Option<u32>,
+    #[ts(type = "number")]
+    pub duration_ms: u64,
+    #[ts(type = "number")]
+    pub executed_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, TS)]
+pub enum HistoryMatch {
+    Prefix,
+    Fuzzy,
+}
+
+/// 历史查询条件，均为可选
+#[derive(Serialize, Deserialize, Debug, Default, TS)]
+#[serde(default)]
+pub struct HistoryQuery {
+    #[ts(type = "number | null")]
+    pub connection_id: Option<u64>,
+    pub query: Option<String>,
+    // 默认为前缀匹配
+    pub mode: Option<HistoryMatch>,
+    pub host: Option<String>,
+    #[ts(type = "number | null")]
+    pub since: Option<u64>,
+    #[ts(type = "number | null")]
+    pub until: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+fn history_tree(db: &Db) -> AppResult<Tree> {
+    Ok(db.open_tree(HISTORY_TREE)?)
+}
+
+fn entry_key(connection_id: u64, seq: u64) -> Vec<u8> {
+    let mut key = connection_id.to_be_bytes().to_vec();
+    key.extend_from_slice(&seq.to_be_bytes());
+    key
+}
+
+/// 记录一条命令，失败只记录日志，不影响命令执行
+pub fn record(
+    db: &Db,
+    connection_id: u64,
+    command: &str,
+    source: HistorySource,
+    exit_code: Option<u32>,
+    duration_ms: u64,
+) {
+    if let Err(e) = try_record(db, connection_id, command, source, exit_code, duration_ms) {
+        eprintln!("记录命令历史失败: {}", e);
+    }
+}
+
+fn try_record(
+    db: &Db,
+    connection_id: u64,
+    command: &str,
+    source: HistorySource,
+    exit_code: Option<u32>,
+    duration_ms: u64,
+) -> AppResult<()> {
+    let settings = load_settings(db)?.history;
+    let command = command.trim();
+    if !settings.enabled || command.is_empty() || is_excluded(&settings, command) {
+        return Ok(());
+    }
+    let host = get_connection(db, connection_id)?
+        .map(|c| c.host)
+        .unwrap_or_default();
+    let entry = HistoryEntry {
+        connection_id,
+        host,
+        command: command.to_string(),
+        source,
+        exit_code,
+        duration_ms,
+        executed_at: current_timestamp(),
+    };
+    let tree = history_tree(db)?;
+    tree.insert(
+        entry_key(connection_id, db.generate_id()?),
+        serde_json::to_vec(&entry)?,
+    )?;
+    prune(&tree, connection_id, &settings)
+}
+
+// 按保留天数和条数上限清理该连接的旧记录。同一连接的记录按时间顺序排列，
+// 过期和超出上限的都在最前面：过期记录从头解码到第一条未过期为止，条数上限只数键
+fn prune(tree: &Tree, connection_id: u64, settings: &HistorySettings) -> AppResult<()> {
+    let prefix = connection_id.to_be_bytes();
+    if settings.retention_days > 0 {
+        let cutoff = current_timestamp().saturating_sub(settings.retention_days as u64 * DAY_SECS);
+        for item in tree.scan_prefix(prefix) {
+            let (key, value) = item?;
+            let expired = serde_json::from_slice::<HistoryEntry>(&value)
+                .map(|entry| entry.executed_at < cutoff)
+                .unwrap_or(true);
+            if !expired {
+                break;
+            }
+            tree.remove(&key)?;
+        }
+    }
+    if settings.max_entries > 0 {
+        // 从最新的记录往前数，超出上限的第一条及更早的记录全部删除
+        if let Some(item) = tree.scan_prefix(prefix).rev().nth(settings.max_entries) {
+            let (last, _) = item?;
+            for item in tree.range(prefix.as_slice()..=last.as_ref()) {
+                let (key, _) = item?;
+                tree.remove(key)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_excluded(settings: &HistorySettings, command: &str) -> bool {
+    let command = command.to_lowercase();
+    settings
+        .exclude_patterns
+        .iter()
+        .any(|pattern| wildcard_match(&pattern.to_lowercase(), &command))
+}
+
+// 只支持 * 通配符
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text[first.len()..].ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// 按条件查询历史，前缀匹配按时间倒序，模糊匹配按得分排序
+pub fn search_history(db: &Db, query: &HistoryQuery) -> AppResult<Vec<HistoryEntry>> {
+    let tree = history_tree(db)?;
+    let items = match query.connection_id {
+        Some(id) => tree.scan_prefix(id.to_be_bytes()),
+        None => tree.iter(),
+    };
+    let text = query.query.as_deref().map(str::trim).unwrap_or("");
+    let mode = query.mode.unwrap_or(HistoryMatch::Prefix);
+
+    let mut matched = Vec::new();
+    for item in items {
+        let (_, value) = item?;
+        let Ok(entry) = serde_json::from_slice::<HistoryEntry>(&value) else {
+            continue;
+        };
+        if query.host.as_deref().is_some_and(|h| h != entry.host)
+            || query.since.is_some_and(|t| entry.executed_at < t)
+            || query.until.is_some_and(|t| entry.executed_at > t)
+        {
+            continue;
+        }
+        let score = if text.is_empty() {
+            0
+        } else {
+            match mode {
+                HistoryMatch::Prefix if entry.command.starts_with(text) => 0,
+                HistoryMatch::Prefix => continue,
+                HistoryMatch::Fuzzy => match fuzzy_score(text, &entry.command) {
+                    Some(score) => score,
+                    None => continue,
+                },
+            }
+        };
+        matched.push((score, entry));
+    }
+    matched.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.executed_at.cmp(&a.1.executed_at)));
+    let limit = query.limit.unwrap_or(100);
+    Ok(matched.into_iter().take(limit).map(|(_, e)| e).collect())
+}
+
+/// 清空历史，指定连接时只清空该连接
+pub fn clear_history(db: &Db, connection_id: Option<u64>) -> AppResult<usize> {
+    let tree = history_tree(db)?;
+    let Some(id) = connection_id else {
+        let count = tree.len();
+        tree.clear()?;
+        return Ok(count);
+    };
+    let mut count = 0;
+    for item in tree.scan_prefix(id.to_be_bytes()) {
+        let (key, _) = item?;
+        tree.remove(key)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// 从终端输入中还原用户输入的命令行：处理退格、Ctrl-C/Ctrl-U，忽略方向键等转义序列。
+/// 使用 Tab 补全或历史翻阅时得到的只是按键内容，无法与远端完全一致
+#[derive(Default)]
+pub struct LineCapture {
+    line: String,
+    escape: EscapeState,
+}
+
+#[derive(Default, PartialEq, Eq)]
+enum EscapeState {
+    #[default]
+    None,
+    Start,
+    Sequence,
+}
+
+impl LineCapture {
+    /// 输入一段按键数据，返回其中完成的命令行
+    pub fn feed(&mut self, data: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        for c in data.chars() {
+            match self.escape {
+                EscapeState::Start => {
+                    self.escape = if c == '[' || c == 'O' {
+                        EscapeState::Sequence
+                    } else {
+                        EscapeState::None
+                    };
+                    continue;
+                }
+                EscapeState::Sequence => {
+                    if ('@'..='~').contains(&c) {
+                        self.escape = EscapeState::None;
+                    }
+                    continue;
+                }
+                EscapeState::None => {}
+            }
+            match c {
+                '\x1b' => self.escape = EscapeState::Start,
+                '\r' | '\n' => {
+                    let line = std::mem::take(&mut self.line);
+                    if !line.trim().is_empty() {
+                        lines.push(line);
+                    }
+                }
+                '\x7f' | '\x08' => {
+                    self.line.pop();
+                }
+                '\x03' | '\x15' => self.line.clear(),
+                c if c.is_control() => {}
+                c => self.line.push(c),
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(tree: &Tree, connection_id: u64, seq: u64, executed_at: u64) {
+        let entry = HistoryEntry {
+            connection_id,
+            host: String::new(),
+            command: format!("echo {}", seq),
+            source: HistorySource::Query,
+            exit_code: Some(0),
+            duration_ms: 0,
+            executed_at,
+        };
+        tree.insert(
+            entry_key(connection_id, seq),
+            serde_json::to_vec(&entry).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn seqs(tree: &Tree, connection_id: u64) -> Vec<u64> {
+        tree.scan_prefix(connection_id.to_be_bytes())
+            .keys()
+            .map(|key| u64::from_be_bytes(key.unwrap()[8..].try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn prunes_oldest_entries_of_one_connection() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let tree = history_tree(&db).unwrap();
+        let now = current_timestamp();
+        insert(&tree, 1, 1, now - 10 * DAY_SECS);
+        for seq in 2..=6 {
+            insert(&tree, 1, seq, now);
+        }
+        insert(&tree, 2, 7, now - 10 * DAY_SECS);
+
+        let settings = HistorySettings {
+            retention_days: 7,
+            max_entries: 3,
+            ..HistorySettings::default()
+        };
+        prune(&tree, 1, &settings).unwrap();
+        assert_eq!(seqs(&tree, 1), vec![4, 5, 6]);
+        assert_eq!(seqs(&tree, 2), vec![7]);
+    }
+}
@@ -0,0 +1,395 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use base64::Engine;
+use russh::client;
+use russh::keys::key::PublicKey;
+use russh::keys::PublicKeyBase64;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sled::Db;
+use ts_rs::TS;
+
+use crate::algorithms::AlgorithmPreferences;
+use crate::error::{AppError, AppResult};
+use crate::session::with_timeout;
+use crate::settings::Timeouts;
+use crate::sled::current_timestamp;
+
+/// 存放受信任主机密钥的 tree，键为 host:port
+const HOST_KEYS_TREE: &str = "host_keys";
+const FINGERPRINT_PREFIX: &str = "SHA256:";
+
+// randomart 画布尺寸与字符表，与 OpenSSH 保持一致
+const ART_WIDTH: usize = 17;
+const ART_HEIGHT: usize = 9;
+const ART_CHARS: &[u8] = b" .o+=*BOX@%&#/^SE";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TrustedHostKey {
+    algorithm: String,
+    fingerprint: String,
+    // OpenSSH 格式的公钥 base64
+    public_key: String,
+    first_seen: u64,
+}
+
+/// 主机密钥信息，供指纹管理界面展示
+#[derive(Serialize, Debug, TS)]
+pub struct HostKeyInfo {
+    // 只固定了指纹、尚未信任的密钥没有算法和首次出现时间
+    pub algorithm: Option<String>,
+    pub fingerprint: String,
+    pub randomart: String,
+    #[ts(type = "number | null")]
+    pub first_seen: Option<u64>,
+    // 是否被固定到当前连接
+    pub pinned: bool,
+    // 是否在已信任的密钥列表中
+    pub trusted: bool,
+}
+
+/// 连接时校验服务器主机密钥，依次检查：固定密钥、已信任的密钥、known_hosts，
+/// 都没有记录时首次信任并保存
+#[derive(Clone)]
+pub struct HostKeyVerifier {
+    pub db: Db,
+    pub host: String,
+    pub port: u16,
+    pub pinned: Option<String>,
+}
+
+impl HostKeyVerifier {
+    pub fn verify(&self, key: &PublicKey) -> AppResult<bool> {
+        let fingerprint = fingerprint(key);
+        if let Some(pinned) = &self.pinned {
+            return Ok(normalize_fingerprint(pinned) == fingerprint);
+        }
+        let mut keys = load(&self.db, &self.host, self.port)?;
+        if keys.iter().any(|k| k.fingerprint == fingerprint) {
+            return Ok(true);
+        }
+        if !keys.is_empty() {
+            eprintln!(
+                "{}:{} 的主机密钥与已信任的密钥不一致: {}",
+                self.host, self.port, fingerprint
+            );
+            return Ok(false);
+        }
+        // sled 中没有记录时参考 known_hosts，密钥变化则拒绝
+        if let Err(russh::keys::Error::KeyChanged { line }) =
+            russh::keys::check_known_hosts(&self.host, self.port, key)
+        {
+            eprintln!(
+                "{}:{} 的主机密钥与 known_hosts 第 {} 行不一致",
+                self.host, self.port, line
+            );
+            return Ok(false);
+        }
+        keys.push(trusted(key));
+        save(&self.db, &self.host, self.port, &keys)?;
+        Ok(true)
+    }
+}
+
+/// SHA256 指纹，格式与 ssh-keygen -l 相同
+pub fn fingerprint(key: &PublicKey) -> String {
+    format!(
+        "{}{}",
+        FINGERPRINT_PREFIX,
+        STANDARD_NO_PAD.encode(Sha256::digest(key.public_key_bytes()))
+    )
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    let fingerprint = fingerprint.trim();
+    let hash = fingerprint
+        .strip_prefix(FINGERPRINT_PREFIX)
+        .unwrap_or(fingerprint)
+        .trim_end_matches('=');
+    format!("{}{}", FINGERPRINT_PREFIX, hash)
+}
+
+fn trusted(key: &PublicKey) -> TrustedHostKey {
+    TrustedHostKey {
+        algorithm: key.name().to_string(),
+        fingerprint: fingerprint(key),
+        public_key: key.public_key_base64(),
+        first_seen: current_timestamp(),
+    }
+}
+
+fn host_key(host: &str, port: u16) -> String {
+    format!("{}:{}", host, port)
+}
+
+fn load(db: &Db, host: &str, port: u16) -> AppResult<Vec<TrustedHostKey>> {
+    let tree = db.open_tree(HOST_KEYS_TREE)?;
+    match tree.get(host_key(host, port))? {
+        Some(value) => Ok(serde_json::from_slice(&value)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn save(db: &Db, host: &str, port: u16, keys: &[TrustedHostKey]) -> AppResult<()> {
+    let tree = db.open_tree(HOST_KEYS_TREE)?;
+    if keys.is_empty() {
+        tree.remove(host_key(host, port))?;
+    } else {
+        tree.insert(host_key(host, port), serde_json::to_vec(keys)?)?;
+    }
+    tree.flush()?;
+    Ok(())
+}
+
+/// 列出已信任的密钥，固定的指纹不在其中时也列出，标记为未信任
+pub fn list_host_keys(
+    db: &Db,
+    host: &str,
+    port: u16,
+    pinned: Option<&str>,
+) -> AppResult<Vec<HostKeyInfo>> {
+    let pinned = pinned.map(normalize_fingerprint);
+    let mut keys = load(db, host, port)?
+        .into_iter()
+        .map(|key| -> AppResult<HostKeyInfo> {
+            let blob = STANDARD.decode(&key.public_key)?;
+            Ok(HostKeyInfo {
+                randomart: randomart(&key.algorithm, &Sha256::digest(blob)),
+                pinned: pinned.as_deref() == Some(key.fingerprint.as_str()),
+                algorithm: Some(key.algorithm),
+                fingerprint: key.fingerprint,
+                first_seen: Some(key.first_seen),
+                trusted: true,
+            })
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+    if let Some(pinned) = pinned.filter(|p| !keys.iter().any(|k| k.fingerprint == *p)) {
+        keys.push(pinned_only(pinned));
+    }
+    Ok(keys)
+}
+
+// 指纹即公钥的 SHA256 摘要，不需要公钥也能画出 randomart
+fn pinned_only(fingerprint: String) -> HostKeyInfo {
+    let digest = fingerprint
+        .strip_prefix(FINGERPRINT_PREFIX)
+        .and_then(|hash| STANDARD_NO_PAD.decode(hash).ok());
+    HostKeyInfo {
+        randomart: digest
+            .map(|digest| randomart("UNKNOWN", &digest))
+            .unwrap_or_default(),
+        algorithm: None,
+        fingerprint,
+        first_seen: None,
+        pinned: true,
+        trusted: false,
+    }
+}
+
+pub fn remove_host_key(db: &Db, host: &str, port: u16, fingerprint: &str) -> AppResult<()> {
+    let fingerprint = normalize_fingerprint(fingerprint);
+    let mut keys = load(db, host, port)?;
+    let count = keys.len();
+    keys.retain(|k| k.fingerprint != fingerprint);
+    if keys.len() == count {
+        return Err(AppError::not_found(format!(
+            "{} 没有指纹为 {} 的主机密钥",
+            host_key(host, port),
+            fingerprint
+        )));
+    }
+    save(db, host, port, &keys)
+}
+
+/// 获取服务器当前出示的主机密钥供用户核对，不修改已信任的密钥
+pub async fn scan_host_key(
+    db: &Db,
+    host: &str,
+    port: u16,
+    timeouts: &Timeouts,
+    algorithms: &AlgorithmPreferences,
+) -> AppResult<HostKeyInfo> {
+    let key = scan(host, port, timeouts, algorithms).await?;
+    let fingerprint = fingerprint(&key);
+    let known = load(db, host, port)?
+        .into_iter()
+        .find(|k| k.fingerprint == fingerprint);
+    Ok(HostKeyInfo {
+        randomart: randomart(key.name(), &Sha256::digest(key.public_key_bytes())),
+        algorithm: Some(key.name().to_string()),
+        fingerprint,
+        first_seen: known.as_ref().map(|k| k.first_seen),
+        pinned: false,
+        trusted: known.is_some(),
+    })
+}
+
+/// 重新获取服务器当前的主机密钥，与用户核对过的指纹一致时才替换该主机所有已信任的密钥。
+/// 用于服务器重装等确认密钥变更合法的场景，指纹不一致说明核对后密钥又发生了变化
+pub async fn replace_host_key(
+    db: &Db,
+    host: &str,
+    port: u16,
+    expected: &str,
+    timeouts: &Timeouts,
+    algorithms: &AlgorithmPreferences,
+) -> AppResult<HostKeyInfo> {
+    let expected = normalize_fingerprint(expected);
+    let key = scan(host, port, timeouts, algorithms).await?;
+    let trusted = trusted(&key);
+    if trusted.fingerprint != expected {
+        return Err(
+            AppError::host_key("服务器当前的主机密钥与确认的指纹不一致").with_details(format!(
+                "确认的指纹: {}，服务器出示的指纹: {}",
+                expected, trusted.fingerprint
+            )),
+        );
+    }
+    save(db, host, port, std::slice::from_ref(&trusted))?;
+    Ok(HostKeyInfo {
+        randomart: randomart(&trusted.algorithm, &Sha256::digest(key.public_key_bytes())),
+        algorithm: Some(trusted.algorithm),
+        fingerprint: trusted.fingerprint,
+        first_seen: Some(trusted.first_seen),
+        pinned: false,
+        trusted: true,
+    })
+}
+
+/// 只用于读取主机密钥的客户端，不做认证
+struct KeyScanner {
+    key: Arc<Mutex<Option<PublicKey>>>,
+}
+
+#[async_trait]
+impl client::Handler for KeyScanner {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &PublicKey,
+    ) -> Result<bool, Self::Error> {
+        if let Ok(mut key) = self.key.lock() {
+            *key = Some(server_public_key.clone());
+        }
+        Ok(true)
+    }
+}
+
+// 使用连接的算法偏好协商，取到的是实际连接时会使用的主机密钥类型
+async fn scan(
+    host: &str,
+    port: u16,
+    timeouts: &Timeouts,
+    algorithms: &AlgorithmPreferences,
+) -> AppResult<PublicKey> {
+    let key = Arc::new(Mutex::new(None));
+    let scanner = KeyScanner { key: key.clone() };
+    let config = Arc::new(client::Config {
+        preferred: algorithms.to_preferred()?,
+        ..Default::default()
+    });
+    let session = with_timeout(
+        timeouts.connect(),
+        client::connect(config, (host, port), scanner),
+        "连接服务器超时",
+    )
+    .await??;
+    let _ = session
+        .disconnect(russh::Disconnect::ByApplication, "", "English")
+        .await;
+    let key = key.lock().ok().and_then(|mut key| key.take());
+    key.ok_or_else(|| AppError::host_key("未能获取服务器主机密钥"))
+}
+
+/// OpenSSH 的 "drunken bishop" 指纹图
+fn randomart(algorithm: &str, digest: &[u8]) -> String {
+    let mut field = [[0usize; ART_WIDTH]; ART_HEIGHT];
+    let (mut x, mut y) = (ART_WIDTH / 2, ART_HEIGHT / 2);
+    let (start_x, start_y) = (x, y);
+    // 前 15 个字符表示经过次数，最后两个是起点 S 和终点 E
+    let max_visits = ART_CHARS.len() - 3;
+
+    for byte in digest {
+        let mut bits = *byte;
+        for _ in 0..4 {
+            x = if bits & 0x1 != 0 {
+                (x + 1).min(ART_WIDTH - 1)
+            } else {
+                x.saturating_sub(1)
+            };
+            y = if bits & 0x2 != 0 {
+                (y + 1).min(ART_HEIGHT - 1)
+            } else {
+                y.saturating_sub(1)
+            };
+            if field[y][x] < max_visits {
+                field[y][x] += 1;
+            }
+            bits >>= 2;
+        }
+    }
+    field[start_y][start_x] = ART_CHARS.len() - 2;
+    field[y][x] = ART_CHARS.len() - 1;
+
+    let mut art = border(&format!("[{}]", algorithm));
+    for row in field {
+        art.push('|');
+        art.extend(row.iter().map(|&visits| ART_CHARS[visits] as char));
+        art.push_str("|\n");
+    }
+    art.push_str(&border("[SHA256]"));
+    art.truncate(art.trim_end().len());
+    art
+}
+
+// 标题居中的边框行
+fn border(title: &str) -> String {
+    let title: String = title.chars().take(ART_WIDTH).collect();
+    let padding = ART_WIDTH - title.chars().count();
+    let left = padding / 2;
+    format!(
+        "+{}{}{}+\n",
+        "-".repeat(left),
+        title,
+        "-".repeat(padding - left)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 与 ssh-keygen -lv 对同一公钥的输出一致
+    #[test]
+    fn randomart_matches_openssh() {
+        let digest = STANDARD_NO_PAD
+            .decode("s4B9YDciyezolfGU6Piq0OB0LfuKWKqenH+Df9HdJ1w")
+            .unwrap();
+        let expected = "\
++--[ED25519 256]--+
+|                 |
+|   o o .         |
+|    B * o        |
+|   =.X + .     E |
+|..oo*.+ S . o .  |
+|o+.oo  + + . + . |
+|..+.o   o     o  |
+|+o+o.o .         |
+|BBoo+oo          |
++----[SHA256]-----+";
+        assert_eq!(randomart("ED25519 256", &digest), expected);
+    }
+
+    #[test]
+    fn randomart_truncates_long_titles() {
+        let art = randomart("ECDSA-SHA2-NISTP521-CERT", &[0; 32]);
+        let first = art.lines().next().unwrap();
+        assert_eq!(first, "+[ECDSA-SHA2-NISTP+");
+        assert!(art
+            .lines()
+            .all(|line| line.chars().count() == ART_WIDTH + 2));
+    }
+}
@@ -0,0 +1,32 @@
+//! 连接存储、SSH 会话、服务器监控与端口转发等核心逻辑，不依赖 Tauri。
+//! 事件通过 [`events::EventSink`] 输出，桌面应用、命令行工具和测试各自提供实现
+
+pub mod algorithms;
+pub mod backup;
+#[cfg(test)]
+mod bindings;
+pub mod bundle;
+pub mod certificate;
+pub mod containers;
+pub mod error;
+pub mod events;
+pub mod files;
+pub mod forward;
+pub mod history;
+pub mod hostkeys;
+pub mod logs;
+pub mod metrics;
+pub mod migrations;
+pub mod platform;
+pub mod recording;
+pub mod session;
+pub mod settings;
+pub mod shell;
+pub mod sled;
+pub mod snippets;
+pub mod ssh;
+pub mod ssh_config;
+pub mod streams;
+pub mod sudo;
+pub mod systemd;
+pub mod utils;
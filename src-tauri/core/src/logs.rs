@@ -0,0 +1,453 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::events::{LogBatch, Sink};
+use crate::session::Session;
+use crate::streams::{self, StreamHandler, StreamOpened};
+use crate::systemd;
+use crate::utils::shell_quote;
+
+/// 每个来源默认缓存的行数
+const DEFAULT_BUFFER_LINES: usize = 1000;
+/// 开始跟踪时默认先输出的历史行数
+const DEFAULT_INITIAL_LINES: usize = 100;
+/// 单批最多的行数，超过时不等定时器立即发送
+const MAX_BATCH_LINES: usize = 500;
+
+// 正在跟踪的日志缓存，键为流 ID
+static TAILS: Lazy<Mutex<HashMap<u64, Arc<Mutex<TailBuffer>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 按出现位置取最靠前的级别关键字，避免消息正文中的 error 覆盖行首的 INFO
+static SEVERITY_PATTERNS: Lazy<Vec<(Severity, Regex)>> = Lazy::new(|| {
+    [
+        (
+            Severity::Error,
+            r"(?i)\b(emerg|alert|crit|critical|fatal|panic|err|error|severe)\b",
+        ),
+        (Severity::Warning, r"(?i)\b(warn|warning)\b"),
+        (Severity::Info, r"(?i)\b(info|notice)\b"),
+        (Severity::Debug, r"(?i)\b(debug|trace)\b"),
+    ]
+    .into_iter()
+    .map(|(severity, pattern)| (severity, Regex::new(pattern).expect("无效的级别表达式")))
+    .collect()
+});
+
+/// 日志来源：文件按 tail -F 跟踪，轮转后重新打开；单元通过 journalctl -f 跟踪
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub enum LogSource {
+    File(String),
+    Unit(String),
+}
+
+#[derive(Deserialize, Debug, Clone, TS)]
+pub struct LogTailOptions {
+    pub sources: Vec<LogSource>,
+    // 正则表达式，非空时只保留匹配任意一个的行
+    #[serde(default)]
+    pub include: Vec<String>,
+    // 匹配任意一个的行被丢弃
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    // 每个来源缓存的最近行数，默认 1000
+    pub buffer_lines: Option<usize>,
+    // 开始时先输出的历史行数，默认 100
+    pub initial_lines: Option<usize>,
+    // 通过 sudo -n 读取，用于 /var/log 下只有 root 可读的文件
+    #[serde(default)]
+    pub sudo: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Debug,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, TS)]
+pub struct LogLine {
+    // sources 中的下标，null 表示远端 shell 自身的输出，例如 sudo 的报错
+    pub source: Option<usize>,
+    // journal 记录的毫秒时间戳，文件日志为 null
+    #[ts(type = "number | null")]
+    pub timestamp: Option<u64>,
+    pub severity: Option<Severity>,
+    pub text: String,
+}
+
+// 每个来源最近的行，下标与 sources 一致，最后一个用于 source 为 null 的行
+struct TailBuffer {
+    capacity: usize,
+    lines: Vec<VecDeque<LogLine>>,
+}
+
+impl TailBuffer {
+    fn new(sources: usize, capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: vec![VecDeque::new(); sources + 1],
+        }
+    }
+
+    fn push(&mut self, line: LogLine) {
+        let index = line.source.unwrap_or(self.lines.len() - 1);
+        let Some(lines) = self.lines.get_mut(index) else {
+            return;
+        };
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+/// 包含与排除规则，在本地对每一行求值
+pub struct LineFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl LineFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> AppResult<Self> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| {
+                    Regex::new(p).map_err(|e| {
+                        AppError::invalid_input(format!("无效的正则表达式: {}", p)).with_details(e)
+                    })
+                })
+                .collect::<AppResult<Vec<_>>>()
+        };
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|r| r.is_match(text)))
+            && !self.exclude.iter().any(|r| r.is_match(text))
+    }
+}
+
+/// 在一个通道上跟踪全部来源，过滤后的行通过 log-lines-{stream_id} 事件批量发送，
+/// 用 StopStream 停止
+pub async fn tail_logs(
+    id: usize,
+    session: &Session,
+    options: LogTailOptions,
+    sink: Sink,
+) -> AppResult<StreamOpened> {
+    if options.sources.is_empty() {
+        return Err(AppError::invalid_input("至少选择一个日志来源"));
+    }
+    let filter = LineFilter::new(&options.include, &options.exclude)?;
+    let command = tail_command(&options)?;
+    let buffer = Arc::new(Mutex::new(TailBuffer::new(
+        options.sources.len(),
+        options.buffer_lines.unwrap_or(DEFAULT_BUFFER_LINES).max(1),
+    )));
+    let sources = options.sources;
+    // 在任务启动前登记缓存，避免命令立即结束时留下无人清理的记录
+    streams::spawn_stream(id, &session.session, &command, |stream_id| {
+        lock(&TAILS).insert(stream_id, buffer.clone());
+        LogTail {
+            id,
+            stream_id,
+            sources,
+            filter,
+            buffer,
+            partial: String::new(),
+            pending: Vec::new(),
+            sink,
+        }
+    })
+    .await
+}
+
+/// 查询日志流缓存的最近几行，按来源顺序排列，用于界面重新打开时恢复内容
+pub fn buffered_lines(stream_id: u64) -> AppResult<Vec<LogLine>> {
+    let buffer = lock(&TAILS)
+        .get(&stream_id)
+        .cloned()
+        .ok_or_else(|| AppError::not_found(format!("日志流 {} 不存在", stream_id)))?;
+    let buffer = lock(&buffer);
+    Ok(buffer.lines.iter().flatten().cloned().collect())
+}
+
+// 缓存只在同步代码中短暂持有，锁中毒时继续使用其中的数据
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 生成远端脚本：每个来源在后台运行，输出的每一行加上 "下标\t" 前缀，
+/// 收到 TERM 时结束整个进程组
+pub fn tail_command(options: &LogTailOptions) -> AppResult<String> {
+    let lines = options.initial_lines.unwrap_or(DEFAULT_INITIAL_LINES);
+    let mut script = String::from("trap \"kill 0\" TERM HUP; ");
+    for (index, source) in options.sources.iter().enumerate() {
+        let command = match source {
+            LogSource::File(path) => {
+                if path.trim().is_empty() {
+                    return Err(AppError::invalid_input("日志文件路径不能为空"));
+                }
+                format!("tail -n {} -F {}", lines, shell_quote(path))
+            }
+            LogSource::Unit(unit) => {
+                systemd::validate_unit(unit)?;
+                format!(
+                    "journalctl -u {} -f -n {} -o json --no-pager",
+                    shell_quote(unit),
+                    lines
+                )
+            }
+        };
+        script.push_str(&format!(
+            "({} 2>&1 | while IFS= read -r line; do printf '{}\\t%s\\n' \"$line\"; done) & ",
+            command, index
+        ));
+    }
+    script.push_str("wait");
+    let sudo = if options.sudo { "sudo -n " } else { "" };
+    Ok(format!("{}sh -c {} 2>&1", sudo, shell_quote(&script)))
+}
+
+/// 解析远端输出的一行，journal 来源的 JSON 记录转为 "标识[PID]: 消息"
+pub fn parse_line(raw: &str, sources: &[LogSource]) -> LogLine {
+    let prefixed = raw
+        .split_once('\t')
+        .and_then(|(index, text)| Some((index.parse::<usize>().ok()?, text)))
+        .filter(|(index, _)| *index < sources.len());
+    let Some((index, text)) = prefixed else {
+        return LogLine {
+            source: None,
+            timestamp: None,
+            severity: detect_severity(raw),
+            text: raw.to_string(),
+        };
+    };
+    if let LogSource::Unit(_) = sources[index] {
+        if let Some(line) = parse_journal_line(index, text) {
+            return line;
+        }
+    }
+    LogLine {
+        source: Some(index),
+        timestamp: None,
+        severity: detect_severity(text),
+        text: text.to_string(),
+    }
+}
+
+fn parse_journal_line(index: usize, text: &str) -> Option<LogLine> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    let entry = systemd::parse_journal(text).pop()?;
+    let identifier = value["SYSLOG_IDENTIFIER"].as_str().unwrap_or("");
+    let text = match value["_PID"].as_str() {
+        Some(pid) if !identifier.is_empty() => {
+            format!("{}[{}]: {}", identifier, pid, entry.message)
+        }
+        _ if !identifier.is_empty() => format!("{}: {}", identifier, entry.message),
+        _ => entry.message,
+    };
+    Some(LogLine {
+        source: Some(index),
+        timestamp: Some(entry.timestamp).filter(|t| *t > 0),
+        severity: entry.priority.map(severity_from_priority),
+        text,
+    })
+}
+
+fn severity_from_priority(priority: u8) -> Severity {
+    match priority {
+        0..=3 => Severity::Error,
+        4 => Severity::Warning,
+        5 | 6 => Severity::Info,
+        _ => Severity::Debug,
+    }
+}
+
+/// 按关键字识别日志级别，取行中最先出现的关键字
+pub fn detect_severity(text: &str) -> Option<Severity> {
+    SEVERITY_PATTERNS
+        .iter()
+        .filter_map(|(severity, pattern)| pattern.find(text).map(|m| (m.start(), *severity)))
+        .min_by_key(|(start, _)| *start)
+        .map(|(_, severity)| severity)
+}
+
+struct LogTail {
+    id: usize,
+    stream_id: u64,
+    sources: Vec<LogSource>,
+    filter: LineFilter,
+    buffer: Arc<Mutex<TailBuffer>>,
+    // 尚未收到换行的部分
+    partial: String,
+    pending: Vec<LogLine>,
+    sink: Sink,
+}
+
+impl LogTail {
+    fn accept(&mut self, raw: &str) {
+        let raw = raw.trim_end_matches('\r');
+        if raw.is_empty() {
+            return;
+        }
+        let line = parse_line(raw, &self.sources);
+        if !self.filter.matches(&line.text) {
+            return;
+        }
+        lock(&self.buffer).push(line.clone());
+        self.pending.push(line);
+        if self.pending.len() >= MAX_BATCH_LINES {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        self.sink.emit(
+            &format!("log-lines-{}", self.stream_id),
+            LogBatch {
+                stream_id: self.stream_id,
+                id: self.id,
+                lines: std::mem::take(&mut self.pending),
+            },
+        );
+    }
+}
+
+impl StreamHandler for LogTail {
+    fn output(&mut self, text: &str) {
+        self.partial.push_str(text);
+        while let Some(end) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=end).collect();
+            self.accept(&line[..line.len() - 1]);
+        }
+    }
+
+    fn tick(&mut self) {
+        self.flush();
+    }
+
+    fn closed(&mut self, exit_status: Option<u32>) {
+        let rest = std::mem::take(&mut self.partial);
+        self.accept(&rest);
+        self.flush();
+        lock(&TAILS).remove(&self.stream_id);
+        streams::emit_closed(self.sink.as_ref(), self.stream_id, self.id, exit_status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources() -> Vec<LogSource> {
+        vec![
+            LogSource::File("/var/log/nginx/error.log".to_string()),
+            LogSource::Unit("nginx.service".to_string()),
+        ]
+    }
+
+    #[test]
+    fn detects_severity_by_first_keyword() {
+        assert_eq!(
+            detect_severity("2024/05/06 10:00:00 [error] 123#0: connect() failed"),
+            Some(Severity::Error)
+        );
+        assert_eq!(
+            detect_severity("INFO retrying after error in upstream"),
+            Some(Severity::Info)
+        );
+        assert_eq!(
+            detect_severity("May  6 10:00:00 host kernel: WARNING: CPU: 1 PID: 0"),
+            Some(Severity::Warning)
+        );
+        assert_eq!(detect_severity("GET /index.html 200"), None);
+        // 单词的一部分不算
+        assert_eq!(detect_severity("terrible informational"), None);
+    }
+
+    #[test]
+    fn parses_prefixed_file_line() {
+        let line = parse_line("0\t[warn] 12#12: low disk space", &sources());
+        assert_eq!(line.source, Some(0));
+        assert_eq!(line.severity, Some(Severity::Warning));
+        assert_eq!(line.text, "[warn] 12#12: low disk space");
+    }
+
+    #[test]
+    fn parses_journal_line() {
+        let raw = r#"1	{"__REALTIME_TIMESTAMP":"1715000000123456","PRIORITY":"3","SYSLOG_IDENTIFIER":"nginx","_PID":"812","MESSAGE":"bind() failed"}"#;
+        let line = parse_line(raw, &sources());
+        assert_eq!(line.source, Some(1));
+        assert_eq!(line.timestamp, Some(1715000000123));
+        assert_eq!(line.severity, Some(Severity::Error));
+        assert_eq!(line.text, "nginx[812]: bind() failed");
+    }
+
+    #[test]
+    fn unprefixed_output_has_no_source() {
+        let line = parse_line("sudo: a password is required", &sources());
+        assert_eq!(line.source, None);
+        // 下标越界同样视为 shell 自身的输出
+        assert_eq!(parse_line("7\tstray", &sources()).source, None);
+    }
+
+    #[test]
+    fn filters_lines() {
+        let filter = LineFilter::new(
+            &["nginx".to_string(), "sshd".to_string()],
+            &["healthcheck".to_string()],
+        )
+        .unwrap();
+        assert!(filter.matches("nginx: started"));
+        assert!(!filter.matches("nginx: GET /healthcheck"));
+        assert!(!filter.matches("cron: job done"));
+        assert!(LineFilter::new(&["(".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn buffer_keeps_last_lines_per_source() {
+        let mut buffer = TailBuffer::new(2, 2);
+        for text in ["a", "b", "c"] {
+            buffer.push(parse_line(&format!("0\t{}", text), &sources()));
+        }
+        buffer.push(parse_line("1\tplain", &sources()));
+        let texts: Vec<&str> = buffer.lines[0].iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["b", "c"]);
+        assert_eq!(buffer.lines[1].len(), 1);
+    }
+
+    #[test]
+    fn builds_single_channel_command() {
+        let options = LogTailOptions {
+            sources: sources(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            buffer_lines: None,
+            initial_lines: Some(10),
+            sudo: true,
+        };
+        let command = tail_command(&options).unwrap();
+        assert!(command.starts_with("sudo -n sh -c 'trap \"kill 0\" TERM HUP; "));
+        assert!(command.contains("tail -n 10 -F /var/log/nginx/error.log 2>&1"));
+        assert!(command.contains("journalctl -u nginx.service -f -n 10 -o json"));
+        assert!(command.contains(r"printf '\''1\t%s\n'\''"));
+        assert!(command.ends_with("wait' 2>&1"));
+    }
+}
@@ -0,0 +1,442 @@
+use crate::error::AppResult;
+use crate::events::{CpuUsage, DiskTotals, DiskUsage, MemoryUsage};
+use crate::platform::CollectorStrategy;
+use crate::session::Session;
+
+// df 输出中需要忽略的伪文件系统
+const PSEUDO_FILESYSTEMS: [&str; 9] = [
+    "tmpfs", "devtmpfs", "devfs", "udev", "none", "shm", "overlay", "fdescfs", "procfs",
+];
+
+/// 磁盘汇总，文本形式用于展示，数值单位为 KB
+pub struct DiskReport {
+    pub usage: DiskUsage,
+    pub totals: DiskTotals,
+}
+
+pub async fn uptime(session: &Session) -> AppResult<String> {
+    Ok(parse_uptime(&session.call("uptime").await?))
+}
+
+pub async fn memory(session: &Session, strategy: CollectorStrategy) -> AppResult<MemoryUsage> {
+    Ok(match strategy {
+        CollectorStrategy::Procps => parse_free(&session.call("free -m").await?),
+        CollectorStrategy::ProcFs => parse_meminfo(&session.call("cat /proc/meminfo").await?),
+        CollectorStrategy::FreeBsd => parse_freebsd_memory(
+            &session
+                .call("sysctl -n hw.physmem hw.pagesize vm.stats.vm.v_free_count vm.stats.vm.v_inactive_count")
+                .await?,
+        ),
+    })
+}
+
+pub async fn cpu(session: &Session, strategy: CollectorStrategy) -> AppResult<CpuUsage> {
+    let command = match strategy {
+        CollectorStrategy::Procps => "top -bn1 | grep 'Cpu(s)'",
+        CollectorStrategy::ProcFs => "top -bn1 | grep '^CPU:'",
+        // 第一屏是开机以来的平均值，取第二屏
+        CollectorStrategy::FreeBsd => "top -b -d 2 | grep '^CPU:' | tail -n 1",
+    };
+    Ok(parse_cpu(&session.call(command).await?))
+}
+
+/// 累计收发字节数 (rx, tx)
+pub async fn network_bytes(
+    session: &Session,
+    strategy: CollectorStrategy,
+) -> AppResult<(u64, u64)> {
+    Ok(match strategy {
+        CollectorStrategy::Procps | CollectorStrategy::ProcFs => {
+            parse_proc_net_dev(&session.call("cat /proc/net/dev").await?)
+        }
+        CollectorStrategy::FreeBsd => parse_netstat(&session.call("netstat -ibn").await?),
+    })
+}
+
+pub async fn disk(session: &Session, strategy: CollectorStrategy) -> AppResult<DiskReport> {
+    Ok(match strategy {
+        CollectorStrategy::Procps => parse_df_total(&session.call("df -h --total").await?),
+        // BusyBox 与 BSD 的 df 没有 --total，自行汇总
+        CollectorStrategy::ProcFs | CollectorStrategy::FreeBsd => {
+            parse_df_posix(&session.call("df -kP").await?)
+        }
+    })
+}
+
+pub fn parse_uptime(output: &str) -> String {
+    output.split(',').next().unwrap_or("").trim().to_string()
+}
+
+/// free -m 的 Mem 行
+pub fn parse_free(output: &str) -> MemoryUsage {
+    let line = output.lines().find(|l| l.starts_with("Mem:")).unwrap_or("");
+    let parts: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .map(|p| p.parse().unwrap_or(0))
+        .collect();
+    MemoryUsage {
+        total: parts.first().copied().unwrap_or(0),
+        used: parts.get(1).copied().unwrap_or(0),
+        free: parts.get(2).copied().unwrap_or(0),
+    }
+}
+
+/// /proc/meminfo，单位为 kB，换算为 MB；没有 MemAvailable 的旧内核扣除缓存
+pub fn parse_meminfo(output: &str) -> MemoryUsage {
+    let field = |name: &str| {
+        output.lines().find_map(|line| {
+            let value = line.strip_prefix(name)?.strip_prefix(':')?;
+            value.split_whitespace().next()?.parse::<u64>().ok()
+        })
+    };
+    let total = field("MemTotal").unwrap_or(0);
+    let free = field("MemFree").unwrap_or(0);
+    let available = field("MemAvailable")
+        .unwrap_or_else(|| free + field("Buffers").unwrap_or(0) + field("Cached").unwrap_or(0));
+    MemoryUsage {
+        total: total / 1024,
+        used: total.saturating_sub(available) / 1024,
+        free: free / 1024,
+    }
+}
+
+/// sysctl 依次输出物理内存字节数、页大小、空闲页数与不活跃页数
+pub fn parse_freebsd_memory(output: &str) -> MemoryUsage {
+    let values: Vec<u64> = output
+        .lines()
+        .map(|l| l.trim().parse().unwrap_or(0))
+        .collect();
+    let value = |i: usize| values.get(i).copied().unwrap_or(0);
+    let total = value(0);
+    let free = value(2) * value(1);
+    let available = free + value(3) * value(1);
+    MemoryUsage {
+        total: total / 1024 / 1024,
+        used: total.saturating_sub(available) / 1024 / 1024,
+        free: free / 1024 / 1024,
+    }
+}
+
+/// 兼容 procps（12.5 us）、BusyBox（2% usr）与 FreeBSD（0.4% user）的 top 输出
+pub fn parse_cpu(output: &str) -> CpuUsage {
+    let line = output.lines().last().unwrap_or("");
+    let values = line.split_once(':').map(|(_, v)| v).unwrap_or("");
+    let mut user = 0.0;
+    let mut system = 0.0;
+    let mut number = None;
+    for token in values.split(|c: char| c.is_whitespace() || c == ',') {
+        if token.is_empty() {
+            continue;
+        }
+        if let Ok(value) = token.trim_end_matches('%').parse::<f32>() {
+            number = Some(value);
+            continue;
+        }
+        match (token, number.take()) {
+            ("us" | "usr" | "user", Some(value)) => user = value,
+            ("sy" | "sys" | "system", Some(value)) => system = value,
+            _ => {}
+        }
+    }
+    CpuUsage {
+        user,
+        system,
+        total: user + system,
+    }
+}
+
+/// /proc/net/dev 中以太网卡的累计字节数
+pub fn parse_proc_net_dev(output: &str) -> (u64, u64) {
+    let mut rx_bytes = 0;
+    let mut tx_bytes = 0;
+    for line in output.lines() {
+        if line.contains("eth0") || line.contains("ens") {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() > 9 {
+                rx_bytes += parts[1].parse::<u64>().unwrap_or(0);
+                tx_bytes += parts[9].parse::<u64>().unwrap_or(0);
+            }
+        }
+    }
+    (rx_bytes, tx_bytes)
+}
+
+/// netstat -ibn 中各网卡 <Link#n> 行的累计字节数，跳过回环
+pub fn parse_netstat(output: &str) -> (u64, u64) {
+    let mut rx_bytes = 0;
+    let mut tx_bytes = 0;
+    for line in output.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let is_link = parts.get(2).is_some_and(|n| n.starts_with("<Link#"));
+        if !is_link || parts[0].starts_with("lo") || parts.len() < 11 {
+            continue;
+        }
+        // Address 列可能为空，从行尾定位：Ibytes Opkts Oerrs Obytes Coll
+        let n = parts.len();
+        rx_bytes += parts[n - 5].parse::<u64>().unwrap_or(0);
+        tx_bytes += parts[n - 2].parse::<u64>().unwrap_or(0);
+    }
+    (rx_bytes, tx_bytes)
+}
+
+/// GNU df -h --total 的 total 行
+pub fn parse_df_total(output: &str) -> DiskReport {
+    let parts: Vec<&str> = output
+        .lines()
+        .last()
+        .unwrap_or("")
+        .split_whitespace()
+        .collect();
+    let column = |i: usize, default: &str| parts.get(i).unwrap_or(&default).to_string();
+    let usage = DiskUsage {
+        total: column(1, "0"),
+        used: column(2, "0"),
+        available: column(3, "0"),
+        use_percentage: column(4, "0%"),
+    };
+    let totals = DiskTotals {
+        used: parse_size_kb(&usage.used),
+        available: parse_size_kb(&usage.available),
+    };
+    DiskReport { usage, totals }
+}
+
+/// POSIX df -kP 输出，汇总真实文件系统；ZFS 数据集共享存储池的可用空间，每个池只计一次
+pub fn parse_df_posix(output: &str) -> DiskReport {
+    let mut used = 0;
+    let mut available = 0;
+    let mut pools: Vec<&str> = Vec::new();
+    for line in output.lines().skip(1) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 6 {
+            continue;
+        }
+        let filesystem = parts[0];
+        if PSEUDO_FILESYSTEMS.contains(&filesystem) || filesystem.contains(':') {
+            continue;
+        }
+        let blocks = |i: usize| parts[i].parse::<u64>().unwrap_or(0);
+        if blocks(1) == 0 {
+            continue;
+        }
+        used += blocks(2);
+        if filesystem.starts_with('/') {
+            available += blocks(3);
+        } else {
+            let pool = filesystem.split('/').next().unwrap_or(filesystem);
+            if !pools.contains(&pool) {
+                pools.push(pool);
+                available += blocks(3);
+            }
+        }
+    }
+    let total = used + available;
+    let percentage = if total == 0 {
+        0
+    } else {
+        (used * 100).div_ceil(total)
+    };
+    DiskReport {
+        usage: DiskUsage {
+            total: format_size_kb(total),
+            used: format_size_kb(used),
+            available: format_size_kb(available),
+            use_percentage: format!("{}%", percentage),
+        },
+        totals: DiskTotals { used, available },
+    }
+}
+
+/// 把 df -h 风格的 20G、1.5T 换算为 KB
+fn parse_size_kb(size: &str) -> u64 {
+    let (number, unit) = size.split_at(
+        size.find(|c: char| c.is_ascii_alphabetic())
+            .unwrap_or(size.len()),
+    );
+    let Ok(number) = number.parse::<f64>() else {
+        return 0;
+    };
+    let exponent = match unit {
+        "" | "K" => 0,
+        "M" => 1,
+        "G" => 2,
+        "T" => 3,
+        "P" => 4,
+        _ => return 0,
+    };
+    (number * 1024f64.powi(exponent)) as u64
+}
+
+/// 按 df -h 的习惯显示：小于 10 时保留一位小数
+fn format_size_kb(kb: u64) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+    let mut value = kb as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 && unit > 0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_uptime() {
+        // procps、BusyBox 与 FreeBSD
+        let cases = [
+            (
+                " 10:00:00 up 3 days,  4:05,  1 user,  load average: 0.10, 0.20, 0.30\n",
+                "10:00:00 up 3 days",
+            ),
+            (
+                " 08:12:44 up 12 min,  load average: 0.00, 0.01, 0.00\n",
+                "08:12:44 up 12 min",
+            ),
+            (
+                "10:00AM  up 21 days, 18:02, 1 user, load averages: 0.31, 0.25, 0.22\n",
+                "10:00AM  up 21 days",
+            ),
+        ];
+        for (output, expected) in cases {
+            assert_eq!(parse_uptime(output), expected);
+        }
+    }
+
+    #[test]
+    fn parses_procps_free() {
+        let output = "\
+              total        used        free      shared  buff/cache   available
+Mem:           7962        2310        3120         120        2531        5221
+Swap:          2047           0        2047
+";
+        let memory = parse_free(output);
+        assert_eq!((memory.total, memory.used, memory.free), (7962, 2310, 3120));
+    }
+
+    #[test]
+    fn parses_busybox_meminfo() {
+        let output = "\
+MemTotal:         124412 kB
+MemFree:           61484 kB
+MemAvailable:      80220 kB
+Buffers:            2808 kB
+Cached:            26676 kB
+";
+        let memory = parse_meminfo(output);
+        assert_eq!((memory.total, memory.used, memory.free), (121, 43, 60));
+    }
+
+    #[test]
+    fn parses_meminfo_without_available() {
+        let output = "\
+MemTotal:          61440 kB
+MemFree:           20480 kB
+Buffers:           10240 kB
+Cached:            10240 kB
+";
+        let memory = parse_meminfo(output);
+        assert_eq!((memory.total, memory.used, memory.free), (60, 20, 20));
+    }
+
+    #[test]
+    fn parses_freebsd_sysctl_memory() {
+        let output = "8522825728\n4096\n1500000\n250000\n";
+        let memory = parse_freebsd_memory(output);
+        assert_eq!(memory.total, 8128);
+        assert_eq!(memory.free, 5859);
+        assert_eq!(memory.used, 1292);
+    }
+
+    #[test]
+    fn parses_top_from_each_platform() {
+        let procps =
+            "%Cpu(s):  12.5 us,  3.1 sy,  0.0 ni, 84.0 id,  0.2 wa,  0.0 hi,  0.2 si,  0.0 st\n";
+        let busybox = "CPU:   2% usr   1% sys   0% nic  96% idle   0% io   0% irq   0% sirq\n";
+        let freebsd = "CPU:  0.0% user,  0.0% nice,  0.0% system,  0.0% interrupt,  100% idle\n\
+CPU:  4.3% user,  0.0% nice,  1.2% system,  0.1% interrupt, 94.4% idle\n";
+
+        let cpu = parse_cpu(procps);
+        assert_eq!((cpu.user, cpu.system), (12.5, 3.1));
+        let cpu = parse_cpu(busybox);
+        assert_eq!((cpu.user, cpu.system, cpu.total), (2.0, 1.0, 3.0));
+        let cpu = parse_cpu(freebsd);
+        assert_eq!((cpu.user, cpu.system), (4.3, 1.2));
+    }
+
+    #[test]
+    fn parses_proc_net_dev() {
+        let output = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:  104857     1000    0    0    0     0          0         0   104857     1000    0    0    0     0       0          0
+  eth0: 5242880     4000    0    0    0     0          0         0  1048576     3000    0    0    0     0       0          0
+";
+        assert_eq!(parse_proc_net_dev(output), (5242880, 1048576));
+    }
+
+    #[test]
+    fn parses_freebsd_netstat() {
+        let output = "\
+Name    Mtu Network       Address              Ipkts Ierrs Idrop     Ibytes    Opkts Oerrs     Obytes  Coll
+vtnet0 1500 <Link#1>      52:54:00:12:34:56   120000     0     0   90000000    80000     0    7000000     0
+vtnet0    - 10.0.0.0/24   10.0.0.5            119000     -     -   89000000    79000     -    6900000     -
+lo0   16384 <Link#2>      lo0                   2000     0     0     200000     2000     0     200000     0
+";
+        assert_eq!(parse_netstat(output), (90000000, 7000000));
+    }
+
+    #[test]
+    fn parses_gnu_df_total() {
+        let output = "\
+Filesystem      Size  Used Avail Use% Mounted on
+/dev/sda1        50G   20G   28G  42% /
+total            50G   20G   28G  42% -
+";
+        let report = parse_df_total(output);
+        assert_eq!(report.usage.total, "50G");
+        assert_eq!(report.usage.use_percentage, "42%");
+        assert_eq!(report.totals.used, 20 * 1024 * 1024);
+    }
+
+    #[test]
+    fn parses_busybox_df() {
+        // OpenWrt：只读根分区、overlay 与 tmpfs
+        let output = "\
+Filesystem           1024-blocks    Used Available Capacity Mounted on
+/dev/root                 4352      4352         0 100% /rom
+tmpfs                    61440       192     61248   0% /tmp
+/dev/ubi0_1              84516      1204     79044   1% /overlay
+overlayfs:/overlay       84516      1204     79044   1% /
+tmpfs                      512         0       512   0% /dev
+";
+        let report = parse_df_posix(output);
+        assert_eq!(report.totals.used, 5556);
+        assert_eq!(report.totals.available, 79044);
+        assert_eq!(report.usage.total, "83M");
+        assert_eq!(report.usage.use_percentage, "7%");
+    }
+
+    #[test]
+    fn parses_freebsd_zfs_df() {
+        let output = "\
+Filesystem         1024-blocks    Used     Avail Capacity  Mounted on
+zroot/ROOT/default    28000000 2000000  26000000     7%    /
+devfs                        1       1         0   100%    /dev
+zroot/tmp             26000200     200  26000000     0%    /tmp
+zroot/usr/home        26100000  100000  26000000     0%    /usr/home
+";
+        let report = parse_df_posix(output);
+        assert_eq!(report.totals.used, 2100200);
+        assert_eq!(report.totals.available, 26000000);
+        assert_eq!(report.usage.available, "25G");
+        assert_eq!(report.usage.use_percentage, "8%");
+    }
+}
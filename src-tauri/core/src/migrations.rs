@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::sled::{current_timestamp, SshConnection};
+
+/// 存放 schema 版本等元数据的 tree
+const META_TREE: &str = "meta";
+/// 无法解析的连接记录会被移动到这里，避免拖垮整个列表
+const QUARANTINE_TREE: &str = "quarantine";
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+type Migration = fn(&Db) -> AppResult<()>;
+
+/// 按顺序排列的迁移，第 N 项把 schema 从版本 N 升级到 N + 1。
+/// 只能在末尾追加，不能修改或删除已发布的迁移
+const MIGRATIONS: &[Migration] = &[normalize_connections];
+
+/// 当前代码期望的 schema 版本
+pub const SCHEMA_VERSION: u64 = MIGRATIONS.len() as u64;
+
+#[derive(Serialize, Deserialize, Debug, TS)]
+pub struct QuarantinedRecord {
+    pub key: String,
+    pub raw: String,
+    pub error: String,
+    #[ts(type = "number | null")]
+    pub quarantined_at: Option<u64>,
+}
+
+/// 打开数据库后调用，依次执行尚未应用的迁移
+pub fn run_migrations(db: &Db) -> AppResult<()> {
+    let current = schema_version(db)?;
+    if current > SCHEMA_VERSION {
+        return Err(AppError::db(format!(
+            "数据库 schema 版本 {} 高于当前程序支持的版本 {}",
+            current, SCHEMA_VERSION
+        )));
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let target = index as u64 + 1;
+        println!("执行数据库迁移: v{} -> v{}", index, target);
+        migration(db)?;
+        set_schema_version(db, target)?;
+    }
+    db.flush()?;
+    Ok(())
+}
+
+pub fn schema_version(db: &Db) -> AppResult<u64> {
+    let meta = db.open_tree(META_TREE)?;
+    match meta.get(SCHEMA_VERSION_KEY)? {
+        Some(value) => {
+            let bytes: [u8; 8] = value
+                .as_ref()
+                .try_into()
+                .map_err(|_| AppError::db("schema 版本格式错误"))?;
+            Ok(u64::from_be_bytes(bytes))
+        }
+        None => Ok(0),
+    }
+}
+
+fn set_schema_version(db: &Db, version: u64) -> AppResult<()> {
+    let meta = db.open_tree(META_TREE)?;
+    meta.insert(SCHEMA_VERSION_KEY, version.to_be_bytes().to_vec())?;
+    Ok(())
+}
+
+/// 把无法解析的记录从连接表移到隔离区
+pub fn quarantine(db: &Db, key: &[u8], value: &[u8], error: &str) -> AppResult<()> {
+    let tree = db.open_tree(QUARANTINE_TREE)?;
+    let record = QuarantinedRecord {
+        key: String::from_utf8_lossy(key).to_string(),
+        raw: String::from_utf8_lossy(value).to_string(),
+        error: error.to_string(),
+        quarantined_at: Some(current_timestamp()),
+    };
+    let record = serde_json::to_vec(&record)?;
+    tree.insert(key, record)?;
+    db.remove(key)?;
+    eprintln!(
+        "连接记录 {} 无法解析，已移至隔离区: {}",
+        String::from_utf8_lossy(key),
+        error
+    );
+    Ok(())
+}
+
+pub fn list_quarantined(db: &Db) -> AppResult<Vec<QuarantinedRecord>> {
+    let tree = db.open_tree(QUARANTINE_TREE)?;
+    let mut records = Vec::new();
+    for item in tree.iter() {
+        let (key, value) = item?;
+        let record = serde_json::from_slice(&value).unwrap_or_else(|e| QuarantinedRecord {
+            key: String::from_utf8_lossy(&key).to_string(),
+            raw: String::from_utf8_lossy(&value).to_string(),
+            error: e.to_string(),
+            quarantined_at: None,
+        });
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// v0 -> v1: 用当前结构重写所有连接记录，补齐新增字段的默认值，
+// 并隔离无法解析的旧记录
+fn normalize_connections(db: &Db) -> AppResult<()> {
+    for item in db.iter() {
+        let (key, value) = item?;
+        match serde_json::from_slice::<SshConnection>(&value) {
+            Ok(connection) => {
+                let value = serde_json::to_vec(&connection)?;
+                db.insert(key, value)?;
+            }
+            Err(e) => quarantine(db, &key, &value, &e.to_string())?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_db() -> Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    #[test]
+    fn normalizes_old_records_and_quarantines_broken_ones() {
+        let db = temp_db();
+        let old = r#"{"id":1,"name":"web","host":"10.0.0.5","port":22,"username":"root"}"#;
+        db.insert("1", old).unwrap();
+        db.insert("2", "not json").unwrap();
+
+        run_migrations(&db).unwrap();
+        assert_eq!(schema_version(&db).unwrap(), SCHEMA_VERSION);
+
+        let value = db.get("1").unwrap().unwrap();
+        let record: serde_json::Value = serde_json::from_slice(&value).unwrap();
+        assert_eq!(record["name"], "web");
+        assert_eq!(record["local_forwards"], serde_json::json!([]));
+        assert_eq!(record["connect_count"], 0);
+
+        assert!(db.get("2").unwrap().is_none());
+        let quarantined = list_quarantined(&db).unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].key, "2");
+        assert_eq!(quarantined[0].raw, "not json");
+        assert!(quarantined[0].quarantined_at.is_some());
+    }
+
+    #[test]
+    fn skips_applied_migrations_and_rejects_newer_schema() {
+        let db = temp_db();
+        run_migrations(&db).unwrap();
+        // 已是最新版本时不再重写记录
+        db.insert("3", "not json").unwrap();
+        run_migrations(&db).unwrap();
+        assert!(db.get("3").unwrap().is_some());
+        assert!(list_quarantined(&db).unwrap().is_empty());
+
+        set_schema_version(&db, SCHEMA_VERSION + 1).unwrap();
+        assert!(run_migrations(&db).is_err());
+    }
+}
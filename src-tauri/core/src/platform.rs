@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use ts_rs::TS;
+
+use crate::error::AppResult;
+use crate::session::Session;
+use crate::sled::current_timestamp;
+
+/// 远端环境信息 tree，键为连接 ID
+const PROFILES_TREE: &str = "host_profiles";
+
+/// 连接后执行一次的探测脚本，各段以 ==名称 开头。登录 shell 可能是 csh，
+/// 所以交给 sh 执行，脚本中不能出现单引号
+const PROBE_SCRIPT: &str = "sh -c 'echo ==uname; uname -s; uname -r; uname -m; \
+echo ==os-release; cat /etc/os-release 2>/dev/null; \
+echo ==shell; echo \"$SHELL\"; \
+echo ==init; cat /proc/1/comm 2>/dev/null || ps -p 1 -o comm= 2>/dev/null; \
+echo ==busybox; readlink \"$(command -v uptime)\" 2>/dev/null; \
+echo ==tools; for t in free top sysctl netstat df systemctl journalctl rc-service docker podman sudo; \
+do command -v \"$t\" >/dev/null 2>&1 && echo \"$t\"; done; exit 0'";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, TS)]
+pub enum OsFamily {
+    Linux,
+    FreeBsd,
+    Darwin,
+    #[default]
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, TS)]
+pub enum InitSystem {
+    Systemd,
+    OpenRc,
+    // OpenWrt 的 procd
+    Procd,
+    SysV,
+    // BSD 的 /etc/rc
+    BsdRc,
+    Launchd,
+    #[default]
+    Unknown,
+}
+
+/// 服务器信息的采集方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectorStrategy {
+    // procps 提供的 free、top 与 GNU df
+    Procps,
+    // 直接读取 /proc，用于 BusyBox 等精简环境
+    ProcFs,
+    // sysctl、netstat 与 POSIX df
+    FreeBsd,
+}
+
+/// 连接时探测到的远端环境
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, TS)]
+pub struct HostProfile {
+    pub os: OsFamily,
+    // os-release 中的 ID，例如 ubuntu、alpine、openwrt
+    pub distro: Option<String>,
+    pub distro_name: Option<String>,
+    pub distro_version: Option<String>,
+    pub kernel: Option<String>,
+    pub arch: Option<String>,
+    pub init_system: InitSystem,
+    pub shell: Option<String>,
+    // 常用命令由 BusyBox 提供
+    pub busybox: bool,
+    // 探测到的可用命令
+    pub tools: Vec<String>,
+    #[ts(type = "number | null")]
+    pub detected_at: Option<u64>,
+}
+
+impl HostProfile {
+    pub fn has_tool(&self, tool: &str) -> bool {
+        self.tools.iter().any(|t| t == tool)
+    }
+
+    /// 按环境选择采集方式，探测失败时沿用 procps 命令
+    pub fn strategy(&self) -> CollectorStrategy {
+        match self.os {
+            OsFamily::FreeBsd => CollectorStrategy::FreeBsd,
+            OsFamily::Linux if self.busybox || !self.has_tool("free") => CollectorStrategy::ProcFs,
+            _ => CollectorStrategy::Procps,
+        }
+    }
+}
+
+/// 在远端执行探测脚本，脚本无法执行时返回未知环境
+pub async fn detect(session: &Session) -> AppResult<HostProfile> {
+    let (output, _) = session.call_with_status(PROBE_SCRIPT).await?;
+    let mut profile = parse_probe(&output);
+    profile.detected_at = Some(current_timestamp());
+    Ok(profile)
+}
+
+/// 解析探测脚本的输出
+pub fn parse_probe(output: &str) -> HostProfile {
+    let mut profile = HostProfile::default();
+    let mut init_comm = None;
+    for (section, body) in sections(output) {
+        match section {
+            "uname" => {
+                let mut lines = body.iter().map(|l| l.trim());
+                profile.os = lines.next().map(parse_os_family).unwrap_or_default();
+                profile.kernel = lines.next().filter(|l| !l.is_empty()).map(String::from);
+                profile.arch = lines.next().filter(|l| !l.is_empty()).map(String::from);
+            }
+            "os-release" => {
+                let release = parse_os_release(&body.join("\n"));
+                profile.distro = release.id;
+                profile.distro_name = release.pretty_name.or(release.name);
+                profile.distro_version = release.version_id;
+            }
+            "shell" => {
+                profile.shell = body
+                    .first()
+                    .map(|l| l.trim())
+                    .filter(|l| !l.is_empty())
+                    .map(String::from);
+            }
+            "init" => {
+                init_comm = body.first().map(|l| l.trim().to_string());
+            }
+            "busybox" => {
+                profile.busybox = body.iter().any(|l| l.contains("busybox"));
+            }
+            "tools" => {
+                profile.tools = body
+                    .iter()
+                    .map(|l| l.trim())
+                    .filter(|l| !l.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+    profile.init_system = detect_init_system(&profile, init_comm.as_deref().unwrap_or(""));
+    profile
+}
+
+fn sections(output: &str) -> Vec<(&str, Vec<&str>)> {
+    let mut sections: Vec<(&str, Vec<&str>)> = Vec::new();
+    for line in output.lines() {
+        if let Some(name) = line.strip_prefix("==") {
+            sections.push((name.trim(), Vec::new()));
+        } else if let Some((_, body)) = sections.last_mut() {
+            body.push(line);
+        }
+    }
+    sections
+}
+
+fn parse_os_family(kernel_name: &str) -> OsFamily {
+    match kernel_name {
+        "Linux" => OsFamily::Linux,
+        "FreeBSD" => OsFamily::FreeBsd,
+        "Darwin" => OsFamily::Darwin,
+        _ => OsFamily::Unknown,
+    }
+}
+
+fn detect_init_system(profile: &HostProfile, init_comm: &str) -> InitSystem {
+    // 容器或精简环境中 PID 1 可能只是 init，借助发行版与工具判断
+    match init_comm.rsplit('/').next().unwrap_or("") {
+        "systemd" => InitSystem::Systemd,
+        "procd" => InitSystem::Procd,
+        "launchd" => InitSystem::Launchd,
+        _ if profile.os == OsFamily::FreeBsd => InitSystem::BsdRc,
+        _ if profile.has_tool("rc-service") => InitSystem::OpenRc,
+        _ if profile.distro.as_deref() == Some("openwrt") => InitSystem::Procd,
+        "init" => InitSystem::SysV,
+        _ => InitSystem::Unknown,
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct OsRelease {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub pretty_name: Option<String>,
+    pub version_id: Option<String>,
+}
+
+/// 解析 /etc/os-release，值可能带单引号或双引号
+pub fn parse_os_release(content: &str) -> OsRelease {
+    let mut release = OsRelease::default();
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        if value.is_empty() {
+            continue;
+        }
+        let field = match key {
+            "ID" => &mut release.id,
+            "NAME" => &mut release.name,
+            "PRETTY_NAME" => &mut release.pretty_name,
+            "VERSION_ID" => &mut release.version_id,
+            _ => continue,
+        };
+        *field = Some(value.to_string());
+    }
+    release
+}
+
+fn profiles_tree(db: &Db) -> AppResult<Tree> {
+    Ok(db.open_tree(PROFILES_TREE)?)
+}
+
+pub fn save_profile(db: &Db, id: u64, profile: &HostProfile) -> AppResult<()> {
+    profiles_tree(db)?.insert(id.to_string(), serde_json::to_vec(profile)?)?;
+    Ok(())
+}
+
+/// 查询上次连接时探测到的环境，从未连接过时返回 None
+pub fn get_profile(db: &Db, id: u64) -> AppResult<Option<HostProfile>> {
+    match profiles_tree(db)?.get(id.to_string())? {
+        Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UBUNTU: &str = "\
+==uname
+Linux
+5.15.0-105-generic
+x86_64
+==os-release
+PRETTY_NAME=\"Ubuntu 22.04.4 LTS\"
+NAME=\"Ubuntu\"
+VERSION_ID=\"22.04\"
+VERSION=\"22.04.4 LTS (Jammy Jellyfish)\"
+ID=ubuntu
+ID_LIKE=debian
+==shell
+/bin/bash
+==init
+systemd
+==busybox
+==tools
+free
+top
+df
+systemctl
+journalctl
+docker
+sudo
+";
+
+    const ALPINE: &str = "\
+==uname
+Linux
+6.6.31-0-lts
+aarch64
+==os-release
+NAME=\"Alpine Linux\"
+ID=alpine
+VERSION_ID=3.20.0
+PRETTY_NAME=\"Alpine Linux v3.20\"
+==shell
+/bin/ash
+==init
+init
+==busybox
+/bin/busybox
+==tools
+free
+top
+df
+rc-service
+";
+
+    const FREEBSD: &str = "\
+==uname
+FreeBSD
+14.0-RELEASE
+amd64
+==os-release
+NAME=FreeBSD
+VERSION=\"14.0-RELEASE\"
+VERSION_ID=\"14.0\"
+ID=freebsd
+PRETTY_NAME=\"FreeBSD 14.0-RELEASE\"
+==shell
+/bin/sh
+==init
+init
+==busybox
+==tools
+top
+sysctl
+netstat
+df
+";
+
+    const OPENWRT: &str = "\
+==uname
+Linux
+5.15.150
+mips
+==os-release
+NAME='OpenWrt'
+VERSION='23.05.3'
+ID='openwrt'
+VERSION_ID='23.05.3'
+PRETTY_NAME='OpenWrt 23.05.3'
+==shell
+
+==init
+procd
+==busybox
+/bin/busybox
+==tools
+free
+top
+df
+";
+
+    #[test]
+    fn parses_ubuntu() {
+        let profile = parse_probe(UBUNTU);
+        assert_eq!(profile.os, OsFamily::Linux);
+        assert_eq!(profile.distro.as_deref(), Some("ubuntu"));
+        assert_eq!(profile.distro_name.as_deref(), Some("Ubuntu 22.04.4 LTS"));
+        assert_eq!(profile.distro_version.as_deref(), Some("22.04"));
+        assert_eq!(profile.kernel.as_deref(), Some("5.15.0-105-generic"));
+        assert_eq!(profile.arch.as_deref(), Some("x86_64"));
+        assert_eq!(profile.init_system, InitSystem::Systemd);
+        assert_eq!(profile.shell.as_deref(), Some("/bin/bash"));
+        assert!(!profile.busybox);
+        assert!(profile.has_tool("docker"));
+        assert_eq!(profile.strategy(), CollectorStrategy::Procps);
+    }
+
+    #[test]
+    fn parses_alpine() {
+        let profile = parse_probe(ALPINE);
+        assert_eq!(profile.distro.as_deref(), Some("alpine"));
+        assert_eq!(profile.arch.as_deref(), Some("aarch64"));
+        assert_eq!(profile.init_system, InitSystem::OpenRc);
+        assert!(profile.busybox);
+        assert_eq!(profile.strategy(), CollectorStrategy::ProcFs);
+    }
+
+    #[test]
+    fn parses_freebsd() {
+        let profile = parse_probe(FREEBSD);
+        assert_eq!(profile.os, OsFamily::FreeBsd);
+        assert_eq!(profile.distro_version.as_deref(), Some("14.0"));
+        assert_eq!(profile.init_system, InitSystem::BsdRc);
+        assert_eq!(profile.strategy(), CollectorStrategy::FreeBsd);
+    }
+
+    #[test]
+    fn parses_openwrt() {
+        let profile = parse_probe(OPENWRT);
+        assert_eq!(profile.distro.as_deref(), Some("openwrt"));
+        assert_eq!(profile.distro_name.as_deref(), Some("OpenWrt 23.05.3"));
+        assert_eq!(profile.init_system, InitSystem::Procd);
+        assert_eq!(profile.shell, None);
+        assert_eq!(profile.strategy(), CollectorStrategy::ProcFs);
+    }
+
+    #[test]
+    fn unknown_output_falls_back_to_procps() {
+        let profile = parse_probe("sh: syntax error\n");
+        assert_eq!(profile, HostProfile::default());
+        assert_eq!(profile.strategy(), CollectorStrategy::Procps);
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::events::{EventSink, ReplayFrame, Sink};
+use crate::sled::current_timestamp;
+use crate::utils::data_dir;
+
+const CAST_VERSION: u32 = 2;
+const CAST_EXTENSION: &str = ".cast";
+const TERM: &str = "xterm-256color";
+/// 回放时两帧之间的最长等待秒数，防止异常的时间戳让回放卡住
+const MAX_FRAME_DELAY: f64 = 60.0 * 60.0;
+
+struct ReplayHandle {
+    // 同一录像重新回放后，旧任务结束时不能移除新任务
+    generation: u64,
+    task: JoinHandle<()>,
+}
+
+// 正在进行的回放，键为录像文件名
+static REPLAYS: Lazy<Mutex<HashMap<String, ReplayHandle>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// asciicast v2 文件的首行
+#[derive(Serialize, Deserialize, Debug, TS)]
+pub struct CastHeader {
+    pub version: u32,
+    pub width: u32,
+    pub height: u32,
+    #[ts(type = "number | null")]
+    pub timestamp: Option<u64>,
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Debug, TS)]
+pub struct RecordingInfo {
+    pub name: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    pub connection_id: Option<usize>,
+    #[ts(type = "number | null")]
+    pub created_at: Option<u64>,
+}
+
+/// 录制选项，终端通道打开后才据此创建录像文件
+#[derive(Debug, Clone, Copy)]
+pub struct RecordOptions {
+    pub record_input: bool,
+}
+
+/// 把终端输出（以及可选的输入）按 asciicast v2 格式写入文件。
+/// 文件读写在阻塞线程中进行，终端任务只负责发送事件
+pub struct Recorder {
+    name: String,
+    events: mpsc::Sender<String>,
+    started: Instant,
+    record_input: bool,
+}
+
+impl Recorder {
+    pub async fn create(id: usize, cols: u32, rows: u32, record_input: bool) -> AppResult<Self> {
+        let (name, writer) =
+            tokio::task::spawn_blocking(move || create_file(&recordings_dir()?, id, cols, rows))
+                .await
+                .map_err(|e| AppError::internal("创建录像文件失败").with_details(e))??;
+        let (events, receiver) = mpsc::channel();
+        let writer_name = name.clone();
+        tokio::task::spawn_blocking(move || write_events(&writer_name, writer, receiver));
+        Ok(Recorder {
+            name,
+            events,
+            started: Instant::now(),
+            record_input,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn output(&mut self, data: &str) {
+        self.write_event("o", data);
+    }
+
+    pub fn input(&mut self, data: &str) {
+        if self.record_input {
+            self.write_event("i", data);
+        }
+    }
+
+    pub fn resize(&mut self, cols: u32, rows: u32) {
+        self.write_event("r", &format!("{}x{}", cols, rows));
+    }
+
+    /// 结束录制，写入线程处理完剩余事件后保存文件
+    pub fn finish(self) {
+        drop(self.events);
+    }
+
+    // 写入线程已退出时只记录日志，不影响终端会话
+    fn write_event(&mut self, code: &str, data: &str) {
+        let time = self.started.elapsed().as_secs_f64();
+        if self
+            .events
+            .send(json!([time, code, data]).to_string())
+            .is_err()
+        {
+            eprintln!("写入录像 {} 失败: 写入线程已退出", self.name);
+        }
+    }
+}
+
+// 选择不重名的文件名并写入文件头
+fn create_file(
+    dir: &Path,
+    id: usize,
+    cols: u32,
+    rows: u32,
+) -> AppResult<(String, BufWriter<File>)> {
+    fs::create_dir_all(dir)?;
+    let created_at = current_timestamp();
+    let mut name = format!("{}-{}{}", id, created_at, CAST_EXTENSION);
+    let mut index = 1;
+    while dir.join(&name).exists() {
+        name = format!("{}-{}-{}{}", id, created_at, index, CAST_EXTENSION);
+        index += 1;
+    }
+
+    let mut writer = BufWriter::new(File::create(dir.join(&name))?);
+    let header = json!({
+        "version": CAST_VERSION,
+        "width": cols,
+        "height": rows,
+        "timestamp": created_at,
+        "title": format!("连接 {}", id),
+        "env": { "TERM": TERM },
+    });
+    writeln!(writer, "{}", header)?;
+    Ok((name, writer))
+}
+
+// 逐条写入事件，发送端全部释放后刷新缓冲区
+fn write_events(name: &str, mut writer: BufWriter<File>, events: mpsc::Receiver<String>) {
+    for event in events {
+        if let Err(e) = writeln!(writer, "{}", event) {
+            eprintln!("写入录像 {} 失败: {}", name, e);
+        }
+    }
+    if let Err(e) = writer.flush() {
+        eprintln!("保存录像 {} 失败: {}", name, e);
+    }
+}
+
+fn recordings_dir() -> AppResult<PathBuf> {
+    Ok(data_dir()?.join("recordings"))
+}
+
+fn recording_path(name: &str) -> AppResult<PathBuf> {
+    if !name.ends_with(CAST_EXTENSION) || name.contains(&['/', '\\'][..]) || name.starts_with('.') {
+        return Err(AppError::invalid_input(format!("无效的录像名称: {}", name)));
+    }
+    let path = recordings_dir()?.join(name);
+    if !path.exists() {
+        return Err(AppError::not_found(format!("录像不存在: {}", name)));
+    }
+    Ok(path)
+}
+
+pub fn list_recordings() -> AppResult<Vec<RecordingInfo>> {
+    let dir = recordings_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut recordings = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(stem) = name.strip_suffix(CAST_EXTENSION) else {
+            continue;
+        };
+        // 文件名格式：<连接 ID>-<时间戳>[-序号].cast
+        let mut parts = stem.split('-');
+        let connection_id = parts.next().and_then(|p| p.parse().ok());
+        let created_at = parts.next().and_then(|p| p.parse().ok());
+        recordings.push(RecordingInfo {
+            size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            name,
+            connection_id,
+            created_at,
+        });
+    }
+    recordings.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.name.cmp(&a.name)));
+    Ok(recordings)
+}
+
+pub fn delete_recording(name: &str) -> AppResult<()> {
+    fs::remove_file(recording_path(name)?)?;
+    Ok(())
+}
+
+/// 复制录像到指定路径，可直接用 asciinema play 播放
+pub fn export_recording(name: &str, target: &str) -> AppResult<String> {
+    let source = recording_path(name)?;
+    let mut target = PathBuf::from(target);
+    if target.is_dir() {
+        target = target.join(name);
+    }
+    fs::copy(&source, &target)?;
+    Ok(target.to_string_lossy().to_string())
+}
+
+/// 读取录像头并在后台按原速度的 speed 倍发送 replay-frame 事件，结束或停止后发送 replay-finished，
+/// max_idle 限制两帧之间的最长等待时间（秒）。同一录像已在回放时先停止旧的回放
+pub async fn start_replay(
+    name: &str,
+    speed: f64,
+    max_idle: Option<f64>,
+    sink: Sink,
+) -> AppResult<CastHeader> {
+    if !(speed.is_finite() && speed > 0.0) {
+        return Err(AppError::invalid_input("回放速度必须大于 0"));
+    }
+    if max_idle.is_some_and(|max_idle| !(max_idle.is_finite() && max_idle >= 0.0)) {
+        return Err(AppError::invalid_input("最长等待时间不能为负数"));
+    }
+    let path = recording_path(name)?;
+    let mut reader = BufReader::new(tokio::fs::File::open(&path).await?);
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let header: CastHeader = serde_json::from_str(&line)
+        .map_err(|e| AppError::parse("录像文件头无法解析").with_details(e))?;
+    if header.version != CAST_VERSION {
+        return Err(AppError::invalid_input(format!(
+            "不支持的 asciicast 版本: {}",
+            header.version
+        )));
+    }
+
+    let name = name.to_string();
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    // 持有锁直到登记完成，任务结束时才能找到自己的记录
+    let mut replays = REPLAYS.lock().await;
+    let task = tokio::spawn({
+        let name = name.clone();
+        let sink = sink.clone();
+        async move {
+            if let Err(e) = replay(&name, reader, speed, max_idle, sink.as_ref()).await {
+                eprintln!("回放录像 {} 失败: {}", name, e);
+            }
+            let mut replays = REPLAYS.lock().await;
+            if replays.get(&name).map(|r| r.generation) == Some(generation) {
+                replays.remove(&name);
+            }
+            sink.emit("replay-finished", &name);
+        }
+    });
+    if let Some(previous) = replays.insert(name.clone(), ReplayHandle { generation, task }) {
+        previous.task.abort();
+        sink.emit("replay-finished", &name);
+    }
+    Ok(header)
+}
+
+/// 停止正在进行的回放并发送 replay-finished
+pub async fn stop_replay(name: &str, sink: Sink) -> AppResult<()> {
+    let replay = REPLAYS
+        .lock()
+        .await
+        .remove(name)
+        .ok_or_else(|| AppError::not_found(format!("录像 {} 没有在回放", name)))?;
+    replay.task.abort();
+    sink.emit("replay-finished", name);
+    Ok(())
+}
+
+async fn replay(
+    name: &str,
+    reader: BufReader<tokio::fs::File>,
+    speed: f64,
+    max_idle: Option<f64>,
+    sink: &dyn EventSink,
+) -> AppResult<()> {
+    let mut previous = 0.0;
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (time, code, data): (f64, String, String) = serde_json::from_str(&line)?;
+        let mut delay = (time - previous).max(0.0);
+        if let Some(max_idle) = max_idle {
+            delay = delay.min(max_idle);
+        }
+        previous = time;
+        let delay = delay / speed;
+        let delay = if delay.is_finite() {
+            delay.min(MAX_FRAME_DELAY)
+        } else {
+            MAX_FRAME_DELAY
+        };
+        tokio::time::sleep(Duration::from_secs_f64(delay)).await;
+        sink.emit(
+            "replay-frame",
+            ReplayFrame {
+                name: name.to_string(),
+                time,
+                code,
+                data,
+            },
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_asciicast_v2() {
+        let dir = tempfile::tempdir().unwrap();
+        let (name, writer) = create_file(dir.path(), 3, 80, 24).unwrap();
+        let (other, _) = create_file(dir.path(), 3, 80, 24).unwrap();
+        assert!(name.starts_with("3-") && name.ends_with(CAST_EXTENSION));
+        assert_ne!(name, other);
+
+        let (events, receiver) = mpsc::channel();
+        let writer_name = name.clone();
+        let writer = std::thread::spawn(move || write_events(&writer_name, writer, receiver));
+        let mut recorder = Recorder {
+            name: name.clone(),
+            events,
+            started: Instant::now(),
+            record_input: false,
+        };
+        recorder.output("$ ls\r\n");
+        recorder.input("ls\r");
+        recorder.resize(120, 40);
+        recorder.finish();
+        writer.join().unwrap();
+
+        let content = fs::read_to_string(dir.path().join(&name)).unwrap();
+        let mut lines = content.lines();
+        let header: CastHeader = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(header.version, CAST_VERSION);
+        assert_eq!((header.width, header.height), (80, 24));
+        assert_eq!(header.title.as_deref(), Some("连接 3"));
+        // 未开启输入录制时不写入 i 事件
+        let events: Vec<(f64, String, String)> = lines
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            (events[0].1.as_str(), events[0].2.as_str()),
+            ("o", "$ ls\r\n")
+        );
+        assert_eq!(
+            (events[1].1.as_str(), events[1].2.as_str()),
+            ("r", "120x40")
+        );
+        assert!(events[0].0 <= events[1].0);
+    }
+}
@@ -0,0 +1,318 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use russh::client::{self, Handle, Msg};
+use russh::keys::*;
+use russh::Channel;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+
+use crate::algorithms::{AlgorithmPreferences, KexTap, NegotiatedAlgorithms};
+use crate::certificate::{self, CertificateInfo};
+use crate::error::{AppError, AppResult};
+use crate::forward;
+use crate::hostkeys::HostKeyVerifier;
+use crate::platform::HostProfile;
+use crate::settings::Timeouts;
+use crate::sled::LocalForward;
+
+pub struct Client {
+    host_keys: HostKeyVerifier,
+    // 服务器实际出示的主机密钥算法，russh 按协商结果解析密钥
+    host_key_algorithm: Arc<Mutex<Option<String>>>,
+}
+
+#[async_trait]
+impl client::Handler for Client {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &key::PublicKey,
+    ) -> Result<bool, Self::Error> {
+        if let Ok(mut algorithm) = self.host_key_algorithm.lock() {
+            *algorithm = Some(server_public_key.name().to_string());
+        }
+        match self.host_keys.verify(server_public_key) {
+            Ok(trusted) => Ok(trusted),
+            Err(e) => {
+                eprintln!("校验主机密钥失败: {}", e);
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// 认证方式，保存在会话中用于断线重连
+#[derive(Clone)]
+pub enum Credentials {
+    Password(String),
+    PrivateKey(PathBuf),
+    // 私钥与 CA 签发的 OpenSSH 用户证书
+    Certificate { key: PathBuf, certificate: PathBuf },
+}
+
+/// 建立连接所需的全部参数
+#[derive(Clone)]
+pub struct ConnectParams {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub credentials: Credentials,
+    pub timeouts: Timeouts,
+    pub algorithms: AlgorithmPreferences,
+    // 所有连接都必须校验主机密钥
+    pub host_keys: HostKeyVerifier,
+}
+
+impl ConnectParams {
+    /// 证书认证时读取证书信息，证书已过期则直接报错，不必等服务器拒绝
+    pub fn certificate_info(&self) -> AppResult<Option<CertificateInfo>> {
+        let Credentials::Certificate { certificate, .. } = &self.credentials else {
+            return Ok(None);
+        };
+        let info = certificate::inspect(certificate)?;
+        if info.is_expired() {
+            return Err(AppError::auth("证书已过期")
+                .with_details(format!("valid_before: {}", info.valid_before)));
+        }
+        Ok(Some(info))
+    }
+}
+
+/// 这个结构体是russh客户端的便捷包装
+pub struct Session {
+    // 端口转发任务共享同一个连接，断线重连后替换为新的连接
+    pub session: Arc<Handle<Client>>,
+    pub params: ConnectParams,
+    // 使用证书认证时的证书信息，用于过期提醒
+    pub certificate: Option<CertificateInfo>,
+    // 连接后探测到的远端环境，监控任务据此选择采集命令
+    pub profile: HostProfile,
+    // 会话内输入的 sudo 密码，只保存在内存中，断线重连后仍然有效
+    pub sudo_password: Option<String>,
+    // 本次连接握手协商出的算法，断线重连后随之更新
+    pub negotiated: Option<NegotiatedAlgorithms>,
+    pub info_task: Option<JoinHandle<()>>,
+    // 监测断线并自动重连的任务
+    pub watch_task: Option<JoinHandle<()>>,
+    // 连接保存的本地端口转发及其监听任务，重连后重新建立
+    pub forwards: Vec<LocalForward>,
+    pub forward_tasks: Vec<JoinHandle<()>>,
+}
+
+impl Session {
+    pub async fn open(params: ConnectParams) -> AppResult<Self> {
+        let certificate = params.certificate_info()?;
+        let (session, negotiated) = Self::establish(&params).await?;
+        Ok(Self {
+            session: Arc::new(session),
+            params,
+            certificate,
+            profile: HostProfile::default(),
+            sudo_password: None,
+            negotiated,
+            info_task: None,
+            watch_task: None,
+            forwards: Vec::new(),
+            forward_tasks: Vec::new(),
+        })
+    }
+
+    /// 按参数建立连接并完成认证，断线重连时也复用这里。
+    /// 同时返回握手协商出的算法，无法得出时为 None
+    pub async fn establish(
+        params: &ConnectParams,
+    ) -> AppResult<(Handle<Client>, Option<NegotiatedAlgorithms>)> {
+        let timeouts = &params.timeouts;
+        let config = client::Config {
+            inactivity_timeout: timeouts.inactivity(),
+            keepalive_interval: timeouts.keepalive_interval(),
+            keepalive_max: timeouts.keepalive_max,
+            preferred: params.algorithms.to_preferred()?,
+            ..Default::default()
+        };
+
+        let config = Arc::new(config);
+        let host_key_algorithm = Arc::new(Mutex::new(None));
+        let sh = Client {
+            host_keys: params.host_keys.clone(),
+            host_key_algorithm: host_key_algorithm.clone(),
+        };
+
+        // 记录握手时服务器发来的 KEXINIT，russh 不公开会话协商的结果
+        let connect = async {
+            let stream = TcpStream::connect((params.host.as_str(), params.port)).await?;
+            let (stream, capture) = KexTap::new(stream);
+            let session = client::connect_stream(config, stream, sh).await?;
+            AppResult::Ok((session, capture))
+        };
+        let (mut session, capture) =
+            with_timeout(timeouts.connect(), connect, "连接服务器超时").await??;
+        with_timeout(
+            timeouts.auth(),
+            Self::authenticate(&mut session, params),
+            "身份验证超时",
+        )
+        .await??;
+
+        let host_key = host_key_algorithm.lock().ok().and_then(|name| name.clone());
+        let negotiated = capture
+            .lock()
+            .map_err(|_| AppError::parse("没有记录到服务器的 KEXINIT"))
+            .and_then(|mut capture| capture.take())
+            .and_then(|(banner, kexinit)| {
+                NegotiatedAlgorithms::from_handshake(&params.algorithms, banner, &kexinit, host_key)
+            });
+        let negotiated = match negotiated {
+            Ok(negotiated) => Some(negotiated),
+            Err(e) => {
+                eprintln!("无法得出协商的算法: {}", e);
+                None
+            }
+        };
+
+        Ok((session, negotiated))
+    }
+
+    async fn authenticate(session: &mut Handle<Client>, params: &ConnectParams) -> AppResult<()> {
+        match &params.credentials {
+            Credentials::PrivateKey(key_path) => {
+                let key_pair = load_secret_key(key_path, None)?;
+                let auth_res = session
+                    .authenticate_publickey(params.user.clone(), Arc::new(key_pair))
+                    .await?;
+                if !auth_res {
+                    return Err(AppError::auth("身份验证失败"));
+                }
+            }
+            Credentials::Certificate { key, certificate } => {
+                let key_pair = load_secret_key(key, None)?;
+                // 每次连接都重新读取证书，短期证书续签后重连即可生效
+                let certificate = certificate::load_certificate(certificate)?;
+                let auth_res = session
+                    .authenticate_openssh_cert(params.user.clone(), Arc::new(key_pair), certificate)
+                    .await?;
+                if !auth_res {
+                    return Err(AppError::auth("证书认证失败"));
+                }
+            }
+            Credentials::Password(password) => {
+                let auth_res = session
+                    .authenticate_password(params.user.clone(), password.clone())
+                    .await?;
+                if !auth_res {
+                    return Err(AppError::auth("密码认证失败"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 底层连接是否已经断开
+    pub fn is_closed(&self) -> bool {
+        self.session.is_closed()
+    }
+
+    /// 在服务器上执行命令
+    pub async fn call(&self, command: &str) -> AppResult<String> {
+        let (output, exit_status) = self.call_with_status(command).await?;
+        match exit_status {
+            Some(status) if status != 0 => Err(AppError::command("命令返回非零退出状态")
+                .with_details(format!("exit status: {}", status))),
+            _ => Ok(output),
+        }
+    }
+
+    /// 执行命令并返回输出与退出状态，非零退出状态不视为错误
+    pub async fn call_with_status(&self, command: &str) -> AppResult<(String, Option<u32>)> {
+        let mut channel = self.exec(command).await?;
+        Ok(Self::collect(&mut channel).await)
+    }
+
+    /// 打开通道并开始执行命令，标准输入立即关闭。返回的通道不借用会话，
+    /// 可以在释放连接池锁之后再用 collect 等待结果
+    pub async fn exec(&self, command: &str) -> AppResult<Channel<Msg>> {
+        let channel = self.session.channel_open_session().await?;
+        channel.exec(true, command).await?;
+        channel.eof().await?;
+        Ok(channel)
+    }
+
+    /// 执行命令并把 input 写入标准输入，写完后关闭输入
+    pub async fn call_with_input(
+        &self,
+        command: &str,
+        input: &[u8],
+    ) -> AppResult<(String, Option<u32>)> {
+        let mut channel = self.session.channel_open_session().await?;
+        channel.exec(true, command).await?;
+        channel.data(input).await?;
+        channel.eof().await?;
+        Ok(Self::collect(&mut channel).await)
+    }
+
+    /// 读取标准输出直到通道关闭
+    pub async fn collect(channel: &mut Channel<Msg>) -> (String, Option<u32>) {
+        let mut output = String::new();
+        let mut exit_status = None;
+        loop {
+            let Some(msg) = channel.wait().await else {
+                break;
+            };
+            match msg {
+                russh::ChannelMsg::Data { ref data } => {
+                    output.push_str(&String::from_utf8_lossy(data));
+                }
+                russh::ChannelMsg::ExitStatus {
+                    exit_status: status,
+                } => {
+                    exit_status = Some(status);
+                }
+                _ => {}
+            }
+        }
+        (output, exit_status)
+    }
+
+    /// 关闭会话
+    pub async fn close(&mut self) -> AppResult<()> {
+        if let Some(task) = self.info_task.take() {
+            task.abort();
+        }
+        if let Some(task) = self.watch_task.take() {
+            task.abort();
+        }
+        forward::stop_forwards(std::mem::take(&mut self.forward_tasks)).await;
+        self.session
+            .disconnect(russh::Disconnect::ByApplication, "", "English")
+            .await?;
+        Ok(())
+    }
+}
+
+// fn load_secret_key<P: AsRef<Path>>(
+//     _key_path: P,
+//     _passphrase: Option<&str>,
+// ) -> Result<keys::KeyPair> {
+//     // 这里你需要实现从文件加载密钥的逻辑
+//     // 这个函数的返回值是 russh_keys::key::KeyPair
+//     todo!()
+// }
+
+// 超时为 None 时不限制等待时间
+pub(crate) async fn with_timeout<F: std::future::Future>(
+    duration: Option<Duration>,
+    future: F,
+    message: &str,
+) -> AppResult<F::Output> {
+    match duration {
+        Some(duration) => tokio::time::timeout(duration, future)
+            .await
+            .map_err(|_| AppError::timeout(message).with_details(format!("{:?}", duration))),
+        None => Ok(future.await),
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::sled::get_connection;
+
+/// 存放全局设置的 tree
+const SETTINGS_TREE: &str = "settings";
+const SETTINGS_KEY: &str = "settings";
+
+/// 全局设置，缺失的字段使用默认值
+#[derive(Serialize, Deserialize, Debug, Clone, Default, TS)]
+#[serde(default)]
+pub struct Settings {
+    pub timeouts: Timeouts,
+    pub history: HistorySettings,
+}
+
+/// 命令历史的保留策略
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[serde(default)]
+pub struct HistorySettings {
+    pub enabled: bool,
+    // 保留天数，0 表示不按时间清理
+    pub retention_days: u32,
+    // 每个连接最多保留的条数，0 表示不限制
+    pub max_entries: usize,
+    // 匹配这些模式的命令不记录，* 匹配任意字符，不区分大小写
+    pub exclude_patterns: Vec<String>,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        HistorySettings {
+            enabled: true,
+            retention_days: 90,
+            max_entries: 5000,
+            exclude_patterns: [
+                "*password*",
+                "*passwd*",
+                "*secret*",
+                "*token*",
+                "*api_key*",
+                "*apikey*",
+                "*private_key*",
+                "mysql* -p*",
+                "*authorization:*",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        }
+    }
+}
+
+/// SSH 超时与保活参数，单位秒，0 表示不启用
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+#[serde(default)]
+pub struct Timeouts {
+    #[ts(type = "number")]
+    pub connect_timeout: u64,
+    #[ts(type = "number")]
+    pub auth_timeout: u64,
+    #[ts(type = "number")]
+    pub keepalive_interval: u64,
+    // 连续多少次保活无响应后断开
+    pub keepalive_max: usize,
+    #[ts(type = "number")]
+    pub inactivity_timeout: u64,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            connect_timeout: 10,
+            auth_timeout: 15,
+            keepalive_interval: 15,
+            keepalive_max: 3,
+            // 依靠保活检测断线，默认不因空闲断开
+            inactivity_timeout: 0,
+        }
+    }
+}
+
+/// 单个连接对全局超时设置的覆盖，为 null 时使用全局值
+#[derive(Serialize, Deserialize, Debug, Clone, Default, TS)]
+#[serde(default)]
+pub struct TimeoutOverrides {
+    #[ts(type = "number | null")]
+    pub connect_timeout: Option<u64>,
+    #[ts(type = "number | null")]
+    pub auth_timeout: Option<u64>,
+    #[ts(type = "number | null")]
+    pub keepalive_interval: Option<u64>,
+    pub keepalive_max: Option<usize>,
+    #[ts(type = "number | null")]
+    pub inactivity_timeout: Option<u64>,
+}
+
+impl Timeouts {
+    /// 用连接级别的覆盖值替换全局值
+    pub fn with_overrides(&self, overrides: &TimeoutOverrides) -> Timeouts {
+        Timeouts {
+            connect_timeout: overrides.connect_timeout.unwrap_or(self.connect_timeout),
+            auth_timeout: overrides.auth_timeout.unwrap_or(self.auth_timeout),
+            keepalive_interval: overrides
+                .keepalive_interval
+                .unwrap_or(self.keepalive_interval),
+            keepalive_max: overrides.keepalive_max.unwrap_or(self.keepalive_max),
+            inactivity_timeout: overrides
+                .inactivity_timeout
+                .unwrap_or(self.inactivity_timeout),
+        }
+    }
+
+    /// 检查参数组合是否有效，启用保活时必须允许至少一次无响应
+    pub fn validate(&self) -> AppResult<()> {
+        if self.keepalive_interval > 0 && self.keepalive_max == 0 {
+            return Err(AppError::invalid_input("启用保活时 keepalive_max 不能为 0"));
+        }
+        Ok(())
+    }
+
+    pub fn connect(&self) -> Option<Duration> {
+        seconds(self.connect_timeout)
+    }
+
+    pub fn auth(&self) -> Option<Duration> {
+        seconds(self.auth_timeout)
+    }
+
+    pub fn keepalive_interval(&self) -> Option<Duration> {
+        seconds(self.keepalive_interval)
+    }
+
+    pub fn inactivity(&self) -> Option<Duration> {
+        seconds(self.inactivity_timeout)
+    }
+}
+
+fn seconds(value: u64) -> Option<Duration> {
+    (value > 0).then_some(Duration::from_secs(value))
+}
+
+pub fn load_settings(db: &Db) -> AppResult<Settings> {
+    let tree = db.open_tree(SETTINGS_TREE)?;
+    match tree.get(SETTINGS_KEY)? {
+        Some(value) => Ok(serde_json::from_slice(&value)?),
+        None => Ok(Settings::default()),
+    }
+}
+
+pub fn save_settings(db: &Db, settings: &Settings) -> AppResult<()> {
+    settings.timeouts.validate()?;
+    let tree = db.open_tree(SETTINGS_TREE)?;
+    tree.insert(SETTINGS_KEY, serde_json::to_vec(settings)?)?;
+    tree.flush()?;
+    Ok(())
+}
+
+/// 按当前全局设置检查连接级别的覆盖，规则与全局设置相同
+pub fn validate_overrides(db: &Db, overrides: &TimeoutOverrides) -> AppResult<()> {
+    load_settings(db)?
+        .timeouts
+        .with_overrides(overrides)
+        .validate()
+}
+
+/// 计算指定连接实际生效的超时参数，连接不存在时使用全局设置
+pub fn resolve_timeouts(db: &Db, id: u64) -> AppResult<Timeouts> {
+    let defaults = load_settings(db)?.timeouts;
+    Ok(match get_connection(db, id)? {
+        Some(connection) => defaults.with_overrides(&connection.timeouts),
+        None => defaults,
+    })
+}
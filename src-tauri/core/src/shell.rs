@@ -0,0 +1,389 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use once_cell::sync::Lazy;
+use russh::client::{Handle, Msg};
+use russh::{Channel, ChannelMsg};
+use serde::Serialize;
+use sled::Db;
+use tokio::sync::{mpsc, Mutex};
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::events::{ShellClosed, ShellOutput, Sink};
+use crate::history::{self, HistorySource, LineCapture};
+use crate::recording::{RecordOptions, Recorder};
+use crate::session::Client;
+
+const TERM: &str = "xterm-256color";
+const ECHO_BUFFER_LEN: usize = 4096;
+
+enum ShellInput {
+    Data(Vec<u8>),
+    Resize { cols: u32, rows: u32 },
+    Close,
+}
+
+struct ShellHandle {
+    // 同一连接重新打开终端后，旧任务结束时不能移除新终端
+    generation: u64,
+    input: mpsc::UnboundedSender<ShellInput>,
+}
+
+// 打开终端时的参数，连接断开后据此在新连接上重新打开
+struct ShellSpec {
+    command: Option<String>,
+    cols: u32,
+    rows: u32,
+    record: Option<RecordOptions>,
+    sink: Sink,
+    db: Db,
+}
+
+// 每个连接最多一个交互式终端，键为连接 ID
+static SHELLS: Lazy<Mutex<HashMap<usize, ShellHandle>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// 因连接断开而中断的终端，重连成功后重新打开，重连失败时关闭
+static INTERRUPTED: Lazy<Mutex<HashMap<usize, ShellSpec>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Serialize, Debug, TS)]
+pub struct ShellOpened {
+    pub id: usize,
+    // 开启录制时的录像文件名
+    pub recording: Option<String>,
+}
+
+/// 在连接上打开带 PTY 的交互式终端，输出通过 shell-output-{id} 事件发送。
+/// command 不为空时在 PTY 中执行该命令（如 docker exec -it），而不是登录 shell
+#[allow(clippy::too_many_arguments)]
+pub async fn open_shell(
+    id: usize,
+    session: &Handle<Client>,
+    command: Option<&str>,
+    cols: u32,
+    rows: u32,
+    record: Option<RecordOptions>,
+    sink: Sink,
+    db: Db,
+) -> AppResult<ShellOpened> {
+    close_shell(id).await;
+    let spec = ShellSpec {
+        command: command.map(str::to_string),
+        cols,
+        rows,
+        record,
+        sink,
+        db,
+    };
+    start(id, session, spec).await
+}
+
+/// 重连成功后在新连接上重新打开中断的终端，输出继续通过原来的事件发送
+pub async fn restore_shell(id: usize, session: &Handle<Client>) {
+    let Some(spec) = INTERRUPTED.lock().await.remove(&id) else {
+        return;
+    };
+    let sink = spec.sink.clone();
+    if let Err(e) = start(id, session, spec).await {
+        eprintln!("连接 {} 恢复终端失败: {}", id, e);
+        emit_closed(&sink, id, None);
+    }
+}
+
+/// 关闭中断的终端并发送 shell-closed，用于重连失败或终端不是因断线而中断的情况
+pub async fn discard_interrupted(id: usize) {
+    if let Some(spec) = INTERRUPTED.lock().await.remove(&id) {
+        emit_closed(&spec.sink, id, None);
+    }
+}
+
+async fn start(id: usize, session: &Handle<Client>, spec: ShellSpec) -> AppResult<ShellOpened> {
+    let channel = session.channel_open_session().await?;
+    channel
+        .request_pty(false, TERM, spec.cols, spec.rows, 0, 0, &[])
+        .await?;
+    match &spec.command {
+        Some(command) => channel.exec(false, command.as_str()).await?,
+        None => channel.request_shell(false).await?,
+    }
+    // 通道打开后再创建录像，避免打开失败时留下空的录像文件
+    let recorder = match spec.record {
+        Some(options) => {
+            match Recorder::create(id, spec.cols, spec.rows, options.record_input).await {
+                Ok(recorder) => Some(recorder),
+                Err(e) => {
+                    let _ = channel.close().await;
+                    return Err(e);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let recording = recorder.as_ref().map(|r| r.name().to_string());
+    let (input, receiver) = mpsc::unbounded_channel();
+    let generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+    SHELLS
+        .lock()
+        .await
+        .insert(id, ShellHandle { generation, input });
+    tokio::spawn(run_shell(id, generation, channel, receiver, recorder, spec));
+    Ok(ShellOpened { id, recording })
+}
+
+pub async fn write(id: usize, data: &str) -> AppResult<()> {
+    send(id, ShellInput::Data(data.as_bytes().to_vec())).await
+}
+
+pub async fn resize(id: usize, cols: u32, rows: u32) -> AppResult<()> {
+    send(id, ShellInput::Resize { cols, rows }).await
+}
+
+/// 关闭终端，终端不存在时什么也不做
+pub async fn close_shell(id: usize) {
+    if let Some(shell) = SHELLS.lock().await.remove(&id) {
+        let _ = shell.input.send(ShellInput::Close);
+    }
+    discard_interrupted(id).await;
+}
+
+async fn send(id: usize, input: ShellInput) -> AppResult<()> {
+    let shells = SHELLS.lock().await;
+    let shell = shells
+        .get(&id)
+        .ok_or_else(|| AppError::not_found(format!("连接 {} 没有打开的终端", id)))?;
+    shell
+        .input
+        .send(input)
+        .map_err(|_| AppError::channel("终端已关闭"))
+}
+
+async fn run_shell(
+    id: usize,
+    generation: u64,
+    mut channel: Channel<Msg>,
+    mut input: mpsc::UnboundedReceiver<ShellInput>,
+    mut recorder: Option<Recorder>,
+    mut spec: ShellSpec,
+) {
+    let output_event = format!("shell-output-{id}");
+    // 数据包可能在多字节字符中间截断，不完整的尾部留到下一次解码
+    let mut pending = Vec::new();
+    let mut exit_status = None;
+    // 远端命令正常结束或用户主动关闭，其余情况视为连接中断
+    let mut finished = false;
+    let mut line_capture = LineCapture::default();
+    // 只记录被回显过的命令行，避免把密码提示下输入的内容写入历史
+    let mut echo = EchoCheck::default();
+    loop {
+        tokio::select! {
+            msg = channel.wait() => match msg {
+                Some(ChannelMsg::Data { data }) | Some(ChannelMsg::ExtendedData { data, .. }) => {
+                    pending.extend_from_slice(&data);
+                    let text = take_utf8(&mut pending);
+                    if text.is_empty() {
+                        continue;
+                    }
+                    if let Some(recorder) = recorder.as_mut() {
+                        recorder.output(&text);
+                    }
+                    for line in echo.output(&text) {
+                        history::record(&spec.db, id as u64, &line, HistorySource::Shell, None, 0);
+                    }
+                    spec.sink.emit(&output_event, ShellOutput { id, data: text });
+                }
+                Some(ChannelMsg::ExitStatus { exit_status: status }) => {
+                    exit_status = Some(status);
+                    finished = true;
+                }
+                Some(ChannelMsg::ExitSignal { .. }) => finished = true,
+                Some(_) => {}
+                None => break,
+            },
+            message = input.recv() => match message {
+                Some(ShellInput::Data(data)) => {
+                    let text = String::from_utf8_lossy(&data);
+                    if let Some(recorder) = recorder.as_mut() {
+                        recorder.input(&text);
+                    }
+                    for line in echo.input(line_capture.feed(&text)) {
+                        history::record(&spec.db, id as u64, &line, HistorySource::Shell, None, 0);
+                    }
+                    if let Err(e) = channel.data(&data[..]).await {
+                        eprintln!("终端 {} 写入失败: {}", id, e);
+                        break;
+                    }
+                }
+                Some(ShellInput::Resize { cols, rows }) => {
+                    if let Some(recorder) = recorder.as_mut() {
+                        recorder.resize(cols, rows);
+                    }
+                    (spec.cols, spec.rows) = (cols, rows);
+                    let _ = channel.window_change(cols, rows, 0, 0).await;
+                }
+                Some(ShellInput::Close) | None => {
+                    finished = true;
+                    let _ = channel.eof().await;
+                    let _ = channel.close().await;
+                    break;
+                }
+            },
+        }
+    }
+
+    if let Some(recorder) = recorder {
+        recorder.finish();
+    }
+    let mut shells = SHELLS.lock().await;
+    let current = shells.get(&id).map(|s| s.generation) == Some(generation);
+    if current {
+        shells.remove(&id);
+    }
+    drop(shells);
+    // 连接断开导致的中断先不通知前端，由断线监测在重连后恢复或关闭
+    if current && !finished {
+        INTERRUPTED.lock().await.insert(id, spec);
+        return;
+    }
+    emit_closed(&spec.sink, id, exit_status);
+}
+
+fn emit_closed(sink: &Sink, id: usize, exit_status: Option<u32>) {
+    sink.emit(
+        &format!("shell-closed-{id}"),
+        ShellClosed { id, exit_status },
+    );
+}
+
+/// 判断输入的命令行是否被远端回显过。逐字输入时按下回车前回显已经到达，
+/// 粘贴的内容则要等后续输出，这类命令行先暂存，收到输出后再判断
+#[derive(Default)]
+struct EchoCheck {
+    // 最近的终端输出，offset 为其首字节在全部输出中的位置
+    output: String,
+    offset: usize,
+    // 当前输入行开始时的输出位置，回显只会出现在这之后
+    line_start: usize,
+    // 等待回显的命令行与开始查找的输出位置
+    pending: VecDeque<(String, usize)>,
+}
+
+impl EchoCheck {
+    /// 记录终端输出，返回因此确认被回显的命令行
+    fn output(&mut self, text: &str) -> Vec<String> {
+        self.output.push_str(text);
+        if self.output.len() > ECHO_BUFFER_LEN {
+            let mut start = self.output.len() - ECHO_BUFFER_LEN;
+            while !self.output.is_char_boundary(start) {
+                start += 1;
+            }
+            self.output.drain(..start);
+            self.offset += start;
+        }
+        self.confirm()
+    }
+
+    /// 记录输入中的完整命令行，返回已经确认被回显的命令行
+    fn input(&mut self, lines: Vec<String>) -> Vec<String> {
+        if lines.is_empty() {
+            return Vec::new();
+        }
+        for line in lines {
+            self.pending.push_back((line, self.line_start));
+        }
+        self.line_start = self.offset + self.output.len();
+        self.confirm()
+    }
+
+    // 回显按输入顺序出现：后面的行被回显时，前面仍未回显的行不会再被回显
+    fn confirm(&mut self) -> Vec<String> {
+        let mut confirmed = Vec::new();
+        let mut waiting = VecDeque::new();
+        let mut from = 0;
+        while let Some((line, start)) = self.pending.pop_front() {
+            let start = start.max(from);
+            // 查找范围已经移出缓冲区
+            if start < self.offset {
+                continue;
+            }
+            match self.output[start - self.offset..].find(line.trim()) {
+                Some(position) => {
+                    from = start + position + line.trim().len();
+                    waiting.clear();
+                    confirmed.push(line);
+                }
+                None => waiting.push_back((line, start)),
+            }
+        }
+        self.pending = waiting;
+        confirmed
+    }
+}
+
+// 取出 buffer 中可以完整解码的部分，无效字节按 lossy 处理
+pub(crate) fn take_utf8(buffer: &mut Vec<u8>) -> String {
+    match std::str::from_utf8(buffer) {
+        Ok(text) => {
+            let text = text.to_string();
+            buffer.clear();
+            text
+        }
+        Err(e) if e.error_len().is_none() => {
+            let valid = e.valid_up_to();
+            let text = String::from_utf8_lossy(&buffer[..valid]).to_string();
+            buffer.drain(..valid);
+            text
+        }
+        Err(_) => {
+            let text = String::from_utf8_lossy(buffer).to_string();
+            buffer.clear();
+            text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn typed_line_is_confirmed_by_earlier_echo() {
+        let mut echo = EchoCheck::default();
+        echo.output("$ ");
+        echo.output("ls -la");
+        assert_eq!(echo.input(lines(&["ls -la"])), lines(&["ls -la"]));
+    }
+
+    #[test]
+    fn pasted_lines_wait_for_echo() {
+        let mut echo = EchoCheck::default();
+        echo.output("$ ");
+        let mut capture = LineCapture::default();
+        assert!(echo.input(capture.feed("uptime\rwhoami\r")).is_empty());
+        assert_eq!(echo.output("upti"), Vec::<String>::new());
+        assert_eq!(
+            echo.output("me\r\n 10:00 up 1 day\r\n$ "),
+            lines(&["uptime"])
+        );
+        assert_eq!(echo.output("whoami\r\nroot\r\n$ "), lines(&["whoami"]));
+    }
+
+    #[test]
+    fn unechoed_input_is_not_recorded() {
+        let mut echo = EchoCheck::default();
+        echo.output("$ sudo true");
+        assert_eq!(echo.input(lines(&["sudo true"])), lines(&["sudo true"]));
+        echo.output("\r\n[sudo] password for root: ");
+        assert!(echo.input(lines(&["hunter2"])).is_empty());
+        echo.output("\r\n$ ");
+        echo.output("ls");
+        assert_eq!(echo.input(lines(&["ls"])), lines(&["ls"]));
+        // 之后的输出中出现相同的文本也不会补记
+        assert!(echo.output("hunter2\r\n").is_empty());
+    }
+}
@@ -0,0 +1,567 @@
+use std::cmp::Reverse;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sled::{self, Db};
+use ts_rs::TS;
+
+use crate::algorithms::AlgorithmPreferences;
+use crate::backup;
+use crate::bundle::{self, BundleFormat, MergeStrategy};
+use crate::certificate;
+use crate::error::{AppError, AppResult};
+use crate::history::{self, HistoryQuery};
+use crate::hostkeys;
+use crate::migrations;
+use crate::platform;
+use crate::settings::{self, Settings, TimeoutOverrides};
+use crate::snippets::{self, Snippet};
+use crate::ssh_config::{self, SshConfigTarget};
+use crate::utils::{data_dir, ApiResponse};
+
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct SshConnection {
+    #[ts(type = "number | null")]
+    pub id: Option<u64>,
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    // OpenSSH 用户证书，未设置时自动查找 <identity_file>-cert.pub
+    #[serde(default)]
+    pub certificate_file: Option<String>,
+    #[serde(default)]
+    pub proxy_jump: Option<String>,
+    #[serde(default)]
+    pub local_forwards: Vec<LocalForward>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    // 超时与保活参数，未设置的项使用全局设置
+    #[serde(default)]
+    pub timeouts: TimeoutOverrides,
+    // 算法偏好，为空时使用默认顺序
+    #[serde(default)]
+    pub algorithms: AlgorithmPreferences,
+    // 固定的主机密钥指纹，设置后只接受该密钥，不再参考其他受信任的密钥
+    #[serde(default)]
+    pub pinned_host_key: Option<String>,
+    #[ts(type = "number | null")]
+    pub created_at: Option<u64>,
+    #[ts(type = "number | null")]
+    pub updated_at: Option<u64>,
+    // 最近一次连接时间与累计连接次数，由 open_connection 记录，用于搜索排序加权
+    #[serde(default)]
+    #[ts(type = "number | null")]
+    pub last_connected_at: Option<u64>,
+    #[serde(default)]
+    #[ts(type = "number")]
+    pub connect_count: u64,
+}
+
+/// 本地端口转发：bind_address:bind_port -> remote_host:remote_port
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct LocalForward {
+    pub bind_address: Option<String>,
+    pub bind_port: u16,
+    pub remote_host: String,
+    pub remote_port: u16,
+}
+
+// impl RedisConnection {
+//     fn new(
+//         name: &str,
+//         host: &str,
+//         port: u16,
+//         username: Option<&str>,
+//         password: Option<&str>,
+//     ) -> Self {
+//         RedisConnection {
+//             id: Some(0),
+//             name: name.to_string(),
+//             host: host.to_string(),
+//             port,
+//             username: username.map(|u| u.to_string()),
+//             password: password.map(|p| p.to_string()),
+//         }
+//     }
+// }
+
+// 获取当前时间戳（秒）
+pub(crate) fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+#[derive(Serialize, Deserialize, Debug, TS)]
+pub enum DbOperation {
+    Insert(SshConnection),
+    Update(SshConnection),
+    Delete(#[ts(type = "number")] u64), // 传入要删除的记录的 ID
+    SelectAll,                          // 查询所有记录
+    // 模糊搜索连接，limit 默认 50
+    Search {
+        query: String,
+        limit: Option<usize>,
+    },
+    // 导出连接和片段为 JSON/TOML 配置包，ids 为 null 或空列表时导出全部连接
+    Export {
+        path: String,
+        format: BundleFormat,
+        #[ts(type = "Array<number> | null")]
+        ids: Option<Vec<u64>>,
+        include_secrets: bool,
+        passphrase: Option<String>,
+    },
+    // 从配置包导入连接和片段
+    Import {
+        path: String,
+        strategy: MergeStrategy,
+        passphrase: Option<String>,
+    },
+    // 导出为 ~/.ssh/config 格式的 Host 配置块，ids 为 null 或空列表时导出全部连接
+    ExportSshConfig {
+        #[ts(type = "Array<number> | null")]
+        ids: Option<Vec<u64>>,
+        target: SshConfigTarget,
+    },
+    ListQuarantined,       // 查询因无法解析而被隔离的记录
+    CreateBackup,          // 手动创建快照
+    ListBackups,           // 查询所有快照
+    RestoreBackup(String), // 传入快照文件名，校验后恢复
+    GetSettings,           // 查询全局设置
+    UpdateSettings(Settings),
+    // 查询连接使用的证书
+    CertificateInfo(#[ts(type = "number")] u64),
+    // 主机密钥指纹管理，均以连接 ID 定位 host:port
+    ListHostKeys(#[ts(type = "number")] u64),
+    RemoveHostKey {
+        #[ts(type = "number")]
+        id: u64,
+        fingerprint: String,
+    },
+    // 获取服务器当前的主机密钥供用户核对
+    ScanHostKey(#[ts(type = "number")] u64),
+    // 服务器当前的主机密钥与核对过的指纹一致时，替换已信任的密钥
+    ReplaceHostKey {
+        #[ts(type = "number")]
+        id: u64,
+        fingerprint: String,
+    },
+    // fingerprint 为 null 时取消固定
+    PinHostKey {
+        #[ts(type = "number")]
+        id: u64,
+        fingerprint: Option<String>,
+    },
+    SearchHistory(HistoryQuery),
+    // 清空命令历史，传入连接 ID 时只清空该连接
+    ClearHistory(#[ts(type = "number | null")] Option<u64>),
+    // 从 OpenSSH 配置导入连接，为 null 时读取 ~/.ssh/config
+    ImportSshConfig(Option<String>),
+    ListSnippets,
+    // 新增或更新命令片段，id 为空时新增
+    SaveSnippet(Snippet),
+    DeleteSnippet(#[ts(type = "number")] u64),
+    // 查询连接时探测到的远端环境，从未连接过时为 null
+    GetHostProfile(#[ts(type = "number")] u64),
+}
+
+/// 启动时打开数据库并执行迁移，返回的句柄放入 Tauri 托管状态中共享
+/// 打开连接数据库。sled 同一时间只允许一个进程打开数据库，
+/// 桌面应用运行时命令行工具无法打开同一个数据目录
+pub fn open_db() -> AppResult<Db> {
+    let db_path = data_dir()?.join("connections_db");
+    let db = sled::open(&db_path).map_err(|e| match e {
+        sled::Error::Io(e) if e.to_string().contains("could not acquire lock") => {
+            AppError::db("数据库被桌面端或另一个 ssh-rust-cli 进程占用")
+                .with_details(db_path.display())
+        }
+        e => AppError::from(e),
+    })?;
+    migrations::run_migrations(&db)?;
+    Ok(db)
+}
+
+/// 执行数据库操作，桌面应用的 Tauri 命令和命令行工具都通过这里访问连接存储
+pub async fn run_db_operation(operation: DbOperation, db: &Db) -> AppResult<serde_json::Value> {
+    let db: Db = db.clone();
+    // 修改数据的操作成功后自动生成快照
+    let mutates = matches!(
+        operation,
+        DbOperation::Insert(_)
+            | DbOperation::Update(_)
+            | DbOperation::Delete(_)
+            | DbOperation::Import { .. }
+            | DbOperation::UpdateSettings(_)
+            | DbOperation::RemoveHostKey { .. }
+            | DbOperation::ReplaceHostKey { .. }
+            | DbOperation::PinHostKey { .. }
+            | DbOperation::ClearHistory(_)
+            | DbOperation::ImportSshConfig(_)
+            | DbOperation::SaveSnippet(_)
+            | DbOperation::DeleteSnippet(_)
+    );
+
+    let result = match operation {
+        DbOperation::Insert(mut connection) => {
+            settings::validate_overrides(&db, &connection.timeouts)?;
+            save_connection(&db, &mut connection)
+        }
+        DbOperation::Update(mut connection) => {
+            settings::validate_overrides(&db, &connection.timeouts)?;
+            update_connection(&db, &mut connection)
+        }
+        DbOperation::Delete(id) => {
+            delete_connection(&db, id)?;
+            let connections = get_all_connections(&db)?;
+            Ok(json!(ApiResponse::success(connections)))
+        }
+        DbOperation::SelectAll => {
+            let connections = get_all_connections(&db)?;
+            Ok(json!(ApiResponse::success(connections)))
+        }
+        DbOperation::Search { query, limit } => {
+            let connections = search_connections(&db, &query, limit)?;
+            Ok(json!(ApiResponse::success(connections)))
+        }
+        DbOperation::Export {
+            path,
+            format,
+            ids,
+            include_secrets,
+            passphrase,
+        } => {
+            let report =
+                bundle::export_bundle(&db, &path, format, ids, include_secrets, passphrase)?;
+            Ok(json!(ApiResponse::success(report)))
+        }
+        DbOperation::Import {
+            path,
+            strategy,
+            passphrase,
+        } => {
+            let report = bundle::import_bundle(&db, &path, strategy, passphrase)?;
+            Ok(json!(ApiResponse::success(report)))
+        }
+        DbOperation::ExportSshConfig { ids, target } => {
+            let report = ssh_config::export_ssh_config(&db, ids, target)?;
+            Ok(json!(ApiResponse::success(report)))
+        }
+        DbOperation::ListQuarantined => {
+            let records = migrations::list_quarantined(&db)?;
+            Ok(json!(ApiResponse::success(records)))
+        }
+        DbOperation::CreateBackup => {
+            let name = backup::create_snapshot(&db)?;
+            Ok(json!(ApiResponse::success(name)))
+        }
+        DbOperation::ListBackups => {
+            let backups = backup::list_backups()?;
+            Ok(json!(ApiResponse::success(backups)))
+        }
+        DbOperation::RestoreBackup(name) => {
+            let report = backup::restore_backup(&db, &name)?;
+            Ok(json!(ApiResponse::success(report)))
+        }
+        DbOperation::GetSettings => {
+            let settings = settings::load_settings(&db)?;
+            Ok(json!(ApiResponse::success(settings)))
+        }
+        DbOperation::UpdateSettings(settings) => {
+            settings::save_settings(&db, &settings)?;
+            Ok(json!(ApiResponse::success(settings)))
+        }
+        DbOperation::CertificateInfo(id) => {
+            let connection = require_connection(&db, id)?;
+            let certificate = certificate::connection_certificate(&connection)?;
+            Ok(json!(ApiResponse::success(certificate)))
+        }
+        DbOperation::ListHostKeys(id) => {
+            let connection = require_connection(&db, id)?;
+            let keys = hostkeys::list_host_keys(
+                &db,
+                &connection.host,
+                connection.port,
+                connection.pinned_host_key.as_deref(),
+            )?;
+            Ok(json!(ApiResponse::success(keys)))
+        }
+        DbOperation::RemoveHostKey { id, fingerprint } => {
+            let connection = require_connection(&db, id)?;
+            hostkeys::remove_host_key(&db, &connection.host, connection.port, &fingerprint)?;
+            Ok(json!(ApiResponse::success(fingerprint)))
+        }
+        DbOperation::ScanHostKey(id) => {
+            let connection = require_connection(&db, id)?;
+            let timeouts = settings::resolve_timeouts(&db, id)?;
+            let key = hostkeys::scan_host_key(
+                &db,
+                &connection.host,
+                connection.port,
+                &timeouts,
+                &connection.algorithms,
+            )
+            .await?;
+            Ok(json!(ApiResponse::success(key)))
+        }
+        DbOperation::ReplaceHostKey { id, fingerprint } => {
+            let mut connection = require_connection(&db, id)?;
+            let timeouts = settings::resolve_timeouts(&db, id)?;
+            let key = hostkeys::replace_host_key(
+                &db,
+                &connection.host,
+                connection.port,
+                &fingerprint,
+                &timeouts,
+                &connection.algorithms,
+            )
+            .await?;
+            // 旧的固定指纹已失效
+            if connection.pinned_host_key.take().is_some() {
+                update_connection(&db, &mut connection)?;
+            }
+            Ok(json!(ApiResponse::success(key)))
+        }
+        DbOperation::PinHostKey { id, fingerprint } => {
+            let mut connection = require_connection(&db, id)?;
+            connection.pinned_host_key = fingerprint.filter(|f| !f.trim().is_empty());
+            update_connection(&db, &mut connection)
+        }
+        DbOperation::SearchHistory(query) => {
+            let entries = history::search_history(&db, &query)?;
+            Ok(json!(ApiResponse::success(entries)))
+        }
+        DbOperation::ClearHistory(id) => {
+            let count = history::clear_history(&db, id)?;
+            Ok(json!(ApiResponse::success(count)))
+        }
+        DbOperation::ImportSshConfig(path) => {
+            let report = ssh_config::import_ssh_config(&db, path.as_deref())?;
+            Ok(json!(ApiResponse::success(report)))
+        }
+        DbOperation::ListSnippets => {
+            let snippets = snippets::list_snippets(&db)?;
+            Ok(json!(ApiResponse::success(snippets)))
+        }
+        DbOperation::SaveSnippet(snippet) => {
+            let snippet = snippets::save_snippet(&db, snippet)?;
+            Ok(json!(ApiResponse::success(snippet)))
+        }
+        DbOperation::DeleteSnippet(id) => {
+            snippets::delete_snippet(&db, id)?;
+            Ok(json!(ApiResponse::success(id)))
+        }
+        DbOperation::GetHostProfile(id) => {
+            let profile = platform::get_profile(&db, id)?;
+            Ok(json!(ApiResponse::success(profile)))
+        }
+    };
+
+    if mutates && result.is_ok() {
+        backup::snapshot_after_change(&db);
+    }
+    result
+}
+
+fn save_connection(db: &Db, connection: &mut SshConnection) -> AppResult<serde_json::Value> {
+    let new_id = db.generate_id()?;
+    let create_at = current_timestamp();
+    connection.id = Some(new_id); // 将生成的 ID 分配给连接
+    connection.created_at = Some(create_at);
+    connection.updated_at = Some(create_at);
+    let key = connection.id.unwrap().to_string();
+    let value = serde_json::to_string(&connection)?.into_bytes();
+    db.insert(key, value)?;
+    Ok(json!(ApiResponse::success(connection)))
+}
+
+pub fn get_all_connections(db: &Db) -> AppResult<Vec<SshConnection>> {
+    let mut connections = Vec::new();
+    for item in db.iter() {
+        let (key, value) = item?;
+        // 单条记录损坏时隔离该记录，不影响其余连接的加载
+        match serde_json::from_slice::<SshConnection>(&value) {
+            Ok(connection) => connections.push(connection),
+            Err(e) => migrations::quarantine(db, &key, &value, &e.to_string())?,
+        }
+    }
+    connections.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+    Ok(connections)
+}
+
+fn require_connection(db: &Db, id: u64) -> AppResult<SshConnection> {
+    get_connection(db, id)?
+        .ok_or_else(|| AppError::not_found(format!("未找到 ID 为 {} 的连接", id)))
+}
+
+/// 按 ID 读取单个连接，不存在时返回 None
+pub fn get_connection(db: &Db, id: u64) -> AppResult<Option<SshConnection>> {
+    match db.get(id.to_string())? {
+        Some(value) => Ok(Some(serde_json::from_slice(&value)?)),
+        None => Ok(None),
+    }
+}
+
+fn update_connection(db: &Db, connection: &mut SshConnection) -> AppResult<serde_json::Value> {
+    if let Some(id) = connection.id {
+        let key = id.to_string();
+        let update_at = current_timestamp();
+        connection.updated_at = Some(update_at);
+        let value = serde_json::to_string(&connection)?.into_bytes();
+        db.insert(key, value)?;
+    } else {
+        return Err(AppError::invalid_input("连接ID不存在"));
+    }
+    Ok(json!(ApiResponse::success(connection)))
+}
+
+fn delete_connection(db: &Db, id: u64) -> AppResult<serde_json::Value> {
+    db.remove(id.to_string())?;
+    Ok(json!(ApiResponse::success(format!("成功删除ID为{}的连接", id))))
+}
+
+/// 记录一次成功连接：更新最近连接时间并累加连接次数
+pub fn record_connected(db: &Db, id: u64) -> AppResult<()> {
+    let key = id.to_string();
+    let Some(value) = db.get(&key)? else {
+        return Ok(());
+    };
+    let mut connection: SshConnection = serde_json::from_slice(&value)?;
+    connection.last_connected_at = Some(current_timestamp());
+    connection.connect_count += 1;
+    let value = serde_json::to_string(&connection)?.into_bytes();
+    db.insert(key, value)?;
+    db.flush()?;
+    Ok(())
+}
+
+pub fn search_connections(
+    db: &Db,
+    query: &str,
+    limit: Option<usize>,
+) -> AppResult<Vec<SshConnection>> {
+    let now = current_timestamp();
+    let query = query.trim();
+    let mut scored: Vec<(i64, SshConnection)> = get_all_connections(db)?
+        .into_iter()
+        .filter_map(|connection| {
+            let score = if query.is_empty() {
+                0
+            } else {
+                match_score(&connection, query)?
+            };
+            let boost = frecency_boost(&connection, now);
+            Some((score + boost, connection))
+        })
+        .collect();
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+    let limit = limit.unwrap_or(50);
+    Ok(scored.into_iter().take(limit).map(|(_, c)| c).collect())
+}
+
+// 各字段按权重取最高分，名称权重最高，备注最低
+fn match_score(connection: &SshConnection, query: &str) -> Option<i64> {
+    let mut fields: Vec<(&str, i64)> =
+        vec![(connection.name.as_str(), 3), (connection.host.as_str(), 2)];
+    if let Some(username) = &connection.username {
+        fields.push((username.as_str(), 1));
+    }
+    for tag in &connection.tags {
+        fields.push((tag.as_str(), 2));
+    }
+    if let Some(notes) = &connection.notes {
+        fields.push((notes.as_str(), 1));
+    }
+    fields
+        .into_iter()
+        .filter_map(|(text, weight)| fuzzy_score(query, text).map(|s| s * weight))
+        .max()
+}
+
+// 最近使用与常用连接加分：连接次数取对数，最近连接按天衰减
+fn frecency_boost(connection: &SshConnection, now: u64) -> i64 {
+    let frequency = ((connection.connect_count as f64) + 1.0).ln() * 10.0;
+    let recency = match connection.last_connected_at {
+        Some(ts) => {
+            let days = now.saturating_sub(ts) as f64 / 86_400.0;
+            30.0 / (1.0 + days)
+        }
+        None => 0.0,
+    };
+    (frequency + recency) as i64
+}
+
+/// 子序列模糊匹配，不区分大小写；连续命中和单词开头命中得分更高。
+/// 查询中的字符未全部按顺序出现时返回 None。
+pub(crate) fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let mut score = 0;
+    let mut qi = 0;
+    let mut prev_match: Option<usize> = None;
+    for (ti, ch) in text.iter().enumerate() {
+        if qi == query.len() {
+            break;
+        }
+        if *ch != query[qi] {
+            continue;
+        }
+        score += 1;
+        if ti > 0 && prev_match == Some(ti - 1) {
+            score += 5;
+        }
+        if ti == 0 || matches!(text[ti - 1], ' ' | '-' | '_' | '.' | '@' | '/') {
+            score += 8;
+        }
+        prev_match = Some(ti);
+        qi += 1;
+    }
+
+    if qi < query.len() {
+        return None;
+    }
+    // 完全包含查询串时额外加分
+    let needle: String = query.iter().collect();
+    let haystack: String = text.iter().collect();
+    if haystack.contains(&needle) {
+        score += 10;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_score_requires_every_query_char_in_order() {
+        assert_eq!(fuzzy_score("", "web-01"), Some(0));
+        assert!(fuzzy_score("WEB", "web-01").is_some());
+        assert!(fuzzy_score("w01", "web-01").is_some());
+        assert_eq!(fuzzy_score("10", "web-01"), None);
+        assert_eq!(fuzzy_score("db", "web-01"), None);
+    }
+
+    #[test]
+    fn fuzzy_score_prefers_contiguous_and_word_start_matches() {
+        let contiguous = fuzzy_score("prod", "prod-db").unwrap();
+        let scattered = fuzzy_score("prod", "p-r-o-d").unwrap();
+        let inner = fuzzy_score("prod", "reprodb").unwrap();
+        assert!(contiguous > inner);
+        assert!(inner > fuzzy_score("prod", "xpxrxoxd").unwrap());
+        // 每个字符都在单词开头，但没有连续命中
+        assert!(contiguous > scattered);
+    }
+}
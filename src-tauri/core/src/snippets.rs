@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::sled::current_timestamp;
+use crate::utils::shell_quote;
+
+/// 命令片段 tree，键为片段 ID
+const SNIPPETS_TREE: &str = "snippets";
+
+/// 可复用的命令模板。模板中的占位符写作 {{name}}、{{name:type}}、{{name=默认值}}
+/// 或 {{name:type=默认值}}，type 可选 string（默认）、int、bool
+#[derive(Serialize, Deserialize, Debug, Clone, TS)]
+pub struct Snippet {
+    #[ts(type = "number | null")]
+    pub id: Option<u64>,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub template: String,
+    // 由模板解析得到，保存时重新生成
+    #[serde(default)]
+    pub placeholders: Vec<Placeholder>,
+    #[ts(type = "number | null")]
+    pub created_at: Option<u64>,
+    #[ts(type = "number | null")]
+    pub updated_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct Placeholder {
+    pub name: String,
+    pub kind: PlaceholderKind,
+    pub default: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum PlaceholderKind {
+    String,
+    Int,
+    Bool,
+}
+
+/// 片段在单个连接上的执行结果
+#[derive(Serialize, Debug, TS)]
+pub struct SnippetRunResult {
+    pub id: usize,
+    pub command: String,
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+// 模板按占位符切分后的片段
+enum Part<'a> {
+    Text(&'a str),
+    Placeholder(Placeholder),
+}
+
+fn snippets_tree(db: &Db) -> AppResult<Tree> {
+    Ok(db.open_tree(SNIPPETS_TREE)?)
+}
+
+pub fn list_snippets(db: &Db) -> AppResult<Vec<Snippet>> {
+    let mut snippets = Vec::new();
+    for item in snippets_tree(db)?.iter() {
+        let (_, value) = item?;
+        snippets.push(serde_json::from_slice::<Snippet>(&value)?);
+    }
+    snippets.sort_by_key(|s| s.name.to_lowercase());
+    Ok(snippets)
+}
+
+pub fn get_snippet(db: &Db, id: u64) -> AppResult<Snippet> {
+    match snippets_tree(db)?.get(id.to_string())? {
+        Some(value) => Ok(serde_json::from_slice(&value)?),
+        None => Err(AppError::not_found(format!("未找到 ID 为 {} 的片段", id))),
+    }
+}
+
+/// 新增或更新片段，保存前解析模板并校验默认值
+/// 检查片段名称与模板，返回模板中的占位符
+pub(crate) fn check_snippet(snippet: &Snippet) -> AppResult<Vec<Placeholder>> {
+    if snippet.name.trim().is_empty() {
+        return Err(AppError::invalid_input("片段名称不能为空"));
+    }
+    placeholders(&snippet.template)
+}
+
+pub fn save_snippet(db: &Db, mut snippet: Snippet) -> AppResult<Snippet> {
+    snippet.placeholders = check_snippet(&snippet)?;
+    let tree = snippets_tree(db)?;
+    let now = current_timestamp();
+    match snippet.id {
+        Some(id) => {
+            let existing = get_snippet(db, id)?;
+            snippet.created_at = existing.created_at;
+        }
+        None => {
+            snippet.id = Some(db.generate_id()?);
+            snippet.created_at = Some(now);
+        }
+    }
+    snippet.updated_at = Some(now);
+    let id = snippet.id.unwrap_or_default();
+    tree.insert(id.to_string(), serde_json::to_vec(&snippet)?)?;
+    Ok(snippet)
+}
+
+pub fn delete_snippet(db: &Db, id: u64) -> AppResult<()> {
+    if snippets_tree(db)?.remove(id.to_string())?.is_none() {
+        return Err(AppError::not_found(format!("未找到 ID 为 {} 的片段", id)));
+    }
+    Ok(())
+}
+
+/// 模板中出现的占位符，同名占位符只返回第一次出现的定义
+pub fn placeholders(template: &str) -> AppResult<Vec<Placeholder>> {
+    let mut placeholders: Vec<Placeholder> = Vec::new();
+    for part in parse(template)? {
+        if let Part::Placeholder(placeholder) = part {
+            if let Some(existing) = placeholders.iter().find(|p| p.name == placeholder.name) {
+                if existing.kind != placeholder.kind {
+                    return Err(AppError::invalid_input(format!(
+                        "占位符 {} 的类型不一致",
+                        placeholder.name
+                    )));
+                }
+                continue;
+            }
+            if let Some(default) = &placeholder.default {
+                validate(&placeholder, default)?;
+            }
+            placeholders.push(placeholder);
+        }
+    }
+    Ok(placeholders)
+}
+
+/// 用给定的值渲染模板，未提供的值使用该占位符第一次出现时定义的默认值，字符串值会做 shell 转义
+pub fn render(template: &str, values: &HashMap<String, String>) -> AppResult<String> {
+    let definitions = placeholders(template)?;
+    let mut command = String::new();
+    for part in parse(template)? {
+        match part {
+            Part::Text(text) => command.push_str(text),
+            Part::Placeholder(placeholder) => {
+                let placeholder = definitions
+                    .iter()
+                    .find(|p| p.name == placeholder.name)
+                    .unwrap_or(&placeholder);
+                let value = values
+                    .get(&placeholder.name)
+                    .or(placeholder.default.as_ref())
+                    .ok_or_else(|| {
+                        AppError::invalid_input(format!("缺少占位符 {} 的值", placeholder.name))
+                    })?;
+                validate(placeholder, value)?;
+                match placeholder.kind {
+                    PlaceholderKind::String => command.push_str(&shell_quote(value)),
+                    PlaceholderKind::Int | PlaceholderKind::Bool => command.push_str(value.trim()),
+                }
+            }
+        }
+    }
+    Ok(command)
+}
+
+fn parse(template: &str) -> AppResult<Vec<Part<'_>>> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| AppError::invalid_input("模板中的 {{ 没有闭合"))?;
+        if start > 0 {
+            parts.push(Part::Text(&rest[..start]));
+        }
+        parts.push(Part::Placeholder(parse_placeholder(
+            &rest[start + 2..start + end],
+        )?));
+        rest = &rest[start + end + 2..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    Ok(parts)
+}
+
+fn parse_placeholder(spec: &str) -> AppResult<Placeholder> {
+    let (head, default) = match spec.split_once('=') {
+        Some((head, default)) => (head, Some(default.to_string())),
+        None => (spec, None),
+    };
+    let (name, kind) = match head.split_once(':') {
+        Some((name, kind)) => (name.trim(), kind.trim()),
+        None => (head.trim(), "string"),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(AppError::invalid_input(format!(
+            "无效的占位符名称: {}",
+            name
+        )));
+    }
+    let kind = match kind {
+        "string" => PlaceholderKind::String,
+        "int" => PlaceholderKind::Int,
+        "bool" => PlaceholderKind::Bool,
+        other => {
+            return Err(AppError::invalid_input(format!(
+                "占位符 {} 的类型 {} 不受支持",
+                name, other
+            )))
+        }
+    };
+    Ok(Placeholder {
+        name: name.to_string(),
+        kind,
+        default,
+    })
+}
+
+fn validate(placeholder: &Placeholder, value: &str) -> AppResult<()> {
+    let valid = match placeholder.kind {
+        PlaceholderKind::String => true,
+        PlaceholderKind::Int => value.trim().parse::<i64>().is_ok(),
+        PlaceholderKind::Bool => matches!(value.trim(), "true" | "false"),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::invalid_input(format!(
+            "占位符 {} 的值 {:?} 不是有效的 {:?}",
+            placeholder.name, value, placeholder.kind
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_placeholder_definitions() {
+        let parsed =
+            placeholders("tail -n {{lines:int=100}} {{file}} && echo {{lines:int}}").unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                Placeholder {
+                    name: "lines".into(),
+                    kind: PlaceholderKind::Int,
+                    default: Some("100".into()),
+                },
+                Placeholder {
+                    name: "file".into(),
+                    kind: PlaceholderKind::String,
+                    default: None,
+                },
+            ]
+        );
+
+        assert!(placeholders("echo {{name").is_err());
+        assert!(placeholders("echo {{bad-name}}").is_err());
+        assert!(placeholders("echo {{n:float}}").is_err());
+        assert!(placeholders("echo {{n:int=abc}}").is_err());
+        assert!(placeholders("echo {{n:int}} {{n:bool}}").is_err());
+    }
+
+    #[test]
+    fn later_occurrences_use_first_default() {
+        let template = "echo {{dir=/var/log}} {{dir}}";
+        assert_eq!(
+            render(template, &HashMap::new()).unwrap(),
+            "echo /var/log /var/log"
+        );
+        assert_eq!(
+            render(template, &values(&[("dir", "/tmp")])).unwrap(),
+            "echo /tmp /tmp"
+        );
+        assert!(render("echo {{dir}}", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn escapes_string_values() {
+        let command = render(
+            "grep {{pattern}} -m {{count:int}} {{verbose:bool}}",
+            &values(&[
+                ("pattern", "'; rm -rf / #"),
+                ("count", " 5 "),
+                ("verbose", "true"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(command, r"grep ''\''; rm -rf / #' -m 5 true");
+        assert!(render("sleep {{n:int}}", &values(&[("n", "1; reboot")])).is_err());
+    }
+}
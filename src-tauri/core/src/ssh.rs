@@ -0,0 +1,1120 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use rand::Rng;
+use russh::client::{Handle, Msg};
+use russh::Channel;
+use sled::Db;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use ts_rs::TS;
+
+use serde_json::json;
+
+use crate::algorithms::NegotiatedAlgorithms;
+use crate::certificate;
+use crate::containers::{self, ContainerAction, ContainerRuntime, Runner};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::events::{
+    CertificateExpiring, ConnectionOpened, ConnectionState, ConnectionStateUpdate, CpuUpdate,
+    DiskUpdate, EventSink, MemoryUpdate, NetworkUpdate, NetworkUsage, ServerInfo, Sink,
+    UptimeUpdate,
+};
+use crate::files::{self, BackupTarget, FileVersion};
+use crate::forward;
+use crate::history::{self, HistorySource};
+use crate::hostkeys::HostKeyVerifier;
+use crate::logs::{self, LogTailOptions};
+use crate::metrics;
+use crate::platform;
+use crate::recording::{self, RecordOptions};
+use crate::session::{Client, ConnectParams, Credentials, Session};
+use crate::settings;
+use crate::shell;
+use crate::sled::get_connection;
+use crate::snippets::{self, SnippetRunResult};
+use crate::streams;
+use crate::sudo;
+use crate::systemd::{self, UnitAction};
+use crate::utils::{expand_home, ApiResponse};
+
+// 定义全局连接池
+static CONNECTION_POOL: Lazy<Mutex<Vec<(usize, Session)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// 断线检测间隔
+pub(crate) const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+// 重连退避参数
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+
+#[derive(Debug, serde::Deserialize, TS)]
+pub enum SshCommand {
+    OpenConnection {
+        id: usize,
+        username: String,
+        password: String,
+        host: String,
+        port: u16,
+    },
+    CloseConnection(usize),
+    // sudo 为 true 时通过 sudo 执行，需要密码时自动使用保存的凭据或 SetSudoPassword 提交的密码
+    ExecuteQuery {
+        id: usize,
+        query: String,
+        #[serde(default)]
+        sudo: bool,
+    },
+    RetryInfoQuery(usize),
+    CloseAllConnections,
+    // 推算连接会协商出的算法，结果来自单独的探测连接，不是会话实际使用的算法
+    Diagnostics(usize),
+    // 打开交互式终端，record 为 true 时录制为 asciicast，record_input 控制是否记录输入
+    OpenShell {
+        id: usize,
+        cols: u32,
+        rows: u32,
+        record: bool,
+        record_input: bool,
+    },
+    ShellInput {
+        id: usize,
+        data: String,
+    },
+    ResizeShell {
+        id: usize,
+        cols: u32,
+        rows: u32,
+    },
+    CloseShell(usize),
+    ListRecordings,
+    DeleteRecording(String),
+    ExportRecording {
+        name: String,
+        path: String,
+    },
+    // speed 为回放倍速，max_idle 为两帧之间的最长等待秒数
+    ReplayRecording {
+        name: String,
+        speed: f64,
+        max_idle: Option<f64>,
+    },
+    // 停止正在进行的回放，传入录像文件名
+    StopReplay(String),
+    // 渲染命令片段并依次在多个连接上执行，values 为占位符的值
+    RunSnippet {
+        #[ts(type = "number")]
+        snippet_id: u64,
+        ids: Vec<usize>,
+        #[ts(type = "Record<string, string>")]
+        values: HashMap<String, String>,
+        #[serde(default)]
+        sudo: bool,
+    },
+    // 校验并保存本次会话的 sudo 密码，用于密钥认证或 sudo 密码与登录密码不同的情况，
+    // password 为 null 时清除
+    SetSudoPassword {
+        id: usize,
+        password: Option<String>,
+    },
+    // 写入远程文件，sudo 为 true 时以 root 身份保存
+    WriteFile {
+        id: usize,
+        path: String,
+        contents: String,
+        sudo: bool,
+    },
+    // 打开远程文本文件用于编辑，返回内容与版本
+    OpenFile {
+        id: usize,
+        path: String,
+        sudo: bool,
+    },
+    // 保存编辑后的文件，expected 为打开时的版本，远端已被修改时除非 force 否则拒绝保存；
+    // backup 缺省时备份到本地
+    SaveFile {
+        id: usize,
+        path: String,
+        contents: String,
+        expected: Option<FileVersion>,
+        force: bool,
+        #[serde(default)]
+        backup: BackupTarget,
+        sudo: bool,
+    },
+    // systemd 服务管理，all 为 true 时包含未运行的服务
+    ListUnits {
+        id: usize,
+        all: bool,
+    },
+    // sudo 为 true 时通过 sudo 执行
+    UnitAction {
+        id: usize,
+        unit: String,
+        action: UnitAction,
+        sudo: bool,
+    },
+    // 状态与最近的日志，journal_lines 默认 50
+    UnitStatus {
+        id: usize,
+        unit: String,
+        journal_lines: Option<usize>,
+    },
+    // 容器管理，runtime 为 null 时按远端环境选择 docker 或 podman，sudo 为 true 时通过 sudo 执行
+    ListContainers {
+        id: usize,
+        runtime: Option<ContainerRuntime>,
+        sudo: bool,
+        // 附带资源占用，需要额外采样约 2 秒
+        stats: bool,
+    },
+    ContainerAction {
+        id: usize,
+        container: String,
+        action: ContainerAction,
+        runtime: Option<ContainerRuntime>,
+        sudo: bool,
+    },
+    // 通过输出流查看容器日志，tail 默认 200 行
+    ContainerLogs {
+        id: usize,
+        container: String,
+        runtime: Option<ContainerRuntime>,
+        sudo: bool,
+        tail: Option<usize>,
+        follow: bool,
+    },
+    // 在容器中打开终端，与 OpenShell 共用连接的终端，shell 默认为 sh
+    ContainerShell {
+        id: usize,
+        container: String,
+        runtime: Option<ContainerRuntime>,
+        sudo: bool,
+        shell: Option<String>,
+        cols: u32,
+        rows: u32,
+    },
+    // 在一个通道上跟踪多个日志文件或 systemd 单元，过滤后的行通过 log-lines 事件批量发送
+    TailLogs {
+        id: usize,
+        options: LogTailOptions,
+    },
+    // 查询日志流缓存的最近几行
+    LogBuffer(#[ts(type = "number")] u64),
+    // 停止 ContainerLogs、TailLogs 等命令打开的输出流
+    StopStream(#[ts(type = "number")] u64),
+}
+
+/// 执行 SSH 命令，事件通过 sink 发送
+pub async fn run_ssh_command(
+    command: SshCommand,
+    sink: Sink,
+    db: &Db,
+) -> AppResult<serde_json::Value> {
+    match command {
+        SshCommand::OpenConnection {
+            id,
+            username,
+            password,
+            host,
+            port,
+        } => open_connection(id, username, password, host, port, sink, db).await,
+        SshCommand::CloseConnection(id) => close_connection(id).await,
+        SshCommand::ExecuteQuery { id, query, sudo } => {
+            if query == "baseinfo" {
+                query_server_info(id).await
+            } else {
+                execute_query(id, query, sudo, db).await
+            }
+        }
+        SshCommand::RetryInfoQuery(id) => retry_info_query(id, sink).await,
+        SshCommand::CloseAllConnections => close_all_connections().await,
+        SshCommand::Diagnostics(id) => diagnostics(id).await,
+        SshCommand::OpenShell {
+            id,
+            cols,
+            rows,
+            record,
+            record_input,
+        } => open_shell(id, cols, rows, record, record_input, sink, db.clone()).await,
+        SshCommand::ShellInput { id, data } => {
+            shell::write(id, &data).await?;
+            Ok(json!(ApiResponse::success(id)))
+        }
+        SshCommand::ResizeShell { id, cols, rows } => {
+            shell::resize(id, cols, rows).await?;
+            Ok(json!(ApiResponse::success(id)))
+        }
+        SshCommand::CloseShell(id) => {
+            shell::close_shell(id).await;
+            Ok(json!(ApiResponse::success(id)))
+        }
+        SshCommand::ListRecordings => {
+            let recordings = recording::list_recordings()?;
+            Ok(json!(ApiResponse::success(recordings)))
+        }
+        SshCommand::DeleteRecording(name) => {
+            recording::delete_recording(&name)?;
+            Ok(json!(ApiResponse::success(name)))
+        }
+        SshCommand::ExportRecording { name, path } => {
+            let path = recording::export_recording(&name, &path)?;
+            Ok(json!(ApiResponse::success(path)))
+        }
+        SshCommand::ReplayRecording {
+            name,
+            speed,
+            max_idle,
+        } => {
+            let header = recording::start_replay(&name, speed, max_idle, sink).await?;
+            Ok(json!(ApiResponse::success(header)))
+        }
+        SshCommand::StopReplay(name) => {
+            recording::stop_replay(&name, sink).await?;
+            Ok(json!(ApiResponse::success(name)))
+        }
+        SshCommand::RunSnippet {
+            snippet_id,
+            ids,
+            values,
+            sudo,
+        } => run_snippet(snippet_id, ids, values, sudo, db).await,
+        SshCommand::SetSudoPassword { id, password } => set_sudo_password(id, password).await,
+        SshCommand::WriteFile {
+            id,
+            path,
+            contents,
+            sudo,
+        } => write_file(id, path, contents, sudo).await,
+        SshCommand::OpenFile { id, path, sudo } => open_file(id, path, sudo).await,
+        SshCommand::SaveFile {
+            id,
+            path,
+            contents,
+            expected,
+            force,
+            backup,
+            sudo,
+        } => save_file(id, path, contents, expected, force, backup, sudo).await,
+        SshCommand::ListUnits { id, all } => list_units(id, all).await,
+        SshCommand::UnitAction {
+            id,
+            unit,
+            action,
+            sudo,
+        } => unit_action(id, unit, action, sudo).await,
+        SshCommand::UnitStatus {
+            id,
+            unit,
+            journal_lines,
+        } => unit_status(id, unit, journal_lines).await,
+        SshCommand::ListContainers {
+            id,
+            runtime,
+            sudo,
+            stats,
+        } => list_containers(id, runtime, sudo, stats).await,
+        SshCommand::ContainerAction {
+            id,
+            container,
+            action,
+            runtime,
+            sudo,
+        } => container_action(id, container, action, runtime, sudo).await,
+        SshCommand::ContainerLogs {
+            id,
+            container,
+            runtime,
+            sudo,
+            tail,
+            follow,
+        } => container_logs(id, container, runtime, sudo, tail, follow, sink).await,
+        SshCommand::ContainerShell {
+            id,
+            container,
+            runtime,
+            sudo,
+            shell,
+            cols,
+            rows,
+        } => {
+            container_shell(
+                id,
+                container,
+                runtime,
+                sudo,
+                shell,
+                cols,
+                rows,
+                sink,
+                db.clone(),
+            )
+            .await
+        }
+        SshCommand::TailLogs { id, options } => tail_logs(id, options, sink).await,
+        SshCommand::LogBuffer(stream_id) => {
+            let lines = logs::buffered_lines(stream_id)?;
+            Ok(json!(ApiResponse::success(lines)))
+        }
+        SshCommand::StopStream(stream_id) => {
+            streams::stop_stream(stream_id).await?;
+            Ok(json!(ApiResponse::success(stream_id)))
+        }
+    }
+}
+
+async fn open_connection(
+    id: usize,
+    username: String,
+    password: String,
+    host: String,
+    port: u16,
+    sink: Sink,
+    db: &Db,
+) -> AppResult<serde_json::Value> {
+    // 解析 URL
+
+    // 将连接添加到全局连接池
+    emit_state(sink.as_ref(), id, ConnectionState::Connecting, 0, None);
+    let new_session = match connect_session(id, username, password, (host.clone(), port), db).await
+    {
+        Ok(session) => session,
+        Err(e) => {
+            emit_state(
+                sink.as_ref(),
+                id,
+                ConnectionState::Failed,
+                0,
+                Some(e.to_string()),
+            );
+            return Err(e);
+        }
+    };
+    if let Err(e) = crate::sled::record_connected(db, id as u64) {
+        eprintln!("记录连接时间失败: {}", e);
+    }
+    let profile = detect_profile(&new_session, id, db).await;
+    let mut pool = CONNECTION_POOL.lock().await;
+    pool.push((id, new_session));
+    if let Some((_, session)) = pool.iter_mut().find(|(conn_id, _)| *conn_id == id) {
+        session.profile = profile;
+        session.forward_tasks = forward::start_forwards(&session.session, &session.forwards).await;
+        let info_task = tokio::spawn(start_info_query(id, sink.clone()));
+        session.info_task = Some(info_task);
+        session.watch_task = Some(tokio::spawn(watch_connection(id, sink.clone())));
+    }
+    emit_state(sink.as_ref(), id, ConnectionState::Connected, 0, None);
+
+    // 这里可以添加更多的连接管理逻辑，比如限制连接池大小、处理连接超时等
+    Ok(json!(ApiResponse::success(ConnectionOpened {
+        id,
+        message: format!("连接到: {:?}", host),
+    })))
+}
+
+/// 探测远端环境并保存，失败时沿用默认采集方式，不影响连接
+async fn detect_profile(session: &Session, id: usize, db: &Db) -> platform::HostProfile {
+    match platform::detect(session).await {
+        Ok(profile) => {
+            if let Err(e) = platform::save_profile(db, id as u64, &profile) {
+                eprintln!("保存远端环境信息失败: {}", e);
+            }
+            profile
+        }
+        Err(e) => {
+            eprintln!("探测远端环境失败: {}", e);
+            platform::HostProfile::default()
+        }
+    }
+}
+
+/// 按保存的连接配置建立会话：配置了私钥时使用私钥认证，私钥旁有证书时改用证书认证，否则使用密码
+pub async fn connect_session(
+    id: usize,
+    username: String,
+    password: String,
+    addr: (String, u16),
+    db: &Db,
+) -> AppResult<Session> {
+    let connection = get_connection(db, id as u64)?;
+    let identity_file = connection
+        .as_ref()
+        .and_then(|c| c.identity_file.as_deref())
+        .filter(|f| !f.is_empty());
+    let credentials = match (identity_file, &connection) {
+        (Some(identity_file), Some(connection)) => {
+            let key = expand_home(identity_file)?;
+            match certificate::certificate_path(connection)? {
+                Some(certificate) => Credentials::Certificate { key, certificate },
+                None => Credentials::PrivateKey(key),
+            }
+        }
+        _ => Credentials::Password(password),
+    };
+    let forwards = connection
+        .as_ref()
+        .map(|c| c.local_forwards.clone())
+        .unwrap_or_default();
+    let params = ConnectParams {
+        host_keys: HostKeyVerifier {
+            db: db.clone(),
+            host: addr.0.clone(),
+            port: addr.1,
+            pinned: connection.as_ref().and_then(|c| c.pinned_host_key.clone()),
+        },
+        host: addr.0,
+        port: addr.1,
+        user: username,
+        credentials,
+        timeouts: settings::resolve_timeouts(db, id as u64)?,
+        algorithms: connection.map(|c| c.algorithms).unwrap_or_default(),
+    };
+    let mut session = Session::open(params).await?;
+    session.forwards = forwards;
+    Ok(session)
+}
+
+async fn open_shell(
+    id: usize,
+    cols: u32,
+    rows: u32,
+    record: bool,
+    record_input: bool,
+    sink: Sink,
+    db: Db,
+) -> AppResult<serde_json::Value> {
+    let record = record.then_some(RecordOptions { record_input });
+    let pool = CONNECTION_POOL.lock().await;
+    let (_, session) = pool
+        .iter()
+        .find(|(conn_id, _)| *conn_id == id)
+        .ok_or_else(|| AppError::connection_not_found(id))?;
+    let opened =
+        shell::open_shell(id, &session.session, None, cols, rows, record, sink, db).await?;
+    Ok(json!(ApiResponse::success(opened)))
+}
+
+async fn close_connection(id: usize) -> AppResult<serde_json::Value> {
+    shell::close_shell(id).await;
+    streams::stop_streams(id).await;
+    let mut pool = CONNECTION_POOL.lock().await;
+    if let Some(index) = pool.iter().position(|(conn_id, _)| *conn_id == id) {
+        let (_, mut session) = pool.remove(index);
+        if let Some(info_task) = session.info_task.take() {
+            info_task.abort();
+        }
+        session.close().await?;
+        Ok(json!(ApiResponse::success(format!(
+            "已关闭连接 ID: {}",
+            id
+        ))))
+    } else {
+        Err(AppError::connection_not_found(id))
+    }
+}
+
+async fn diagnostics(id: usize) -> AppResult<serde_json::Value> {
+    let pool = CONNECTION_POOL.lock().await;
+    let (_, session) = pool
+        .iter()
+        .find(|(conn_id, _)| *conn_id == id)
+        .ok_or_else(|| AppError::connection_not_found(id))?;
+    let negotiated = session
+        .negotiated
+        .clone()
+        .ok_or_else(|| AppError::parse("未能获取该连接协商的算法"))?;
+    Ok(json!(ApiResponse::success(negotiated)))
+}
+
+async fn close_all_connections() -> AppResult<serde_json::Value> {
+    let mut pool = CONNECTION_POOL.lock().await;
+    let mut count = 0;
+    for (id, session) in pool.iter_mut() {
+        shell::close_shell(*id).await;
+        streams::stop_streams(*id).await;
+        session.close().await?;
+        count += 1;
+    }
+    pool.clear();
+    println!("已关闭 {} 个连接", count);
+    Ok(json!(ApiResponse::success("已关闭所有连接")))
+}
+
+async fn execute_query(
+    connection_id: usize,
+    query: String,
+    sudo: bool,
+    db: &Db,
+) -> AppResult<serde_json::Value> {
+    let result = run_command(connection_id, &query, sudo, db).await?;
+    Ok(json!(ApiResponse::success(result)))
+}
+
+/// 在连接上执行命令并记录历史，非零退出状态视为错误
+async fn run_command(connection_id: usize, query: &str, sudo: bool, db: &Db) -> AppResult<String> {
+    let started = Instant::now();
+    // 只在打开通道时持有连接池锁，等待命令结束期间不影响其他连接
+    let pending = {
+        let pool = CONNECTION_POOL.lock().await;
+        let Some((_, session)) = pool.iter().find(|(id, _)| *id == connection_id) else {
+            return Err(AppError::connection_not_found(connection_id));
+        };
+        if sudo {
+            sudo::start(session, query).await.map(PendingCommand::Sudo)
+        } else {
+            session.exec(query).await.map(PendingCommand::Plain)
+        }
+    };
+    let result = match pending {
+        Ok(PendingCommand::Sudo(exec)) => {
+            let result = exec.wait().await;
+            if matches!(&result, Err(e) if e.kind == ErrorKind::Auth) {
+                clear_sudo_password(connection_id).await;
+            }
+            result
+        }
+        Ok(PendingCommand::Plain(mut channel)) => Ok(Session::collect(&mut channel).await),
+        Err(e) => Err(e),
+    };
+    let exit_code = result.as_ref().ok().and_then(|(_, status)| *status);
+    history::record(
+        db,
+        connection_id as u64,
+        query,
+        HistorySource::Query,
+        exit_code,
+        started.elapsed().as_millis() as u64,
+    );
+    let (result, exit_status) = result?;
+    if let Some(status) = exit_status.filter(|s| *s != 0) {
+        return Err(AppError::command("命令返回非零退出状态")
+            .with_details(format!("exit status: {}", status)));
+    }
+    Ok(result)
+}
+
+// 已开始执行、等待结果的命令
+enum PendingCommand {
+    Sudo(sudo::SudoExec),
+    Plain(Channel<Msg>),
+}
+
+// sudo 密码错误时清除会话内保存的密码
+async fn clear_sudo_password(id: usize) {
+    let mut pool = CONNECTION_POOL.lock().await;
+    if let Some((_, session)) = pool.iter_mut().find(|(conn_id, _)| *conn_id == id) {
+        session.sudo_password = None;
+    }
+}
+
+// 模板只渲染一次，单个连接失败不影响其余连接
+async fn run_snippet(
+    snippet_id: u64,
+    ids: Vec<usize>,
+    values: HashMap<String, String>,
+    sudo: bool,
+    db: &Db,
+) -> AppResult<serde_json::Value> {
+    if ids.is_empty() {
+        return Err(AppError::invalid_input("至少选择一个连接"));
+    }
+    let snippet = snippets::get_snippet(db, snippet_id)?;
+    let command = snippets::render(&snippet.template, &values)?;
+    let mut results = Vec::new();
+    for id in ids {
+        let result = run_command(id, &command, sudo, db).await;
+        results.push(SnippetRunResult {
+            id,
+            command: command.clone(),
+            output: result.as_ref().ok().cloned(),
+            error: result.err().map(|e| e.to_string()),
+        });
+    }
+    Ok(json!(ApiResponse::success(results)))
+}
+
+async fn set_sudo_password(id: usize, password: Option<String>) -> AppResult<serde_json::Value> {
+    let mut pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter_mut().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    match password {
+        Some(password) => sudo::set_password(session, password).await?,
+        None => session.sudo_password = None,
+    }
+    Ok(json!(ApiResponse::success(id)))
+}
+
+async fn write_file(
+    id: usize,
+    path: String,
+    contents: String,
+    sudo: bool,
+) -> AppResult<serde_json::Value> {
+    let mut pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter_mut().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    files::write_file(session, &path, contents.as_bytes(), sudo).await?;
+    Ok(json!(ApiResponse::success(path)))
+}
+
+async fn open_file(id: usize, path: String, sudo: bool) -> AppResult<serde_json::Value> {
+    let mut pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter_mut().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    let file = files::open_file(session, &path, sudo).await?;
+    Ok(json!(ApiResponse::success(file)))
+}
+
+async fn save_file(
+    id: usize,
+    path: String,
+    contents: String,
+    expected: Option<FileVersion>,
+    force: bool,
+    backup: BackupTarget,
+    sudo: bool,
+) -> AppResult<serde_json::Value> {
+    let mut pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter_mut().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    let saved = files::save_file(
+        session,
+        &path,
+        &contents,
+        expected.as_ref(),
+        force,
+        backup,
+        sudo,
+    )
+    .await?;
+    Ok(json!(ApiResponse::success(saved)))
+}
+
+async fn list_units(id: usize, all: bool) -> AppResult<serde_json::Value> {
+    let pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    let units = systemd::list_units(session, all).await?;
+    Ok(json!(ApiResponse::success(units)))
+}
+
+async fn unit_action(
+    id: usize,
+    unit: String,
+    action: UnitAction,
+    sudo: bool,
+) -> AppResult<serde_json::Value> {
+    let pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    let output = systemd::unit_action(session, &unit, action, sudo).await?;
+    Ok(json!(ApiResponse::success(output)))
+}
+
+async fn unit_status(
+    id: usize,
+    unit: String,
+    journal_lines: Option<usize>,
+) -> AppResult<serde_json::Value> {
+    let pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    let status = systemd::unit_status(session, &unit, journal_lines).await?;
+    Ok(json!(ApiResponse::success(status)))
+}
+
+async fn list_containers(
+    id: usize,
+    runtime: Option<ContainerRuntime>,
+    sudo: bool,
+    stats: bool,
+) -> AppResult<serde_json::Value> {
+    let pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    let runner = Runner::new(&session.profile, runtime, sudo);
+    let containers = containers::list_containers(session, runner, stats).await?;
+    Ok(json!(ApiResponse::success(containers)))
+}
+
+async fn container_action(
+    id: usize,
+    container: String,
+    action: ContainerAction,
+    runtime: Option<ContainerRuntime>,
+    sudo: bool,
+) -> AppResult<serde_json::Value> {
+    let pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    let runner = Runner::new(&session.profile, runtime, sudo);
+    let output = containers::container_action(session, runner, &container, action).await?;
+    Ok(json!(ApiResponse::success(output)))
+}
+
+async fn container_logs(
+    id: usize,
+    container: String,
+    runtime: Option<ContainerRuntime>,
+    sudo: bool,
+    tail: Option<usize>,
+    follow: bool,
+    sink: Sink,
+) -> AppResult<serde_json::Value> {
+    let pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    let runner = Runner::new(&session.profile, runtime, sudo);
+    let command = containers::logs_command(runner, &container, tail, follow)?;
+    let opened = streams::start_stream(id, &session.session, &command, sink).await?;
+    Ok(json!(ApiResponse::success(opened)))
+}
+
+async fn tail_logs(id: usize, options: LogTailOptions, sink: Sink) -> AppResult<serde_json::Value> {
+    let pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    let opened = logs::tail_logs(id, session, options, sink).await?;
+    Ok(json!(ApiResponse::success(opened)))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn container_shell(
+    id: usize,
+    container: String,
+    runtime: Option<ContainerRuntime>,
+    sudo: bool,
+    shell: Option<String>,
+    cols: u32,
+    rows: u32,
+    sink: Sink,
+    db: Db,
+) -> AppResult<serde_json::Value> {
+    let pool = CONNECTION_POOL.lock().await;
+    let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) else {
+        return Err(AppError::connection_not_found(id));
+    };
+    let runner = Runner::new(&session.profile, runtime, sudo);
+    let command = containers::exec_command(runner, &container, shell.as_deref())?;
+    let opened = shell::open_shell(
+        id,
+        &session.session,
+        Some(&command),
+        cols,
+        rows,
+        None,
+        sink,
+        db,
+    )
+    .await?;
+    Ok(json!(ApiResponse::success(opened)))
+}
+
+async fn query_server_info(connection_id: usize) -> AppResult<serde_json::Value> {
+    println!("查询服务器信息, id: {}", connection_id);
+    let pool = CONNECTION_POOL.lock().await;
+    if let Some((_, session)) = pool.iter().find(|(id, _)| *id == connection_id) {
+        let strategy = session.profile.strategy();
+        let uptime = metrics::uptime(session).await?;
+        let memory_usage = metrics::memory(session, strategy).await?;
+        let cpu_usage = metrics::cpu(session, strategy).await?;
+
+        // 查询网络速率
+        let (rx_before, tx_before) = metrics::network_bytes(session, strategy).await?;
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        let (rx_after, tx_after) = metrics::network_bytes(session, strategy).await?;
+
+        let rx_speed = (rx_after.saturating_sub(rx_before) as f64) / 1024.0; // 转换为kb/s
+        let tx_speed = (tx_after.saturating_sub(tx_before) as f64) / 1024.0; // 转换为kb/s
+
+        // 查询磁盘空间使用情况
+        let disk = metrics::disk(session, strategy).await?;
+
+        let network_usage = NetworkUsage { rx_speed, tx_speed };
+
+        Ok(json!(ApiResponse::success(ServerInfo {
+            uptime,
+            memory_usage,
+            cpu_usage,
+            network_usage,
+            disk_usage: disk.totals,
+            upload_speed: tx_speed,
+            download_speed: rx_speed,
+        })))
+    } else {
+        Err(AppError::connection_not_found(connection_id))
+    }
+}
+
+async fn start_info_query(id: usize, sink: Sink) {
+    let mut uptime_interval = tokio::time::interval(std::time::Duration::from_secs(5));
+    let mut memory_interval = tokio::time::interval(std::time::Duration::from_secs(5));
+    let mut cpu_interval = tokio::time::interval(std::time::Duration::from_secs(5));
+    let mut network_interval = tokio::time::interval(std::time::Duration::from_secs(1));
+    let mut disk_interval = tokio::time::interval(std::time::Duration::from_secs(5)); // Increased interval for disk usage
+
+    loop {
+        tokio::select! {
+            _ = uptime_interval.tick() => {
+                if let Err(e) = query_and_emit_uptime(id, sink.as_ref()).await {
+                    eprintln!("查询运行时间失败: {}", e);
+                }
+            }
+            _ = memory_interval.tick() => {
+                if let Err(e) = query_and_emit_memory(id, sink.as_ref()).await {
+                    eprintln!("查询内存使用失败: {}", e);
+                }
+            }
+            _ = cpu_interval.tick() => {
+                if let Err(e) = query_and_emit_cpu(id, sink.as_ref()).await {
+                    eprintln!("查询CPU使用失败: {}", e);
+                }
+            }
+            _ = network_interval.tick() => {
+                if let Err(e) = query_and_emit_network(id, sink.as_ref()).await {
+                    eprintln!("查询网络使用失败: {}", e);
+                }
+            }
+            _ = disk_interval.tick() => {
+                if let Err(e) = query_and_emit_disk(id, sink.as_ref()).await {
+                    eprintln!("查询磁盘使用失败: {}", e);
+                }
+            }
+        }
+    }
+}
+
+fn emit_state(
+    sink: &dyn EventSink,
+    id: usize,
+    state: ConnectionState,
+    attempt: u32,
+    message: Option<String>,
+) {
+    sink.emit(
+        &format!("connection-state-{id}"),
+        ConnectionStateUpdate {
+            id,
+            state,
+            attempt,
+            message,
+        },
+    );
+}
+
+/// 第 attempt 次重连前的等待时间：指数退避，上限 MAX_RECONNECT_DELAY，
+/// 取一半固定等待加一半随机抖动，避免多个连接同时重连
+fn reconnect_delay(attempt: u32) -> Duration {
+    let exp = RECONNECT_BASE_DELAY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_RECONNECT_DELAY);
+    let half = exp.as_millis() as u64 / 2;
+    let jitter = rand::thread_rng().gen_range(0..=half);
+    Duration::from_millis(half + jitter)
+}
+
+/// 按退避策略重新建立连接，直到成功、认证失败或达到最大次数。
+/// 连接池的断线监测与命令行的端口转发共用，每次等待前调用 on_attempt
+pub(crate) async fn reconnect(
+    params: &ConnectParams,
+    mut on_attempt: impl FnMut(u32),
+) -> AppResult<(Handle<Client>, Option<NegotiatedAlgorithms>)> {
+    let mut last_error = None;
+    for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+        on_attempt(attempt);
+        tokio::time::sleep(reconnect_delay(attempt)).await;
+        match Session::establish(params).await {
+            Ok(established) => return Ok(established),
+            Err(e) => {
+                eprintln!(
+                    "{}:{} 第 {} 次重连失败: {}",
+                    params.host, params.port, attempt, e
+                );
+                // 认证失败重试也无济于事
+                if e.kind == ErrorKind::Auth {
+                    return Err(e);
+                }
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| AppError::network("自动重连失败")))
+}
+
+/// 定期检查连接是否断开，断开后停止监控与端口转发并按退避策略重连，
+/// 重连成功后恢复服务器信息监控、端口转发、终端和输出流
+async fn watch_connection(id: usize, sink: Sink) {
+    // 已提醒过的证书，按过期时间区分，续签后的新证书会再次提醒
+    let mut warned_certificate = None;
+    loop {
+        tokio::time::sleep(WATCH_INTERVAL).await;
+        let (params, forward_tasks) = {
+            let mut pool = CONNECTION_POOL.lock().await;
+            let Some((_, session)) = pool.iter_mut().find(|(conn_id, _)| *conn_id == id) else {
+                return;
+            };
+            if !session.is_closed() {
+                // 连接正常时中断的终端和输出流不是断线造成的
+                shell::discard_interrupted(id).await;
+                streams::discard_interrupted(id).await;
+                if let Some(certificate) = session.certificate.as_ref().filter(|c| c.expires_soon())
+                {
+                    if warned_certificate != Some(certificate.valid_before) {
+                        warned_certificate = Some(certificate.valid_before);
+                        sink.emit(
+                            &format!("certificate-expiring-{id}"),
+                            CertificateExpiring {
+                                id,
+                                certificate: certificate.clone(),
+                            },
+                        );
+                    }
+                }
+                continue;
+            }
+            if let Some(info_task) = session.info_task.take() {
+                info_task.abort();
+            }
+            (
+                session.params.clone(),
+                std::mem::take(&mut session.forward_tasks),
+            )
+        };
+        println!("连接 {} 已断开，开始重连", id);
+        forward::stop_forwards(forward_tasks).await;
+
+        let reconnected = reconnect(&params, |attempt| {
+            emit_state(
+                sink.as_ref(),
+                id,
+                ConnectionState::Reconnecting,
+                attempt,
+                None,
+            );
+        })
+        .await;
+        let (handle, negotiated) = match reconnected {
+            Ok(established) => established,
+            Err(_) => {
+                shell::discard_interrupted(id).await;
+                streams::discard_interrupted(id).await;
+                emit_state(
+                    sink.as_ref(),
+                    id,
+                    ConnectionState::Failed,
+                    0,
+                    Some("自动重连失败".to_string()),
+                );
+                return;
+            }
+        };
+
+        let mut pool = CONNECTION_POOL.lock().await;
+        let Some((_, session)) = pool.iter_mut().find(|(conn_id, _)| *conn_id == id) else {
+            // 重连期间连接已被用户关闭
+            return;
+        };
+        session.session = Arc::new(handle);
+        session.negotiated = negotiated;
+        session.certificate = params.certificate_info().ok().flatten();
+        session.info_task = Some(tokio::spawn(start_info_query(id, sink.clone())));
+        session.forward_tasks = forward::start_forwards(&session.session, &session.forwards).await;
+        shell::restore_shell(id, &session.session).await;
+        streams::restore_streams(id, &session.session).await;
+        drop(pool);
+        emit_state(sink.as_ref(), id, ConnectionState::Connected, 0, None);
+    }
+}
+
+async fn query_and_emit_uptime(id: usize, sink: &dyn EventSink) -> AppResult<()> {
+    let pool = CONNECTION_POOL.lock().await;
+    if let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) {
+        let uptime = metrics::uptime(session).await?;
+        sink.emit(
+            &format!("server-uptime-update-{id}"),
+            UptimeUpdate { uptime },
+        );
+    }
+    Ok(())
+}
+
+async fn query_and_emit_memory(id: usize, sink: &dyn EventSink) -> AppResult<()> {
+    let pool = CONNECTION_POOL.lock().await;
+    if let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) {
+        let memory_usage = metrics::memory(session, session.profile.strategy()).await?;
+        sink.emit(
+            &format!("server-memory-update-{id}"),
+            MemoryUpdate { memory_usage },
+        );
+    }
+    Ok(())
+}
+
+async fn query_and_emit_cpu(id: usize, sink: &dyn EventSink) -> AppResult<()> {
+    let pool = CONNECTION_POOL.lock().await;
+    if let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) {
+        let cpu_usage = metrics::cpu(session, session.profile.strategy()).await?;
+        sink.emit(&format!("server-cpu-update-{id}"), CpuUpdate { cpu_usage });
+    }
+    Ok(())
+}
+
+async fn query_and_emit_network(id: usize, sink: &dyn EventSink) -> AppResult<()> {
+    let pool = CONNECTION_POOL.lock().await;
+    if let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) {
+        let strategy = session.profile.strategy();
+        let (rx_before, tx_before) = metrics::network_bytes(session, strategy).await?;
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        let (rx_after, tx_after) = metrics::network_bytes(session, strategy).await?;
+
+        let rx_speed = (rx_after.saturating_sub(rx_before) as f64) / 1024.0; // 转换为kb/s
+        let tx_speed = (tx_after.saturating_sub(tx_before) as f64) / 1024.0; // 转换为kb/s
+
+        let network_usage = NetworkUsage { rx_speed, tx_speed };
+        sink.emit(
+            &format!("server-network-update-{id}"),
+            NetworkUpdate { network_usage },
+        );
+    }
+    Ok(())
+}
+
+async fn query_and_emit_disk(id: usize, sink: &dyn EventSink) -> AppResult<()> {
+    let pool = CONNECTION_POOL.lock().await;
+    if let Some((_, session)) = pool.iter().find(|(conn_id, _)| *conn_id == id) {
+        let disk = metrics::disk(session, session.profile.strategy()).await?;
+        sink.emit(
+            &format!("server-disk-update-{id}"),
+            DiskUpdate {
+                disk_usage: disk.usage,
+            },
+        );
+    }
+    Ok(())
+}
+
+async fn retry_info_query(id: usize, sink: Sink) -> AppResult<serde_json::Value> {
+    let mut pool = CONNECTION_POOL.lock().await;
+    if let Some((_, session)) = pool.iter_mut().find(|(conn_id, _)| *conn_id == id) {
+        if let Some(old_task) = session.info_task.take() {
+            old_task.abort();
+        }
+        let new_task = tokio::spawn(start_info_query(id, sink));
+        session.info_task = Some(new_task);
+        Ok(json!(ApiResponse::success("重新开始查询服务器信息")))
+    } else {
+        Err(AppError::connection_not_found(id))
+    }
+}
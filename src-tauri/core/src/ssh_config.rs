@@ -0,0 +1,466 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sled::Db;
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::settings::TimeoutOverrides;
+use crate::sled::{current_timestamp, get_all_connections, LocalForward, SshConnection};
+
+/// 托管配置文件名，位于 ~/.ssh 下并通过 Include 引入主配置
+const MANAGED_FILE_NAME: &str = "ssh-rust.config";
+
+const MANAGED_HEADER: &str = "# 由 ssh-rust 自动生成，手动修改会在下次导出时被覆盖\n";
+
+/// 导出目标：独立文件，或 ~/.ssh 下由主配置 Include 的托管文件
+#[derive(Serialize, Deserialize, Debug, TS)]
+pub enum SshConfigTarget {
+    File(String),
+    ManagedInclude,
+}
+
+#[derive(Serialize, Debug, TS)]
+pub struct SshConfigExportReport {
+    pub path: String,
+    pub hosts: Vec<String>,
+    // 是否向 ~/.ssh/config 新增了 Include 指令
+    pub include_added: bool,
+}
+
+#[derive(Serialize, Debug, TS)]
+pub struct SshConfigImportReport {
+    pub path: String,
+    pub imported: Vec<String>,
+    // 与已有连接同名而跳过的 Host
+    pub skipped: Vec<String>,
+}
+
+/// 将选中的连接渲染为 OpenSSH 的 Host 配置块并写入目标文件。ids 为 None 或空列表时导出全部连接
+pub fn export_ssh_config(
+    db: &Db,
+    ids: Option<Vec<u64>>,
+    target: SshConfigTarget,
+) -> AppResult<SshConfigExportReport> {
+    let ids = ids.filter(|ids| !ids.is_empty());
+    let connections: Vec<SshConnection> = get_all_connections(db)?
+        .into_iter()
+        .filter(|c| match (&ids, c.id) {
+            (Some(ids), Some(id)) => ids.contains(&id),
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .collect();
+
+    let mut hosts = Vec::new();
+    let mut content = String::from(MANAGED_HEADER);
+    for connection in &connections {
+        let alias = unique_alias(&host_alias(&connection.name), &hosts);
+        content.push('\n');
+        content.push_str(&render_host_block(&alias, connection));
+        hosts.push(alias);
+    }
+
+    let (path, include_added) = match target {
+        SshConfigTarget::File(path) => (PathBuf::from(path), false),
+        SshConfigTarget::ManagedInclude => {
+            let ssh_dir = dirs::home_dir()
+                .ok_or_else(|| AppError::internal("无法获取用户主目录"))?
+                .join(".ssh");
+            let include_added = ensure_include(&ssh_dir.join("config"))?;
+            (ssh_dir.join(MANAGED_FILE_NAME), include_added)
+        }
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+
+    Ok(SshConfigExportReport {
+        path: path.to_string_lossy().to_string(),
+        hosts,
+        include_added,
+    })
+}
+
+fn render_host_block(alias: &str, connection: &SshConnection) -> String {
+    let mut block = format!("Host {}\n", alias);
+    block.push_str(&format!("    HostName {}\n", connection.host));
+    block.push_str(&format!("    Port {}\n", connection.port));
+    if let Some(username) = connection.username.as_deref().filter(|u| !u.is_empty()) {
+        block.push_str(&format!("    User {}\n", quote_value(username)));
+    }
+    if let Some(identity_file) = connection
+        .identity_file
+        .as_deref()
+        .filter(|f| !f.is_empty())
+    {
+        block.push_str(&format!(
+            "    IdentityFile {}\n",
+            quote_value(identity_file)
+        ));
+    }
+    if let Some(certificate_file) = connection
+        .certificate_file
+        .as_deref()
+        .filter(|f| !f.is_empty())
+    {
+        block.push_str(&format!(
+            "    CertificateFile {}\n",
+            quote_value(certificate_file)
+        ));
+    }
+    if let Some(proxy_jump) = connection.proxy_jump.as_deref().filter(|p| !p.is_empty()) {
+        block.push_str(&format!("    ProxyJump {}\n", proxy_jump));
+    }
+    let algorithms = &connection.algorithms;
+    for (keyword, names) in [
+        ("KexAlgorithms", &algorithms.kex),
+        ("HostKeyAlgorithms", &algorithms.host_key),
+        ("Ciphers", &algorithms.cipher),
+        ("MACs", &algorithms.mac),
+    ] {
+        if !names.is_empty() {
+            block.push_str(&format!("    {} {}\n", keyword, names.join(",")));
+        }
+    }
+    let timeouts = &connection.timeouts;
+    if let Some(connect_timeout) = timeouts.connect_timeout.filter(|t| *t > 0) {
+        block.push_str(&format!("    ConnectTimeout {}\n", connect_timeout));
+    }
+    if let Some(keepalive_interval) = timeouts.keepalive_interval {
+        block.push_str(&format!("    ServerAliveInterval {}\n", keepalive_interval));
+    }
+    if let Some(keepalive_max) = timeouts.keepalive_max {
+        block.push_str(&format!("    ServerAliveCountMax {}\n", keepalive_max));
+    }
+    for forward in &connection.local_forwards {
+        let bind = match forward.bind_address.as_deref() {
+            Some(address) if !address.is_empty() => format!("{}:{}", address, forward.bind_port),
+            _ => forward.bind_port.to_string(),
+        };
+        block.push_str(&format!(
+            "    LocalForward {} {}:{}\n",
+            bind, forward.remote_host, forward.remote_port
+        ));
+    }
+    block
+}
+
+/// 从 OpenSSH 配置导入连接，path 为空时读取 ~/.ssh/config。
+/// 只导入不含通配符的 Host，不处理 Include、Match 以及 Host * 中的全局默认值
+pub fn import_ssh_config(db: &Db, path: Option<&str>) -> AppResult<SshConfigImportReport> {
+    let path = match path {
+        Some(path) => crate::utils::expand_home(path)?,
+        None => dirs::home_dir()
+            .ok_or_else(|| AppError::internal("无法获取用户主目录"))?
+            .join(".ssh")
+            .join("config"),
+    };
+    let content = fs::read_to_string(&path)?;
+
+    let mut existing: Vec<String> = get_all_connections(db)?
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    let mut report = SshConfigImportReport {
+        path: path.to_string_lossy().to_string(),
+        imported: Vec::new(),
+        skipped: Vec::new(),
+    };
+    let now = current_timestamp();
+    for mut connection in parse_host_blocks(&content) {
+        if existing.contains(&connection.name) {
+            report.skipped.push(connection.name);
+            continue;
+        }
+        let id = db.generate_id()?;
+        connection.id = Some(id);
+        connection.created_at = Some(now);
+        connection.updated_at = Some(now);
+        db.insert(id.to_string(), serde_json::to_vec(&connection)?)?;
+        existing.push(connection.name.clone());
+        report.imported.push(connection.name);
+    }
+    db.flush()?;
+    Ok(report)
+}
+
+// 每个不含通配符的 Host 别名生成一个连接，同一块中的多个别名共享配置
+fn parse_host_blocks(content: &str) -> Vec<SshConnection> {
+    let mut connections = Vec::new();
+    let mut block: Vec<SshConnection> = Vec::new();
+    let mut in_match = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (keyword, value) = match line.split_once(|c: char| c.is_whitespace() || c == '=') {
+            Some((keyword, value)) => (keyword, value.trim().trim_start_matches('=').trim()),
+            None => (line, ""),
+        };
+        let keyword = keyword.to_lowercase();
+        if keyword == "host" || keyword == "match" {
+            connections.append(&mut block);
+            in_match = keyword == "match";
+            if !in_match {
+                block = value
+                    .split_whitespace()
+                    .map(|alias| alias.trim_matches('"'))
+                    .filter(|alias| !alias.contains(['*', '?', '!']))
+                    .map(new_connection)
+                    .collect();
+            }
+            continue;
+        }
+        if in_match {
+            continue;
+        }
+        let value = value.trim_matches('"');
+        for connection in block.iter_mut() {
+            apply_option(connection, &keyword, value);
+        }
+    }
+    connections.append(&mut block);
+    connections
+}
+
+fn new_connection(alias: &str) -> SshConnection {
+    SshConnection {
+        id: None,
+        name: alias.to_string(),
+        host: alias.to_string(),
+        port: 22,
+        username: None,
+        password: None,
+        identity_file: None,
+        certificate_file: None,
+        proxy_jump: None,
+        local_forwards: Vec::new(),
+        tags: Vec::new(),
+        notes: None,
+        timeouts: TimeoutOverrides::default(),
+        algorithms: Default::default(),
+        pinned_host_key: None,
+        created_at: None,
+        updated_at: None,
+        last_connected_at: None,
+        connect_count: 0,
+    }
+}
+
+// 与 ssh 一致，同一选项只有第一次出现的值生效
+fn apply_option(connection: &mut SshConnection, keyword: &str, value: &str) {
+    let value = value.to_string();
+    match keyword {
+        "hostname" if connection.host == connection.name => connection.host = value,
+        "port" => {
+            if let Ok(port) = value.parse() {
+                if connection.port == 22 {
+                    connection.port = port;
+                }
+            }
+        }
+        "user" => {
+            connection.username.get_or_insert(value);
+        }
+        "identityfile" => {
+            connection.identity_file.get_or_insert(value);
+        }
+        "certificatefile" => {
+            connection.certificate_file.get_or_insert(value);
+        }
+        "proxyjump" => {
+            connection.proxy_jump.get_or_insert(value);
+        }
+        "connecttimeout" => {
+            let timeout = value.parse().ok();
+            connection.timeouts.connect_timeout = connection.timeouts.connect_timeout.or(timeout);
+        }
+        "serveraliveinterval" => {
+            let interval = value.parse().ok();
+            let timeouts = &mut connection.timeouts;
+            timeouts.keepalive_interval = timeouts.keepalive_interval.or(interval);
+        }
+        "serveralivecountmax" => {
+            let max = value.parse().ok();
+            connection.timeouts.keepalive_max = connection.timeouts.keepalive_max.or(max);
+        }
+        "localforward" => {
+            if let Some(forward) = parse_local_forward(&value) {
+                connection.local_forwards.push(forward);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 解析 "[bind_address:]port host:hostport"，同时接受 -L 形式的 "[bind_address:]port:host:hostport"
+pub fn parse_local_forward(spec: &str) -> Option<LocalForward> {
+    let parts: Vec<&str> = spec
+        .split(|c: char| c == ':' || c.is_whitespace())
+        .filter(|p| !p.is_empty())
+        .collect();
+    let (bind_address, rest) = match parts.len() {
+        3 => (None, &parts[..]),
+        4 => (Some(parts[0].to_string()), &parts[1..]),
+        _ => return None,
+    };
+    Some(LocalForward {
+        bind_address,
+        bind_port: rest[0].parse().ok()?,
+        remote_host: rest[1].to_string(),
+        remote_port: rest[2].parse().ok()?,
+    })
+}
+
+// Host 别名不能包含空白和通配符，这些字符统一替换为 '-'
+fn host_alias(name: &str) -> String {
+    let alias: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_whitespace() || matches!(c, '*' | '?' | '!' | '#' | '"' | ',') {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect();
+    if alias.is_empty() {
+        "host".to_string()
+    } else {
+        alias
+    }
+}
+
+fn unique_alias(alias: &str, used: &[String]) -> String {
+    if !used.iter().any(|u| u == alias) {
+        return alias.to_string();
+    }
+    let mut index = 2;
+    loop {
+        let candidate = format!("{}-{}", alias, index);
+        if !used.contains(&candidate) {
+            return candidate;
+        }
+        index += 1;
+    }
+}
+
+fn quote_value(value: &str) -> String {
+    if value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+// Include 必须出现在所有 Host 块之前才能对全局生效，因此插入到文件开头
+fn ensure_include(config_path: &Path) -> AppResult<bool> {
+    let existing = match fs::read_to_string(config_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let already_included = existing.lines().any(|line| {
+        let mut parts = line.split_whitespace();
+        matches!(parts.next(), Some(keyword) if keyword.eq_ignore_ascii_case("include"))
+            && parts.any(|p| p.trim_matches('"').ends_with(MANAGED_FILE_NAME))
+    });
+    if already_included {
+        return Ok(false);
+    }
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = format!("Include {}\n\n{}", MANAGED_FILE_NAME, existing);
+    fs::write(config_path, content)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_host_block_that_parses_back() {
+        let mut connection = new_connection("web");
+        connection.host = "10.0.0.5".to_string();
+        connection.port = 2222;
+        connection.username = Some("deploy user".to_string());
+        connection.identity_file = Some("~/.ssh/id_ed25519".to_string());
+        connection.proxy_jump = Some("bastion".to_string());
+        connection.algorithms.cipher = vec!["aes256-ctr".to_string(), "aes128-ctr".to_string()];
+        connection.timeouts.connect_timeout = Some(10);
+        connection.timeouts.keepalive_interval = Some(30);
+        connection.local_forwards.push(LocalForward {
+            bind_address: None,
+            bind_port: 8080,
+            remote_host: "localhost".to_string(),
+            remote_port: 80,
+        });
+
+        let block = render_host_block("web", &connection);
+        assert_eq!(
+            block,
+            "Host web\n    HostName 10.0.0.5\n    Port 2222\n    User \"deploy user\"\n    \
+             IdentityFile ~/.ssh/id_ed25519\n    ProxyJump bastion\n    \
+             Ciphers aes256-ctr,aes128-ctr\n    ConnectTimeout 10\n    \
+             ServerAliveInterval 30\n    LocalForward 8080 localhost:80\n"
+        );
+
+        let parsed = parse_host_blocks(&block);
+        assert_eq!(parsed.len(), 1);
+        let parsed = &parsed[0];
+        assert_eq!(parsed.name, "web");
+        assert_eq!(parsed.host, "10.0.0.5");
+        assert_eq!(parsed.port, 2222);
+        assert_eq!(parsed.username.as_deref(), Some("deploy user"));
+        assert_eq!(parsed.identity_file.as_deref(), Some("~/.ssh/id_ed25519"));
+        assert_eq!(parsed.proxy_jump.as_deref(), Some("bastion"));
+        assert_eq!(parsed.timeouts.connect_timeout, Some(10));
+        assert_eq!(parsed.timeouts.keepalive_interval, Some(30));
+        assert_eq!(parsed.local_forwards.len(), 1);
+        assert_eq!(parsed.local_forwards[0].bind_port, 8080);
+        assert_eq!(parsed.local_forwards[0].remote_port, 80);
+    }
+
+    #[test]
+    fn parses_host_blocks_like_ssh() {
+        let content = "\
+# 注释
+Host *
+    User everyone
+
+Host db db-alias *.internal
+    HostName=10.0.0.9
+    User admin
+    User ignored
+    Port 2200
+
+Match host db
+    User matched
+
+Host jump
+    ServerAliveCountMax 5
+";
+        let parsed = parse_host_blocks(content);
+        let names: Vec<&str> = parsed.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["db", "db-alias", "jump"]);
+        for connection in &parsed[..2] {
+            assert_eq!(connection.host, "10.0.0.9");
+            assert_eq!(connection.port, 2200);
+            // 同一选项只有第一次出现的值生效，Match 块被忽略
+            assert_eq!(connection.username.as_deref(), Some("admin"));
+        }
+        let jump = &parsed[2];
+        assert_eq!(jump.host, "jump");
+        assert_eq!(jump.port, 22);
+        assert_eq!(jump.username, None);
+        assert_eq!(jump.timeouts.keepalive_max, Some(5));
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use russh::client::{Handle, Msg};
+use russh::{Channel, ChannelMsg, Sig};
+use serde::Serialize;
+use tokio::sync::{oneshot, Mutex};
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::events::{EventSink, Sink, StreamClosed, StreamOutput};
+use crate::session::Client;
+use crate::shell::take_utf8;
+
+/// 调用 StreamHandler::tick 的间隔
+const TICK_INTERVAL: Duration = Duration::from_millis(200);
+
+struct StreamHandle {
+    // 所属连接 ID
+    id: usize,
+    stop: oneshot::Sender<()>,
+}
+
+// 因连接断开而中断的输出流，重连成功后在新连接上重新执行命令
+struct InterruptedStream {
+    id: usize,
+    command: String,
+    handler: Box<dyn StreamHandler>,
+}
+
+// 正在运行的输出流，键为流 ID
+static STREAMS: Lazy<Mutex<HashMap<u64, StreamHandle>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+// 中断的输出流，键为流 ID
+static INTERRUPTED: Lazy<Mutex<HashMap<u64, InterruptedStream>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Serialize, Debug, TS)]
+pub struct StreamOpened {
+    #[ts(type = "number")]
+    pub stream_id: u64,
+    pub id: usize,
+    pub command: String,
+}
+
+/// 输出流的处理方式，在流的任务中依次调用
+pub trait StreamHandler: Send + 'static {
+    /// 收到一段已解码的输出，可能不是完整的行
+    fn output(&mut self, text: &str);
+    /// 定期调用，用于批量发送
+    fn tick(&mut self) {}
+    /// 命令结束或被停止
+    fn closed(&mut self, exit_status: Option<u32>);
+}
+
+// 原样发送输出
+struct EventOutput {
+    id: usize,
+    stream_id: u64,
+    sink: Sink,
+}
+
+impl StreamHandler for EventOutput {
+    fn output(&mut self, text: &str) {
+        self.sink.emit(
+            &format!("stream-output-{}", self.stream_id),
+            StreamOutput {
+                stream_id: self.stream_id,
+                id: self.id,
+                data: text.to_string(),
+            },
+        );
+    }
+
+    fn closed(&mut self, exit_status: Option<u32>) {
+        emit_closed(self.sink.as_ref(), self.stream_id, self.id, exit_status);
+    }
+}
+
+/// 在连接上执行持续输出的命令（如 docker logs -f），
+/// 输出通过 stream-output-{stream_id} 事件发送，结束时发送 stream-closed-{stream_id}
+pub async fn start_stream(
+    id: usize,
+    session: &Handle<Client>,
+    command: &str,
+    sink: Sink,
+) -> AppResult<StreamOpened> {
+    spawn_stream(id, session, command, |stream_id| EventOutput {
+        id,
+        stream_id,
+        sink,
+    })
+    .await
+}
+
+/// 执行命令并把输出交给 make_handler 创建的处理方式，参数为分配的流 ID
+pub async fn spawn_stream<H: StreamHandler>(
+    id: usize,
+    session: &Handle<Client>,
+    command: &str,
+    make_handler: impl FnOnce(u64) -> H,
+) -> AppResult<StreamOpened> {
+    let channel = exec(session, command).await?;
+    let stream_id = NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed);
+    let handler = Box::new(make_handler(stream_id));
+    register(stream_id, id, command.to_string(), channel, handler).await;
+    Ok(StreamOpened {
+        stream_id,
+        id,
+        command: command.to_string(),
+    })
+}
+
+/// 重连成功后在新连接上重新执行中断的输出流，流 ID 与处理方式不变
+pub async fn restore_streams(id: usize, session: &Handle<Client>) {
+    for (stream_id, stream) in take_interrupted(id).await {
+        match exec(session, &stream.command).await {
+            Ok(channel) => {
+                register(stream_id, id, stream.command, channel, stream.handler).await;
+            }
+            Err(e) => {
+                eprintln!("连接 {} 恢复输出流 {} 失败: {}", id, stream_id, e);
+                let mut handler = stream.handler;
+                handler.closed(None);
+            }
+        }
+    }
+}
+
+/// 结束连接的全部中断输出流，用于重连失败或输出流不是因断线而中断的情况
+pub async fn discard_interrupted(id: usize) {
+    for (_, mut stream) in take_interrupted(id).await {
+        stream.handler.closed(None);
+    }
+}
+
+async fn take_interrupted(id: usize) -> Vec<(u64, InterruptedStream)> {
+    let mut interrupted = INTERRUPTED.lock().await;
+    let stream_ids: Vec<u64> = interrupted
+        .iter()
+        .filter(|(_, stream)| stream.id == id)
+        .map(|(stream_id, _)| *stream_id)
+        .collect();
+    stream_ids
+        .into_iter()
+        .filter_map(|stream_id| Some((stream_id, interrupted.remove(&stream_id)?)))
+        .collect()
+}
+
+async fn exec(session: &Handle<Client>, command: &str) -> AppResult<Channel<Msg>> {
+    let channel = session.channel_open_session().await?;
+    channel.exec(true, command).await?;
+    Ok(channel)
+}
+
+async fn register(
+    stream_id: u64,
+    id: usize,
+    command: String,
+    channel: Channel<Msg>,
+    handler: Box<dyn StreamHandler>,
+) {
+    let (stop, stopped) = oneshot::channel();
+    STREAMS
+        .lock()
+        .await
+        .insert(stream_id, StreamHandle { id, stop });
+    tokio::spawn(run_stream(
+        stream_id, id, command, channel, stopped, handler,
+    ));
+}
+
+/// 停止输出流并结束远端命令
+pub async fn stop_stream(stream_id: u64) -> AppResult<()> {
+    if let Some(stream) = STREAMS.lock().await.remove(&stream_id) {
+        let _ = stream.stop.send(());
+        return Ok(());
+    }
+    let mut stream = INTERRUPTED
+        .lock()
+        .await
+        .remove(&stream_id)
+        .ok_or_else(|| AppError::not_found(format!("输出流 {} 不存在", stream_id)))?;
+    stream.handler.closed(None);
+    Ok(())
+}
+
+/// 关闭连接时停止该连接的全部输出流
+pub async fn stop_streams(id: usize) {
+    let mut streams = STREAMS.lock().await;
+    let stream_ids: Vec<u64> = streams
+        .iter()
+        .filter(|(_, stream)| stream.id == id)
+        .map(|(stream_id, _)| *stream_id)
+        .collect();
+    for stream_id in stream_ids {
+        if let Some(stream) = streams.remove(&stream_id) {
+            let _ = stream.stop.send(());
+        }
+    }
+    drop(streams);
+    discard_interrupted(id).await;
+}
+
+/// stream-closed-{stream_id} 事件
+pub(crate) fn emit_closed(
+    sink: &dyn EventSink,
+    stream_id: u64,
+    id: usize,
+    exit_status: Option<u32>,
+) {
+    sink.emit(
+        &format!("stream-closed-{stream_id}"),
+        StreamClosed {
+            stream_id,
+            id,
+            exit_status,
+        },
+    );
+}
+
+async fn run_stream(
+    stream_id: u64,
+    id: usize,
+    command: String,
+    mut channel: Channel<Msg>,
+    mut stopped: oneshot::Receiver<()>,
+    mut handler: Box<dyn StreamHandler>,
+) {
+    let mut pending = Vec::new();
+    let mut exit_status = None;
+    // 命令正常结束或被停止，其余情况视为连接中断
+    let mut finished = false;
+    let mut ticker = tokio::time::interval(TICK_INTERVAL);
+    loop {
+        tokio::select! {
+            msg = channel.wait() => match msg {
+                Some(ChannelMsg::Data { data }) | Some(ChannelMsg::ExtendedData { data, .. }) => {
+                    pending.extend_from_slice(&data);
+                    let text = take_utf8(&mut pending);
+                    if !text.is_empty() {
+                        handler.output(&text);
+                    }
+                }
+                Some(ChannelMsg::ExitStatus { exit_status: status }) => {
+                    exit_status = Some(status);
+                    finished = true;
+                }
+                Some(ChannelMsg::ExitSignal { .. }) => finished = true,
+                Some(_) => {}
+                None => break,
+            },
+            _ = ticker.tick() => handler.tick(),
+            _ = &mut stopped => {
+                finished = true;
+                // 没有 PTY 时远端进程收不到挂断信号，先发送 TERM 再关闭通道
+                let _ = channel.signal(Sig::TERM).await;
+                let _ = channel.eof().await;
+                let _ = channel.close().await;
+                break;
+            }
+        }
+    }
+
+    let current = STREAMS.lock().await.remove(&stream_id).is_some();
+    // 连接断开导致的中断由断线监测在重连后恢复或结束
+    if current && !finished {
+        INTERRUPTED.lock().await.insert(
+            stream_id,
+            InterruptedStream {
+                id,
+                command,
+                handler,
+            },
+        );
+        return;
+    }
+    handler.closed(exit_status);
+}
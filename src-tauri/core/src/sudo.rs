@@ -0,0 +1,168 @@
+use russh::client::Msg;
+use russh::{Channel, ChannelMsg};
+
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::session::{Credentials, Session};
+use crate::utils::shell_quote;
+
+/// 传给 sudo -p 的提示，出现在 stderr 中时写入密码。不能包含 %，否则会被 sudo 展开
+const PROMPT: &str = "[ssh-rust] sudo password:";
+
+/// 提权时使用的密码：会话内输入的密码优先，其次是密码认证时保存的凭据
+fn password(session: &Session) -> Option<String> {
+    session
+        .sudo_password
+        .clone()
+        .or_else(|| match &session.params.credentials {
+            Credentials::Password(password) => Some(password.clone()),
+            _ => None,
+        })
+}
+
+/// 用 sudo 包装命令。免密或凭据仍在缓存期内时用 -n 执行，标准输入接 /dev/null，
+/// 不会出现提示；否则 -S 从标准输入读取密码，-p 指定便于识别的提示
+pub fn wrap(command: &str) -> String {
+    let command = shell_quote(command);
+    format!(
+        "sudo -n true 2>/dev/null && exec sudo -n sh -c {command} </dev/null; \
+         exec sudo -S -p {} sh -c {command}",
+        shell_quote(PROMPT)
+    )
+}
+
+/// 已开始执行的 sudo 命令。通道不借用会话，可以在释放连接池锁之后再等待结果
+pub struct SudoExec {
+    channel: Channel<Msg>,
+    password: Option<String>,
+}
+
+/// 打开通道开始执行 sudo 命令，密码在 wait 中按提示写入
+pub async fn start(session: &Session, command: &str) -> AppResult<SudoExec> {
+    let channel = session.session.channel_open_session().await?;
+    channel.exec(true, wrap(command)).await?;
+    Ok(SudoExec {
+        channel,
+        password: password(session),
+    })
+}
+
+impl SudoExec {
+    /// 等待命令结束，返回标准输出与退出状态，非零退出状态不视为错误。
+    /// 没有可用的密码时返回 SudoRequired，密码错误时返回 Auth
+    pub async fn wait(self) -> AppResult<(String, Option<u32>)> {
+        run(self.channel, self.password.as_deref()).await
+    }
+}
+
+/// 通过 sudo 执行命令，返回标准输出与退出状态，非零退出状态不视为错误。
+/// 没有可用的密码时返回 SudoRequired，密码错误时清除会话内保存的密码
+pub async fn call(session: &mut Session, command: &str) -> AppResult<(String, Option<u32>)> {
+    let result = start(session, command).await?.wait().await;
+    if matches!(&result, Err(e) if e.kind == ErrorKind::Auth) {
+        session.sudo_password = None;
+    }
+    result
+}
+
+/// 校验密码后保存到会话中，之后的 sudo 命令自动使用
+pub async fn set_password(session: &mut Session, password: String) -> AppResult<()> {
+    // -k 忽略已缓存的凭据，确保密码确实经过校验
+    let command = format!("sudo -k -S -p {} -v", shell_quote(PROMPT));
+    let channel = session.session.channel_open_session().await?;
+    channel.exec(true, command).await?;
+    run(channel, Some(&password)).await?;
+    session.sudo_password = Some(password);
+    Ok(())
+}
+
+// 在 stderr 中等待提示并写入密码，密码之后关闭标准输入，再次出现提示说明密码错误。
+// 没有密码时第一次提示就关闭标准输入，sudo 随即失败退出
+async fn run(
+    mut channel: Channel<Msg>,
+    password: Option<&str>,
+) -> AppResult<(String, Option<u32>)> {
+    let mut output = String::new();
+    let mut errors = String::new();
+    let mut prompts = 0;
+    let mut exit_status = None;
+    while let Some(msg) = channel.wait().await {
+        match msg {
+            ChannelMsg::Data { ref data } => output.push_str(&String::from_utf8_lossy(data)),
+            ChannelMsg::ExtendedData { ref data, .. } => {
+                errors.push_str(&String::from_utf8_lossy(data));
+                let seen = errors.matches(PROMPT).count();
+                if seen > prompts {
+                    if prompts == 0 {
+                        if let Some(password) = password {
+                            channel.data(format!("{}\n", password).as_bytes()).await?;
+                        }
+                        channel.eof().await?;
+                    }
+                    prompts = seen;
+                }
+            }
+            ChannelMsg::ExitStatus {
+                exit_status: status,
+            } => exit_status = Some(status),
+            _ => {}
+        }
+    }
+
+    if prompts > 0 && password.is_none() {
+        return Err(AppError::sudo_required("需要 sudo 密码"));
+    }
+    if prompts > 1 {
+        return Err(AppError::auth("sudo 密码错误"));
+    }
+    if exit_status.is_some_and(|s| s != 0) {
+        // sudo 自身的错误，例如不在 sudoers 中
+        if let Some(message) = sudo_error(&errors) {
+            return Err(AppError::command("sudo 执行失败").with_details(message));
+        }
+    }
+    Ok((output, exit_status))
+}
+
+// 去掉提示后取 sudo 自身输出的第一条错误，命令本身的错误输出不算
+fn sudo_error(errors: &str) -> Option<String> {
+    errors
+        .replace(PROMPT, "\n")
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("sudo:") || line.contains("sudoers"))
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_command() {
+        assert_eq!(
+            wrap("cat /etc/shadow | head -n 1"),
+            "sudo -n true 2>/dev/null && exec sudo -n sh -c 'cat /etc/shadow | head -n 1' </dev/null; \
+             exec sudo -S -p '[ssh-rust] sudo password:' sh -c 'cat /etc/shadow | head -n 1'"
+        );
+    }
+
+    #[test]
+    fn finds_sudo_error() {
+        let errors = format!(
+            "{}alice is not in the sudoers file.  This incident will be reported.\n",
+            PROMPT
+        );
+        assert_eq!(
+            sudo_error(&errors).as_deref(),
+            Some("alice is not in the sudoers file.  This incident will be reported.")
+        );
+        assert_eq!(
+            sudo_error("sudo: unknown user nobody2\n").as_deref(),
+            Some("sudo: unknown user nobody2")
+        );
+        assert_eq!(
+            sudo_error("grep: /root/x: No such file or directory\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use ts_rs::TS;
+
+use crate::error::{AppError, AppResult};
+use crate::platform::InitSystem;
+use crate::session::Session;
+use crate::utils::shell_quote;
+
+/// 状态中默认附带的日志行数
+const DEFAULT_JOURNAL_LINES: usize = 50;
+
+/// systemctl show 读取的属性
+const STATUS_PROPERTIES: &str = "Id,Description,LoadState,ActiveState,SubState,UnitFileState,\
+MainPID,ActiveEnterTimestamp,MemoryCurrent,FragmentPath";
+
+/// systemctl list-units 的一行
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+pub struct SystemdUnit {
+    pub unit: String,
+    pub load: String,
+    pub active: String,
+    pub sub: String,
+    pub description: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, TS)]
+pub enum UnitAction {
+    Start,
+    Stop,
+    Restart,
+    Enable,
+    Disable,
+}
+
+impl UnitAction {
+    fn verb(self) -> &'static str {
+        match self {
+            UnitAction::Start => "start",
+            UnitAction::Stop => "stop",
+            UnitAction::Restart => "restart",
+            UnitAction::Enable => "enable",
+            UnitAction::Disable => "disable",
+        }
+    }
+}
+
+/// 单个服务的状态、systemctl status 原文与最近的日志
+#[derive(Serialize, Debug, Clone, Default, PartialEq, TS)]
+pub struct UnitStatus {
+    pub unit: String,
+    pub description: String,
+    pub load_state: String,
+    pub active_state: String,
+    pub sub_state: String,
+    // enabled、disabled、static 等，未安装的服务为 null
+    pub unit_file_state: Option<String>,
+    pub main_pid: Option<u32>,
+    pub active_since: Option<String>,
+    #[ts(type = "number | null")]
+    pub memory_bytes: Option<u64>,
+    pub fragment_path: Option<String>,
+    pub status_text: String,
+    pub journal: Vec<JournalEntry>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, TS)]
+pub struct JournalEntry {
+    // 毫秒时间戳
+    #[ts(type = "number")]
+    pub timestamp: u64,
+    // syslog 级别，0 为 emerg，7 为 debug
+    pub priority: Option<u8>,
+    pub message: String,
+}
+
+/// 列出 service 单元，all 为 true 时包含未运行的服务。
+/// 旧版 systemd 不支持 --output=json，改为解析纯文本
+pub async fn list_units(session: &Session, all: bool) -> AppResult<Vec<SystemdUnit>> {
+    ensure_systemd(session)?;
+    let all = if all { " --all" } else { "" };
+    let base = format!("systemctl list-units --type=service{} --no-pager", all);
+    let (output, _) = session
+        .call_with_status(&format!("{} --output=json 2>/dev/null", base))
+        .await?;
+    if let Some(units) = parse_units_json(&output) {
+        return Ok(units);
+    }
+    let output = session
+        .call(&format!("{} --plain --no-legend", base))
+        .await?;
+    Ok(parse_units_plain(&output))
+}
+
+/// 启动、停止、重启、启用或禁用服务，sudo 为 true 时通过 sudo -n 执行，需要免密 sudo
+pub async fn unit_action(
+    session: &Session,
+    unit: &str,
+    action: UnitAction,
+    sudo: bool,
+) -> AppResult<String> {
+    ensure_systemd(session)?;
+    validate_unit(unit)?;
+    let sudo = if sudo { "sudo -n " } else { "" };
+    let command = format!(
+        "{}systemctl {} {} 2>&1",
+        sudo,
+        action.verb(),
+        shell_quote(unit)
+    );
+    let (output, status) = session.call_with_status(&command).await?;
+    if let Some(status) = status.filter(|s| *s != 0) {
+        return Err(
+            AppError::command(format!("{} {} 失败", action.verb(), unit)).with_details(format!(
+                "exit status: {}\n{}",
+                status,
+                output.trim()
+            )),
+        );
+    }
+    Ok(output)
+}
+
+/// 查询服务状态，journal_lines 为附带的日志行数，默认 50，普通用户无权读取日志时为空
+pub async fn unit_status(
+    session: &Session,
+    unit: &str,
+    journal_lines: Option<usize>,
+) -> AppResult<UnitStatus> {
+    ensure_systemd(session)?;
+    validate_unit(unit)?;
+    let unit = shell_quote(unit);
+    let properties = session
+        .call(&format!(
+            "systemctl show {} --no-pager --property={}",
+            unit, STATUS_PROPERTIES
+        ))
+        .await?;
+    let mut status = parse_show(&properties);
+    if status.load_state == "not-found" {
+        return Err(AppError::not_found(format!("未找到服务 {}", status.unit)));
+    }
+    // 服务未运行时 systemctl status 返回 3，不视为错误
+    let (status_text, _) = session
+        .call_with_status(&format!(
+            "systemctl status {} --no-pager --lines=0 2>&1",
+            unit
+        ))
+        .await?;
+    status.status_text = status_text;
+    let lines = journal_lines.unwrap_or(DEFAULT_JOURNAL_LINES);
+    if lines > 0 {
+        let (journal, _) = session
+            .call_with_status(&format!(
+                "journalctl -u {} -n {} --no-pager --output=json 2>/dev/null",
+                unit, lines
+            ))
+            .await?;
+        status.journal = parse_journal(&journal);
+    }
+    Ok(status)
+}
+
+fn ensure_systemd(session: &Session) -> AppResult<()> {
+    // 探测失败时仍然尝试执行
+    match session.profile.init_system {
+        InitSystem::Systemd | InitSystem::Unknown => Ok(()),
+        other => Err(AppError::invalid_input(format!(
+            "远端未使用 systemd（{:?}）",
+            other
+        ))),
+    }
+}
+
+// 单元名只允许 systemd 规定的字符，且不能被当作选项
+pub(crate) fn validate_unit(unit: &str) -> AppResult<()> {
+    let valid = !unit.is_empty()
+        && !unit.starts_with('-')
+        && unit
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ":-_.@\\".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::invalid_input(format!("无效的单元名: {}", unit)))
+    }
+}
+
+pub fn parse_units_json(output: &str) -> Option<Vec<SystemdUnit>> {
+    serde_json::from_str(output.trim()).ok()
+}
+
+/// 解析 --plain --no-legend 输出：UNIT LOAD ACTIVE SUB DESCRIPTION
+pub fn parse_units_plain(output: &str) -> Vec<SystemdUnit> {
+    output
+        .lines()
+        .filter_map(|line| {
+            // 未加载的单元前可能带有 ● 标记
+            let mut rest = line.trim().trim_start_matches('●');
+            Some(SystemdUnit {
+                unit: next_field(&mut rest)?,
+                load: next_field(&mut rest)?,
+                active: next_field(&mut rest)?,
+                sub: next_field(&mut rest)?,
+                description: rest.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+// 取出下一个以空白分隔的字段，rest 指向剩余部分
+fn next_field(rest: &mut &str) -> Option<String> {
+    let trimmed = rest.trim_start();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    *rest = &trimmed[end..];
+    Some(trimmed[..end].to_string())
+}
+
+/// 解析 systemctl show 的 key=value 输出
+pub fn parse_show(output: &str) -> UnitStatus {
+    let mut status = UnitStatus::default();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        // systemd 用 [not set] 或空值表示缺省
+        let optional = (!value.is_empty() && value != "[not set]").then(|| value.to_string());
+        match key {
+            "Id" => status.unit = value.to_string(),
+            "Description" => status.description = value.to_string(),
+            "LoadState" => status.load_state = value.to_string(),
+            "ActiveState" => status.active_state = value.to_string(),
+            "SubState" => status.sub_state = value.to_string(),
+            "UnitFileState" => status.unit_file_state = optional,
+            "MainPID" => status.main_pid = value.parse().ok().filter(|pid| *pid != 0),
+            "ActiveEnterTimestamp" => status.active_since = optional,
+            "MemoryCurrent" => status.memory_bytes = value.parse().ok(),
+            "FragmentPath" => status.fragment_path = optional,
+            _ => {}
+        }
+    }
+    status
+}
+
+/// 解析 journalctl --output=json，每行一条记录
+pub fn parse_journal(output: &str) -> Vec<JournalEntry> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .map(|entry| JournalEntry {
+            timestamp: entry["__REALTIME_TIMESTAMP"]
+                .as_str()
+                .and_then(|t| t.parse::<u64>().ok())
+                .map(|micros| micros / 1000)
+                .unwrap_or(0),
+            priority: entry["PRIORITY"].as_str().and_then(|p| p.parse().ok()),
+            message: journal_message(&entry["MESSAGE"]),
+        })
+        .collect()
+}
+
+// 非 UTF-8 的消息以字节数组形式输出
+fn journal_message(value: &Value) -> String {
+    match value {
+        Value::String(message) => message.clone(),
+        Value::Array(bytes) => {
+            let bytes: Vec<u8> = bytes
+                .iter()
+                .filter_map(|b| b.as_u64().map(|b| b as u8))
+                .collect();
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_list_units_json() {
+        let output = r#"[{"unit":"cron.service","load":"loaded","active":"active","sub":"running","description":"Regular background program processing daemon"},{"unit":"ssh.service","load":"loaded","active":"inactive","sub":"dead","description":"OpenBSD Secure Shell server"}]"#;
+        let units = parse_units_json(output).unwrap();
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[1].unit, "ssh.service");
+        assert_eq!(units[1].sub, "dead");
+    }
+
+    #[test]
+    fn falls_back_to_plain_output() {
+        // systemd 245 不认识 --output=json，会输出普通表格
+        assert!(parse_units_json("cron.service loaded active running cron\n").is_none());
+        let output = "\
+cron.service                loaded    active   running Regular background program processing daemon
+● nfs-server.service        not-found inactive dead    nfs-server.service
+";
+        let units = parse_units_plain(output);
+        assert_eq!(units.len(), 2);
+        assert_eq!(
+            units[0].description,
+            "Regular background program processing daemon"
+        );
+        assert_eq!(units[1].unit, "nfs-server.service");
+        assert_eq!(units[1].load, "not-found");
+    }
+
+    #[test]
+    fn parses_show_properties() {
+        let output = "\
+Id=nginx.service
+Description=A high performance web server and a reverse proxy server
+LoadState=loaded
+ActiveState=active
+SubState=running
+UnitFileState=enabled
+MainPID=1234
+ActiveEnterTimestamp=Mon 2024-05-06 10:00:00 UTC
+MemoryCurrent=10485760
+FragmentPath=/lib/systemd/system/nginx.service
+";
+        let status = parse_show(output);
+        assert_eq!(status.unit, "nginx.service");
+        assert_eq!(status.active_state, "active");
+        assert_eq!(status.unit_file_state.as_deref(), Some("enabled"));
+        assert_eq!(status.main_pid, Some(1234));
+        assert_eq!(status.memory_bytes, Some(10485760));
+    }
+
+    #[test]
+    fn parses_show_for_stopped_unit() {
+        let output = "\
+Id=nginx.service
+LoadState=loaded
+ActiveState=inactive
+SubState=dead
+MainPID=0
+ActiveEnterTimestamp=
+MemoryCurrent=[not set]
+";
+        let status = parse_show(output);
+        assert_eq!(status.main_pid, None);
+        assert_eq!(status.active_since, None);
+        assert_eq!(status.memory_bytes, None);
+    }
+
+    #[test]
+    fn parses_journal_json() {
+        let output = r#"{"__REALTIME_TIMESTAMP":"1715000000123456","PRIORITY":"6","MESSAGE":"Started nginx.service."}
+{"__REALTIME_TIMESTAMP":"1715000001000000","PRIORITY":"3","MESSAGE":[104,105,255]}
+"#;
+        let entries = parse_journal(output);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].timestamp, 1715000000123);
+        assert_eq!(entries[0].priority, Some(6));
+        assert_eq!(entries[0].message, "Started nginx.service.");
+        assert_eq!(entries[1].message, "hi\u{fffd}");
+    }
+
+    #[test]
+    fn rejects_invalid_unit_names() {
+        assert!(validate_unit("nginx.service").is_ok());
+        assert!(validate_unit("getty@tty1.service").is_ok());
+        assert!(validate_unit("--all").is_err());
+        assert!(validate_unit("a; rm -rf /").is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::error::{AppError, AppResult, ErrorKind};
+
+#[derive(Serialize)]
+pub struct ApiResponse<T> {
+    code: u16,
+    data: Option<T>,
+    message: Option<String>,
+    // 以下字段只在失败响应中出现
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_code: Option<ErrorKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<String>,
+}
+
+impl<T> ApiResponse<T> {
+    /// 创建成功的响应
+    pub fn success(data: T) -> Self {
+        ApiResponse {
+            code: 200,
+            message: None,
+            data: Some(data),
+            error_code: None,
+            details: None,
+        }
+    }
+
+    /// 创建失败的响应
+    pub fn error(error: &AppError) -> Self {
+        ApiResponse {
+            code: error.kind.status(),
+            message: Some(error.message.clone()),
+            data: None,
+            error_code: Some(error.kind),
+            details: error.details.clone(),
+        }
+    }
+}
+
+/// 数据目录环境变量，便于测试和便携版指定临时目录
+pub const DATA_DIR_ENV: &str = "SSH_RUST_DATA_DIR";
+/// 数据目录命令行参数，优先级高于环境变量
+pub const DATA_DIR_FLAG: &str = "--data-dir";
+
+/// 解析 --data-dir=<目录> 形式的参数，返回其中的目录
+pub fn inline_data_dir(arg: &str) -> Option<&str> {
+    arg.strip_prefix(DATA_DIR_FLAG)?.strip_prefix('=')
+}
+
+/// 获取数据目录：命令行参数 > 环境变量 > ~/.ssh-rust
+pub fn data_dir() -> AppResult<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == DATA_DIR_FLAG {
+            if let Some(dir) = args.next() {
+                return Ok(PathBuf::from(dir));
+            }
+        } else if let Some(dir) = inline_data_dir(&arg) {
+            return Ok(PathBuf::from(dir));
+        }
+    }
+    if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    let home_dir = dirs::home_dir().ok_or_else(|| AppError::internal("无法获取用户主目录"))?;
+    Ok(home_dir.join(".ssh-rust"))
+}
+
+/// 展开路径开头的 ~，用于连接配置中的密钥、证书路径
+pub fn expand_home(path: &str) -> AppResult<PathBuf> {
+    match path.strip_prefix("~/").or_else(|| path.strip_prefix("~\\")) {
+        Some(rest) => {
+            let home_dir =
+                dirs::home_dir().ok_or_else(|| AppError::internal("无法获取用户主目录"))?;
+            Ok(home_dir.join(rest))
+        }
+        None if path == "~" => {
+            dirs::home_dir().ok_or_else(|| AppError::internal("无法获取用户主目录"))
+        }
+        None => Ok(PathBuf::from(path)),
+    }
+}
+
+/// 只含安全字符时原样输出，否则用单引号包裹，内部的单引号写作 '\''
+pub fn shell_quote(value: &str) -> String {
+    let safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c));
+    if safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
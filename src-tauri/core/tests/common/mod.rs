@@ -0,0 +1,323 @@
+//! 集成测试共用的进程内 SSH 服务器与事件收集器
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use russh::keys::key::{KeyPair, PublicKey};
+use russh::server::{self, Auth, Msg, Session};
+use russh::{Channel, ChannelId, CryptoVec, Disconnect, MethodSet};
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use ssh_rust_core::events::EventSink;
+
+/// 未配置的命令返回 127，与 shell 找不到命令时一致
+const COMMAND_NOT_FOUND: u32 = 127;
+
+#[derive(Clone)]
+pub struct CannedOutput {
+    pub stdout: String,
+    pub exit_status: u32,
+}
+
+#[derive(Default)]
+struct Shared {
+    password: Option<(String, String)>,
+    public_keys: Vec<PublicKey>,
+    commands: HashMap<String, CannedOutput>,
+    // 输出后不退出的命令，模拟 tail -f、docker logs --follow 等持续输出
+    streams: HashMap<String, String>,
+}
+
+/// 测试服务器配置：认证方式、主机密钥与命令输出
+pub struct TestServerBuilder {
+    shared: Shared,
+    host_key: Option<KeyPair>,
+}
+
+impl TestServerBuilder {
+    pub fn password(mut self, user: &str, password: &str) -> Self {
+        self.shared.password = Some((user.to_string(), password.to_string()));
+        self
+    }
+
+    pub fn public_key(mut self, key: PublicKey) -> Self {
+        self.shared.public_keys.push(key);
+        self
+    }
+
+    pub fn host_key(mut self, key: KeyPair) -> Self {
+        self.host_key = Some(key);
+        self
+    }
+
+    pub fn command(self, command: &str, stdout: &str) -> Self {
+        self.command_with_status(command, stdout, 0)
+    }
+
+    pub fn command_with_status(mut self, command: &str, stdout: &str, exit_status: u32) -> Self {
+        self.shared.commands.insert(
+            command.to_string(),
+            CannedOutput {
+                stdout: stdout.to_string(),
+                exit_status,
+            },
+        );
+        self
+    }
+
+    pub fn stream(mut self, command: &str, stdout: &str) -> Self {
+        self.shared
+            .streams
+            .insert(command.to_string(), stdout.to_string());
+        self
+    }
+
+    /// 在 127.0.0.1 的随机端口上启动服务器
+    pub async fn start(self) -> TestServer {
+        let host_key = self
+            .host_key
+            .unwrap_or_else(|| KeyPair::generate_ed25519().expect("无法生成主机密钥"));
+        let host_public_key = host_key.clone_public_key().expect("无法读取主机公钥");
+        let mut methods = MethodSet::empty();
+        if self.shared.password.is_some() {
+            methods |= MethodSet::PASSWORD;
+        }
+        if !self.shared.public_keys.is_empty() {
+            methods |= MethodSet::PUBLICKEY;
+        }
+        let config = Arc::new(server::Config {
+            methods,
+            keys: vec![host_key],
+            auth_rejection_time: Duration::from_millis(10),
+            auth_rejection_time_initial: Some(Duration::ZERO),
+            ..Default::default()
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("无法监听测试端口");
+        let addr = listener.local_addr().expect("无法获取监听地址");
+        let shared = Arc::new(self.shared);
+        let sessions: Arc<Mutex<Vec<server::Handle>>> = Arc::default();
+
+        let accept_sessions = sessions.clone();
+        let accept_task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = Handler {
+                    shared: shared.clone(),
+                };
+                let config = config.clone();
+                let sessions = accept_sessions.clone();
+                tokio::spawn(async move {
+                    if let Ok(session) = server::run_stream(config, stream, handler).await {
+                        // russh 在内部任务中运行会话，断开时需要通过 Handle 通知它
+                        sessions.lock().unwrap().push(session.handle());
+                        let _ = session.await;
+                    }
+                });
+            }
+        });
+
+        TestServer {
+            addr,
+            host_key: host_public_key,
+            accept_task,
+            sessions,
+        }
+    }
+}
+
+pub struct TestServer {
+    pub addr: SocketAddr,
+    pub host_key: PublicKey,
+    accept_task: JoinHandle<()>,
+    sessions: Arc<Mutex<Vec<server::Handle>>>,
+}
+
+impl TestServer {
+    pub fn builder() -> TestServerBuilder {
+        TestServerBuilder {
+            shared: Shared::default(),
+            host_key: None,
+        }
+    }
+
+    pub fn host(&self) -> String {
+        self.addr.ip().to_string()
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// 断开所有已建立的会话，服务器继续接受新连接，用于模拟断线重连
+    pub async fn drop_sessions(&self) {
+        let sessions: Vec<server::Handle> = self.sessions.lock().unwrap().drain(..).collect();
+        for session in sessions {
+            let _ = session
+                .disconnect(Disconnect::ByApplication, String::new(), String::new())
+                .await;
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+        // 测试结束时运行时可能已经关闭，此时会话随运行时一起结束
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            for session in self.sessions.lock().unwrap().drain(..) {
+                runtime.spawn(async move {
+                    let _ = session
+                        .disconnect(Disconnect::ByApplication, String::new(), String::new())
+                        .await;
+                });
+            }
+        }
+    }
+}
+
+struct Handler {
+    shared: Arc<Shared>,
+}
+
+#[async_trait]
+impl server::Handler for Handler {
+    type Error = russh::Error;
+
+    async fn auth_password(&mut self, user: &str, password: &str) -> Result<Auth, Self::Error> {
+        let accepted = self
+            .shared
+            .password
+            .as_ref()
+            .is_some_and(|(u, p)| u == user && p == password);
+        Ok(auth_result(accepted))
+    }
+
+    async fn auth_publickey(
+        &mut self,
+        _user: &str,
+        public_key: &PublicKey,
+    ) -> Result<Auth, Self::Error> {
+        let fingerprint = public_key.fingerprint();
+        let accepted = self
+            .shared
+            .public_keys
+            .iter()
+            .any(|k| k.fingerprint() == fingerprint);
+        Ok(auth_result(accepted))
+    }
+
+    async fn channel_open_session(
+        &mut self,
+        _channel: Channel<Msg>,
+        _session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    async fn exec_request(
+        &mut self,
+        channel: ChannelId,
+        data: &[u8],
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        let command = String::from_utf8_lossy(data);
+        if let Some(stdout) = self.shared.streams.get(command.as_ref()) {
+            session.channel_success(channel);
+            session.data(channel, CryptoVec::from(stdout.clone().into_bytes()));
+            return Ok(());
+        }
+        let output = self
+            .shared
+            .commands
+            .get(command.as_ref())
+            .cloned()
+            .unwrap_or(CannedOutput {
+                stdout: String::new(),
+                exit_status: COMMAND_NOT_FOUND,
+            });
+        session.channel_success(channel);
+        session.data(channel, CryptoVec::from(output.stdout.into_bytes()));
+        session.exit_status_request(channel, output.exit_status);
+        session.eof(channel);
+        session.close(channel);
+        Ok(())
+    }
+}
+
+fn auth_result(accepted: bool) -> Auth {
+    if accepted {
+        Auth::Accept
+    } else {
+        Auth::Reject {
+            proceed_with_methods: None,
+        }
+    }
+}
+
+/// 收集所有事件，供测试断言
+#[derive(Default)]
+pub struct CollectingSink {
+    events: Mutex<Vec<(String, Value)>>,
+}
+
+impl EventSink for CollectingSink {
+    fn emit_value(&self, event: &str, payload: Value) {
+        self.events
+            .lock()
+            .unwrap()
+            .push((event.to_string(), payload));
+    }
+}
+
+impl CollectingSink {
+    pub fn events(&self, event: &str) -> Vec<Value> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| name == event)
+            .map(|(_, payload)| payload.clone())
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.events.lock().unwrap().clear();
+    }
+
+    /// 等待第一个满足条件的事件，超时则测试失败
+    pub async fn wait_for(
+        &self,
+        event: &str,
+        timeout: Duration,
+        matches: impl Fn(&Value) -> bool,
+    ) -> Value {
+        let wait = async {
+            loop {
+                if let Some(payload) = self.events(event).into_iter().find(|p| matches(p)) {
+                    return payload;
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .unwrap_or_else(|_| panic!("等待事件 {} 超时", event))
+    }
+}
+
+/// 临时数据库，测试结束后自动删除
+pub fn temp_db() -> sled::Db {
+    sled::Config::new()
+        .temporary(true)
+        .open()
+        .expect("无法创建临时数据库")
+}
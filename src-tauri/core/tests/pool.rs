@@ -0,0 +1,227 @@
+//! 连接池是进程内的全局状态，生命周期相关的断言放在同一个测试中顺序执行
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::Value;
+use ssh_rust_core::containers::{self, ContainerRuntime, Runner};
+use ssh_rust_core::error::{AppResult, ErrorKind};
+use ssh_rust_core::ssh::{run_ssh_command, SshCommand};
+
+use common::{temp_db, CollectingSink, TestServer};
+
+const USER: &str = "tester";
+const PASSWORD: &str = "secret";
+const EVENT_TIMEOUT: Duration = Duration::from_secs(15);
+
+const FREE_OUTPUT: &str = "\
+              total        used        free      shared  buff/cache   available
+Mem:           7962        2310        3120         120        2531        5221
+Swap:          2047           0        2047
+";
+const TOP_OUTPUT: &str =
+    "%Cpu(s):  12.5 us,  3.1 sy,  0.0 ni, 84.0 id,  0.2 wa,  0.0 hi,  0.2 si,  0.0 st\n";
+const NET_DEV_OUTPUT: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:  104857     1000    0    0    0     0          0         0   104857     1000    0    0    0     0       0          0
+  eth0: 5242880     4000    0    0    0     0          0         0  1048576     3000    0    0    0     0       0          0
+";
+const DF_OUTPUT: &str = "\
+Filesystem      Size  Used Avail Use% Mounted on
+/dev/sda1        50G   20G   28G  42% /
+total            50G   20G   28G  42% -
+";
+
+const LOG_OUTPUT: &str = "GET / 200\n";
+
+fn logs_command() -> String {
+    let runner = Runner {
+        runtime: ContainerRuntime::Docker,
+        sudo: false,
+    };
+    containers::logs_command(runner, "web", Some(10), true).unwrap()
+}
+
+fn container_logs(id: usize) -> SshCommand {
+    SshCommand::ContainerLogs {
+        id,
+        container: "web".to_string(),
+        runtime: Some(ContainerRuntime::Docker),
+        sudo: false,
+        tail: Some(10),
+        follow: true,
+    }
+}
+
+async fn start_server() -> TestServer {
+    TestServer::builder()
+        .password(USER, PASSWORD)
+        .command("echo hi", "hi\n")
+        .command_with_status("exit 3", "", 3)
+        .command(
+            "uptime",
+            " 10:00:00 up 3 days,  4:05,  1 user,  load average: 0.10, 0.20, 0.30\n",
+        )
+        .command("free -m", FREE_OUTPUT)
+        .command("top -bn1 | grep 'Cpu(s)'", TOP_OUTPUT)
+        .command("cat /proc/net/dev", NET_DEV_OUTPUT)
+        .command("df -h --total", DF_OUTPUT)
+        .stream(&logs_command(), LOG_OUTPUT)
+        .start()
+        .await
+}
+
+async fn run(command: SshCommand, sink: &Arc<CollectingSink>, db: &sled::Db) -> AppResult<Value> {
+    run_ssh_command(command, sink.clone(), db).await
+}
+
+fn open(id: usize, server: &TestServer) -> SshCommand {
+    SshCommand::OpenConnection {
+        id,
+        username: USER.to_string(),
+        password: PASSWORD.to_string(),
+        host: server.host(),
+        port: server.port(),
+    }
+}
+
+fn query(id: usize, query: &str) -> SshCommand {
+    SshCommand::ExecuteQuery {
+        id,
+        query: query.to_string(),
+        sudo: false,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pool_lifecycle() {
+    let server = start_server().await;
+    let db = temp_db();
+    let sink = Arc::new(CollectingSink::default());
+
+    // 打开连接并执行命令
+    let opened = run(open(1, &server), &sink, &db).await.unwrap();
+    assert_eq!(opened["data"]["id"], 1);
+    sink.wait_for("connection-state-1", EVENT_TIMEOUT, |p| {
+        p["state"] == "Connected"
+    })
+    .await;
+    let output = run(query(1, "echo hi"), &sink, &db).await.unwrap();
+    assert_eq!(output["data"], "hi\n");
+    let error = run(query(1, "exit 3"), &sink, &db).await.unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Command);
+
+    // 监控任务解析的服务器信息
+    let memory = sink
+        .wait_for("server-memory-update-1", EVENT_TIMEOUT, |_| true)
+        .await;
+    assert_eq!(memory["memory_usage"]["total"], 7962);
+    assert_eq!(memory["memory_usage"]["used"], 2310);
+    assert_eq!(memory["memory_usage"]["free"], 3120);
+    let uptime = sink
+        .wait_for("server-uptime-update-1", EVENT_TIMEOUT, |_| true)
+        .await;
+    assert_eq!(uptime["uptime"], "10:00:00 up 3 days");
+    let disk = sink
+        .wait_for("server-disk-update-1", EVENT_TIMEOUT, |_| true)
+        .await;
+    assert_eq!(disk["disk_usage"]["total"], "50G");
+    assert_eq!(disk["disk_usage"]["use_percentage"], "42%");
+    let cpu = sink
+        .wait_for("server-cpu-update-1", EVENT_TIMEOUT, |_| true)
+        .await;
+    assert_eq!(cpu["cpu_usage"]["user"], 12.5);
+    assert_eq!(cpu["cpu_usage"]["system"], 3.1_f32 as f64);
+
+    let info = run(query(1, "baseinfo"), &sink, &db).await.unwrap();
+    assert_eq!(info["data"]["memory_usage"]["total"], 7962);
+    // 两次采样相同，速率为 0
+    assert_eq!(info["data"]["network_usage"]["rx_speed"], 0.0);
+
+    // 重新启动监控
+    run(SshCommand::RetryInfoQuery(1), &sink, &db)
+        .await
+        .unwrap();
+    let error = run(SshCommand::RetryInfoQuery(99), &sink, &db)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind, ErrorKind::NotFound);
+
+    // 持续输出的日志流
+    let opened = run(container_logs(1), &sink, &db).await.unwrap();
+    let stream_id = opened["data"]["stream_id"].as_u64().unwrap();
+    let output = sink
+        .wait_for(&format!("stream-output-{stream_id}"), EVENT_TIMEOUT, |_| {
+            true
+        })
+        .await;
+    assert_eq!(output["data"], LOG_OUTPUT);
+
+    // 断线后自动重连
+    sink.clear();
+    server.drop_sessions().await;
+    sink.wait_for("connection-state-1", EVENT_TIMEOUT, |p| {
+        p["state"] == "Reconnecting"
+    })
+    .await;
+    sink.wait_for("connection-state-1", EVENT_TIMEOUT, |p| {
+        p["state"] == "Connected"
+    })
+    .await;
+    let output = run(query(1, "echo hi"), &sink, &db).await.unwrap();
+    assert_eq!(output["data"], "hi\n");
+
+    // 日志流在新连接上以原来的 ID 重新打开
+    let output = sink
+        .wait_for(&format!("stream-output-{stream_id}"), EVENT_TIMEOUT, |_| {
+            true
+        })
+        .await;
+    assert_eq!(output["data"], LOG_OUTPUT);
+    assert!(sink
+        .events(&format!("stream-closed-{stream_id}"))
+        .is_empty());
+    run(SshCommand::StopStream(stream_id), &sink, &db)
+        .await
+        .unwrap();
+
+    // 关闭单个连接
+    run(SshCommand::CloseConnection(1), &sink, &db)
+        .await
+        .unwrap();
+    let error = run(query(1, "echo hi"), &sink, &db).await.unwrap_err();
+    assert_eq!(error.kind, ErrorKind::NotFound);
+
+    // 关闭全部连接
+    run(open(2, &server), &sink, &db).await.unwrap();
+    run(open(3, &server), &sink, &db).await.unwrap();
+    run(SshCommand::CloseAllConnections, &sink, &db)
+        .await
+        .unwrap();
+    for id in [2, 3] {
+        let error = run(query(id, "echo hi"), &sink, &db).await.unwrap_err();
+        assert_eq!(error.kind, ErrorKind::NotFound);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn open_connection_with_wrong_password_reports_failure() {
+    let server = start_server().await;
+    let db = temp_db();
+    let sink = Arc::new(CollectingSink::default());
+
+    let command = SshCommand::OpenConnection {
+        id: 100,
+        username: USER.to_string(),
+        password: "wrong".to_string(),
+        host: server.host(),
+        port: server.port(),
+    };
+    let error = run(command, &sink, &db).await.unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Auth);
+    let failed = sink.events("connection-state-100");
+    assert_eq!(failed.last().unwrap()["state"], "Failed");
+}
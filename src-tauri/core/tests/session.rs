@@ -0,0 +1,201 @@
+mod common;
+
+use russh::keys::key::KeyPair;
+use ssh_rust_core::algorithms::AlgorithmPreferences;
+use ssh_rust_core::error::ErrorKind;
+use ssh_rust_core::hostkeys::{self, HostKeyVerifier};
+use ssh_rust_core::session::{ConnectParams, Credentials, Session};
+use ssh_rust_core::settings::Timeouts;
+
+use common::{temp_db, TestServer};
+
+const USER: &str = "tester";
+const PASSWORD: &str = "secret";
+
+fn params(server: &TestServer, db: &sled::Db, credentials: Credentials) -> ConnectParams {
+    ConnectParams {
+        host: server.host(),
+        port: server.port(),
+        user: USER.to_string(),
+        credentials,
+        timeouts: Timeouts::default(),
+        algorithms: AlgorithmPreferences::default(),
+        host_keys: HostKeyVerifier {
+            db: db.clone(),
+            host: server.host(),
+            port: server.port(),
+            pinned: None,
+        },
+    }
+}
+
+fn password(server: &TestServer, db: &sled::Db, password: &str) -> ConnectParams {
+    params(server, db, Credentials::Password(password.to_string()))
+}
+
+#[tokio::test]
+async fn connect_with_password_and_call() {
+    let server = TestServer::builder()
+        .password(USER, PASSWORD)
+        .command("echo hello", "hello\n")
+        .start()
+        .await;
+
+    let db = temp_db();
+    let mut session = Session::open(password(&server, &db, PASSWORD))
+        .await
+        .expect("密码认证应当成功");
+    assert_eq!(session.call("echo hello").await.unwrap(), "hello\n");
+    session.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn wrong_password_is_auth_error() {
+    let server = TestServer::builder().password(USER, PASSWORD).start().await;
+
+    let db = temp_db();
+    let error = Session::open(password(&server, &db, "wrong"))
+        .await
+        .err()
+        .expect("错误的密码应当被拒绝");
+    assert_eq!(error.kind, ErrorKind::Auth);
+}
+
+#[tokio::test]
+async fn connect_with_private_key() {
+    let key = KeyPair::generate_ed25519().expect("无法生成密钥");
+    let public_key = key.clone_public_key().unwrap();
+    let key_file = tempfile::NamedTempFile::new().unwrap();
+    russh::keys::encode_pkcs8_pem(&key, key_file.as_file()).unwrap();
+
+    let server = TestServer::builder()
+        .public_key(public_key)
+        .command("whoami", "tester\n")
+        .start()
+        .await;
+
+    let db = temp_db();
+    let credentials = Credentials::PrivateKey(key_file.path().to_path_buf());
+    let session = Session::open(params(&server, &db, credentials))
+        .await
+        .expect("公钥认证应当成功");
+    assert_eq!(session.call("whoami").await.unwrap(), "tester\n");
+}
+
+#[tokio::test]
+async fn call_reports_exit_status() {
+    let server = TestServer::builder()
+        .password(USER, PASSWORD)
+        .command_with_status("false", "", 1)
+        .start()
+        .await;
+    let db = temp_db();
+    let session = Session::open(password(&server, &db, PASSWORD))
+        .await
+        .unwrap();
+
+    let (output, status) = session.call_with_status("false").await.unwrap();
+    assert_eq!(output, "");
+    assert_eq!(status, Some(1));
+
+    let error = session.call("false").await.unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Command);
+
+    // 未配置的命令按 shell 的习惯返回 127
+    let (_, status) = session.call_with_status("missing").await.unwrap();
+    assert_eq!(status, Some(127));
+}
+
+#[tokio::test]
+async fn records_negotiated_algorithms() {
+    let server = TestServer::builder().password(USER, PASSWORD).start().await;
+    let db = temp_db();
+    let mut params = password(&server, &db, PASSWORD);
+    params.algorithms.cipher = vec!["aes256-ctr".to_string()];
+    params.algorithms.mac = vec!["hmac-sha2-256".to_string()];
+    let session = Session::open(params).await.unwrap();
+
+    let negotiated = session.negotiated.as_ref().expect("应当记录协商的算法");
+    assert!(negotiated.server_banner.starts_with("SSH-2.0-"));
+    assert_eq!(negotiated.host_key, "ssh-ed25519");
+    assert_eq!(negotiated.cipher_client_to_server, "aes256-ctr");
+    assert_eq!(negotiated.cipher_server_to_client, "aes256-ctr");
+    assert_eq!(
+        negotiated.mac_client_to_server.as_deref(),
+        Some("hmac-sha2-256")
+    );
+}
+
+#[tokio::test]
+async fn host_key_is_trusted_on_first_use_and_pinned_key_enforced() {
+    let server = TestServer::builder().password(USER, PASSWORD).start().await;
+    let db = temp_db();
+    let pinned_params = |pinned: Option<String>| {
+        let mut params = password(&server, &db, PASSWORD);
+        params.host_keys.pinned = pinned;
+        params
+    };
+
+    Session::open(pinned_params(None))
+        .await
+        .expect("首次连接应当信任主机密钥");
+    let keys = hostkeys::list_host_keys(&db, &server.host(), server.port(), None).unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].fingerprint, hostkeys::fingerprint(&server.host_key));
+
+    let pinned = Some("SHA256:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string());
+    let error = Session::open(pinned_params(pinned.clone()))
+        .await
+        .err()
+        .expect("固定密钥不匹配时应当拒绝");
+    assert_eq!(error.kind, ErrorKind::HostKey);
+
+    // 不在信任列表中的固定指纹也要列出
+    let keys =
+        hostkeys::list_host_keys(&db, &server.host(), server.port(), pinned.as_deref()).unwrap();
+    assert_eq!(keys.len(), 2);
+    assert!(keys[1].pinned && !keys[1].trusted);
+    assert_eq!(Some(&keys[1].fingerprint), pinned.as_ref());
+}
+
+#[tokio::test]
+async fn host_key_is_replaced_only_after_confirming_fingerprint() {
+    let server = TestServer::builder().start().await;
+    let db = temp_db();
+    let (host, port) = (server.host(), server.port());
+    let timeouts = Timeouts::default();
+    let algorithms = AlgorithmPreferences::default();
+
+    let scanned = hostkeys::scan_host_key(&db, &host, port, &timeouts, &algorithms)
+        .await
+        .unwrap();
+    assert_eq!(scanned.fingerprint, hostkeys::fingerprint(&server.host_key));
+    assert!(!scanned.trusted);
+    assert!(hostkeys::list_host_keys(&db, &host, port, None)
+        .unwrap()
+        .is_empty());
+
+    let stale = "SHA256:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+    let error = hostkeys::replace_host_key(&db, &host, port, stale, &timeouts, &algorithms)
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind, ErrorKind::HostKey);
+    assert!(hostkeys::list_host_keys(&db, &host, port, None)
+        .unwrap()
+        .is_empty());
+
+    let replaced = hostkeys::replace_host_key(
+        &db,
+        &host,
+        port,
+        &scanned.fingerprint,
+        &timeouts,
+        &algorithms,
+    )
+    .await
+    .unwrap();
+    assert!(replaced.trusted);
+    let keys = hostkeys::list_host_keys(&db, &host, port, None).unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].fingerprint, scanned.fingerprint);
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use sled::Db;
+use ssh_rust_core::error::AppResult;
+use ssh_rust_core::events::EventSink;
+use ssh_rust_core::sled::{run_db_operation, DbOperation};
+use ssh_rust_core::ssh::{run_ssh_command, SshCommand};
+use tauri::{Manager, Runtime, State, Window};
+use window_shadows::set_shadow;
+
+/// 把核心库的事件转发给前端窗口
+pub struct WindowSink(pub Window);
+
+impl EventSink for WindowSink {
+    fn emit_value(&self, event: &str, payload: serde_json::Value) {
+        if let Err(e) = self.0.emit(event, payload) {
+            eprintln!("发送事件 {} 失败: {}", event, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn handle_db_operation(
+    operation: DbOperation,
+    db: State<'_, Db>,
+) -> AppResult<serde_json::Value> {
+    run_db_operation(operation, db.inner()).await
+}
+
+#[tauri::command]
+pub async fn handle_ssh_command(
+    command: SshCommand,
+    window: Window,
+    db: State<'_, Db>,
+) -> AppResult<serde_json::Value> {
+    run_ssh_command(command, Arc::new(WindowSink(window)), db.inner()).await
+}
+
+pub fn set_window_shadow<R: Runtime>(app: &tauri::App<R>) {
+    let window = app.get_window("main").unwrap();
+    set_shadow(&window, true).unwrap();
+}
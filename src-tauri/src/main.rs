@@ -1,23 +1,33 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use utils::set_window_shadow;
+use commands::set_window_shadow;
+use ssh_rust_core::{backup, sled};
+use tauri::{Manager, RunEvent};
 
-mod session;
-mod sled;
-mod ssh;
-mod utils;
+mod commands;
 
 fn main() {
     tauri::Builder::default()
         .setup(|app| {
             set_window_shadow(app);
+            let db = sled::open_db()?;
+            tauri::async_runtime::spawn(backup::run_daily_snapshots(db.clone()));
+            app.manage(db);
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
-            sled::handle_db_operation,
-            ssh::handle_ssh_command
+            commands::handle_db_operation,
+            commands::handle_ssh_command
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let RunEvent::Exit = event {
+                // 退出前把数据库缓冲写入磁盘
+                if let Err(e) = app_handle.state::<::sled::Db>().flush() {
+                    eprintln!("刷新数据库失败: {}", e);
+                }
+            }
+        });
 }
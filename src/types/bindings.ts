@@ -0,0 +1,152 @@
+// 此文件由 src-tauri/core/src/bindings.rs 根据 Rust 类型生成，请勿手动修改。
+// 修改 Rust 类型后运行 `UPDATE_BINDINGS=1 cargo test` 重新生成。
+
+export type SshCommand = { "OpenConnection": { id: number, username: string, password: string, host: string, port: number, } } | { "CloseConnection": number } | { "ExecuteQuery": { id: number, query: string, sudo: boolean, } } | { "RetryInfoQuery": number } | "CloseAllConnections" | { "Diagnostics": number } | { "OpenShell": { id: number, cols: number, rows: number, record: boolean, record_input: boolean, } } | { "ShellInput": { id: number, data: string, } } | { "ResizeShell": { id: number, cols: number, rows: number, } } | { "CloseShell": number } | "ListRecordings" | { "DeleteRecording": string } | { "ExportRecording": { name: string, path: string, } } | { "ReplayRecording": { name: string, speed: number, max_idle: number | null, } } | { "StopReplay": string } | { "RunSnippet": { snippet_id: number, ids: Array<number>, values: Record<string, string>, sudo: boolean, } } | { "SetSudoPassword": { id: number, password: string | null, } } | { "WriteFile": { id: number, path: string, contents: string, sudo: boolean, } } | { "OpenFile": { id: number, path: string, sudo: boolean, } } | { "SaveFile": { id: number, path: string, contents: string, expected: FileVersion | null, force: boolean, backup: BackupTarget, sudo: boolean, } } | { "ListUnits": { id: number, all: boolean, } } | { "UnitAction": { id: number, unit: string, action: UnitAction, sudo: boolean, } } | { "UnitStatus": { id: number, unit: string, journal_lines: number | null, } } | { "ListContainers": { id: number, runtime: ContainerRuntime | null, sudo: boolean, stats: boolean, } } | { "ContainerAction": { id: number, container: string, action: ContainerAction, runtime: ContainerRuntime | null, sudo: boolean, } } | { "ContainerLogs": { id: number, container: string, runtime: ContainerRuntime | null, sudo: boolean, tail: number | null, follow: boolean, } } | { "ContainerShell": { id: number, container: string, runtime: ContainerRuntime | null, sudo: boolean, shell: string | null, cols: number, rows: number, } } | { "TailLogs": { id: number, options: LogTailOptions, } } | { "LogBuffer": number } | { "StopStream": number };
+
+export type DbOperation = { "Insert": SshConnection } | { "Update": SshConnection } | { "Delete": number } | "SelectAll" | { "Search": { query: string, limit: number | null, } } | { "Export": { path: string, format: BundleFormat, ids: Array<number> | null, include_secrets: boolean, passphrase: string | null, } } | { "Import": { path: string, strategy: MergeStrategy, passphrase: string | null, } } | { "ExportSshConfig": { ids: Array<number> | null, target: SshConfigTarget, } } | "ListQuarantined" | "CreateBackup" | "ListBackups" | { "RestoreBackup": string } | "GetSettings" | { "UpdateSettings": Settings } | { "CertificateInfo": number } | { "ListHostKeys": number } | { "RemoveHostKey": { id: number, fingerprint: string, } } | { "ScanHostKey": number } | { "ReplaceHostKey": { id: number, fingerprint: string, } } | { "PinHostKey": { id: number, fingerprint: string | null, } } | { "SearchHistory": HistoryQuery } | { "ClearHistory": number | null } | { "ImportSshConfig": string | null } | "ListSnippets" | { "SaveSnippet": Snippet } | { "DeleteSnippet": number } | { "GetHostProfile": number };
+
+export type SshConnection = { id: number | null, name: string, host: string, port: number, username: string | null, password: string | null, identity_file: string | null, certificate_file: string | null, proxy_jump: string | null, local_forwards: Array<LocalForward>, tags: Array<string>, notes: string | null, timeouts: TimeoutOverrides, algorithms: AlgorithmPreferences, pinned_host_key: string | null, created_at: number | null, updated_at: number | null, last_connected_at: number | null, connect_count: number, };
+
+export type LocalForward = { bind_address: string | null, bind_port: number, remote_host: string, remote_port: number, };
+
+export type TimeoutOverrides = { connect_timeout: number | null, auth_timeout: number | null, keepalive_interval: number | null, keepalive_max: number | null, inactivity_timeout: number | null, };
+
+export type AlgorithmPreferences = { kex: Array<string>, host_key: Array<string>, cipher: Array<string>, mac: Array<string>, };
+
+export type Settings = { timeouts: Timeouts, history: HistorySettings, };
+
+export type Timeouts = { connect_timeout: number, auth_timeout: number, keepalive_interval: number, keepalive_max: number, inactivity_timeout: number, };
+
+export type HistorySettings = { enabled: boolean, retention_days: number, max_entries: number, exclude_patterns: Array<string>, };
+
+export type HistoryQuery = { connection_id: number | null, query: string | null, mode: HistoryMatch | null, host: string | null, since: number | null, until: number | null, limit: number | null, };
+
+export type HistoryMatch = "Prefix" | "Fuzzy";
+
+export type Snippet = { id: number | null, name: string, description: string | null, tags: Array<string>, template: string, placeholders: Array<Placeholder>, created_at: number | null, updated_at: number | null, };
+
+export type Placeholder = { name: string, kind: PlaceholderKind, default: string | null, };
+
+export type PlaceholderKind = "String" | "Int" | "Bool";
+
+export type BundleFormat = "Json" | "Toml";
+
+export type MergeStrategy = "Skip" | "Overwrite" | "Duplicate";
+
+export type SshConfigTarget = { "File": string } | "ManagedInclude";
+
+export type UnitAction = "Start" | "Stop" | "Restart" | "Enable" | "Disable";
+
+export type ContainerRuntime = "Docker" | "Podman";
+
+export type ContainerAction = "Start" | "Stop" | "Restart" | "Remove";
+
+export type LogTailOptions = { sources: Array<LogSource>, include: Array<string>, exclude: Array<string>, buffer_lines: number | null, initial_lines: number | null, sudo: boolean, };
+
+export type LogSource = { "File": string } | { "Unit": string };
+
+export type FileVersion = { size: number, mtime: number, hash: string, };
+
+export type BackupTarget = "None" | "Remote" | "Local";
+
+export type ErrorKind = "DB" | "NETWORK" | "TIMEOUT" | "AUTH" | "HOST_KEY" | "CHANNEL" | "COMMAND" | "SFTP" | "PARSE" | "NOT_FOUND" | "INVALID_INPUT" | "IO" | "INTERNAL" | "SUDO_REQUIRED" | "CONFLICT";
+
+export type ConnectionOpened = { id: number, message: string, };
+
+export type ServerInfo = { uptime: string, memory_usage: MemoryUsage, cpu_usage: CpuUsage, network_usage: NetworkUsage, disk_usage: DiskTotals, upload_speed: number, download_speed: number, };
+
+export type DiskTotals = { used: number, available: number, };
+
+export type ExportReport = { path: string, exported: number, snippets: number, encrypted: boolean, };
+
+export type ImportReport = { imported: number, overwritten: number, skipped: number, snippets_imported: number, snippets_overwritten: number, snippets_skipped: number, conflicts: Array<string>, };
+
+export type SshConfigExportReport = { path: string, hosts: Array<string>, include_added: boolean, };
+
+export type SshConfigImportReport = { path: string, imported: Array<string>, skipped: Array<string>, };
+
+export type QuarantinedRecord = { key: string, raw: string, error: string, quarantined_at: number | null, };
+
+export type BackupInfo = { name: string, size: number, created_at: number | null, };
+
+export type RestoreReport = { restored_from: string, connections: number, previous_snapshot: string, };
+
+export type NegotiatedAlgorithms = { server_banner: string, kex: string, host_key: string, cipher_client_to_server: string, cipher_server_to_client: string, mac_client_to_server: string | null, mac_server_to_client: string | null, };
+
+export type CertificateInfo = { path: string, key_id: string, principals: Array<string>, valid_after: number, valid_before: number, expires_in: number, };
+
+export type HostKeyInfo = { algorithm: string | null, fingerprint: string, randomart: string, first_seen: number | null, pinned: boolean, trusted: boolean, };
+
+export type ShellOpened = { id: number, recording: string | null, };
+
+export type RecordingInfo = { name: string, size: number, connection_id: number | null, created_at: number | null, };
+
+export type CastHeader = { version: number, width: number, height: number, timestamp: number | null, title: string | null, };
+
+export type HistoryEntry = { connection_id: number, host: string, command: string, source: HistorySource, exit_code: number | null, duration_ms: number, executed_at: number, };
+
+export type HistorySource = "Query" | "Shell";
+
+export type SnippetRunResult = { id: number, command: string, output: string | null, error: string | null, };
+
+export type HostProfile = { os: OsFamily, distro: string | null, distro_name: string | null, distro_version: string | null, kernel: string | null, arch: string | null, init_system: InitSystem, shell: string | null, busybox: boolean, tools: Array<string>, detected_at: number | null, };
+
+export type OsFamily = "Linux" | "FreeBsd" | "Darwin" | "Unknown";
+
+export type InitSystem = "Systemd" | "OpenRc" | "Procd" | "SysV" | "BsdRc" | "Launchd" | "Unknown";
+
+export type SystemdUnit = { unit: string, load: string, active: string, sub: string, description: string, };
+
+export type UnitStatus = { unit: string, description: string, load_state: string, active_state: string, sub_state: string, unit_file_state: string | null, main_pid: number | null, active_since: string | null, memory_bytes: number | null, fragment_path: string | null, status_text: string, journal: Array<JournalEntry>, };
+
+export type JournalEntry = { timestamp: number, priority: number | null, message: string, };
+
+export type Container = { id: string, name: string, image: string, command: string, state: string, status: string, ports: Array<string>, created_at: string, stats: ContainerStats | null, };
+
+export type ContainerStats = { cpu_percent: number, memory_usage: string, memory_percent: number, net_io: string, block_io: string, pids: number | null, };
+
+export type StreamOpened = { stream_id: number, id: number, command: string, };
+
+export type LogLine = { source: number | null, timestamp: number | null, severity: Severity | null, text: string, };
+
+export type Severity = "Error" | "Warning" | "Info" | "Debug";
+
+export type RemoteFile = { path: string, contents: string, version: FileVersion, mode: string, owner: string, group: string, };
+
+export type FileSaved = { path: string, version: FileVersion, backup: string | null, };
+
+export type UptimeUpdate = { uptime: string, };
+
+export type MemoryUpdate = { memory_usage: MemoryUsage, };
+
+export type MemoryUsage = { total: number, used: number, free: number, };
+
+export type CpuUpdate = { cpu_usage: CpuUsage, };
+
+export type CpuUsage = { user: number, system: number, total: number, };
+
+export type NetworkUpdate = { network_usage: NetworkUsage, };
+
+export type NetworkUsage = { rx_speed: number, tx_speed: number, };
+
+export type DiskUpdate = { disk_usage: DiskUsage, };
+
+export type DiskUsage = { total: string, used: string, available: string, use_percentage: string, };
+
+export type ConnectionStateUpdate = { id: number, state: ConnectionState, attempt: number, message: string | null, };
+
+export type ConnectionState = "Connecting" | "Connected" | "Reconnecting" | "Failed";
+
+export type CertificateExpiring = { id: number, certificate: CertificateInfo, };
+
+export type ShellOutput = { id: number, data: string, };
+
+export type ShellClosed = { id: number, exit_status: number | null, };
+
+export type StreamOutput = { stream_id: number, id: number, data: string, };
+
+export type StreamClosed = { stream_id: number, id: number, exit_status: number | null, };
+
+export type LogBatch = { stream_id: number, id: number, lines: Array<LogLine>, };
+
+export type ReplayFrame = { name: string, time: number, code: string, data: string, };
@@ -1,10 +1,13 @@
-interface Connection {
+// 命令、参数、响应与事件类型由 Rust 定义生成，见 ./bindings.ts
+type SshConnection = import('./bindings').SshConnection
+type LocalForward = import('./bindings').LocalForward
+type ServerInfo = import('./bindings').ServerInfo
+type ConnectionOpened = import('./bindings').ConnectionOpened
+type ConnectionStateUpdate = import('./bindings').ConnectionStateUpdate
+type CertificateExpiring = import('./bindings').CertificateExpiring
+
+interface Connection extends SshConnection {
     id: number
-    name: string
-    host: string
-    port: number
-    username?: string
-    password?: string
     connected?: boolean
 }
 
@@ -36,34 +39,21 @@ interface Response<T> {
     data: T
 }
 
-// SSH 命令类型
-type SshCommandResult<T> = Response<T>
+// 命令失败时 invoke 抛出的错误，error_code 为稳定的机器可读错误码
+type ErrorCode = import('./bindings').ErrorKind
 
-type OpenConnectionCommand = {
-    OpenConnection: {
-        id: number
-        username?: string
-        password?: string
-        host: string
-        port: number
-    }
-}
-
-type CloseConnectionCommand = {
-    CloseConnection: number
+interface ApiError {
+    code: number
+    message: string
+    data: null
+    error_code: ErrorCode
+    details?: string
 }
 
-type ExecuteQueryCommand = {
-    ExecuteQuery: {
-        id: number
-        query: string
-    }
-}
+// SSH 命令类型
+type SshCommandResult<T> = Response<T>
 
-type SshCommand =
-    | OpenConnectionCommand
-    | CloseConnectionCommand
-    | ExecuteQueryCommand
+type SshCommand = import('./bindings').SshCommand
 
 // 添加一个辅助函数类型
 type InvokeSshCommand = (command: SshCommand) => Promise<SshCommandResult>
@@ -71,16 +61,7 @@ type InvokeSshCommand = (command: SshCommand) => Promise<SshCommandResult>
 // 数据库操作类型
 type DbOperationResult<T> = Response<T>
 
-type InsertOperation = { Insert: Connection }
-type UpdateOperation = { Update: Connection }
-type DeleteOperation = { Delete: number }
-type SelectAllOperation = 'SelectAll'
-
-type DbOperation =
-    | InsertOperation
-    | UpdateOperation
-    | DeleteOperation
-    | SelectAllOperation
+type DbOperation = import('./bindings').DbOperation
 
 // 添加一个辅助函数类型
 type InvokeDbOperation = (operation: DbOperation) => Promise<DbOperationResult>